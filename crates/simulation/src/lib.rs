@@ -14,13 +14,33 @@ impl Default for SimulationRate {
     }
 }
 
+/// Number of simulation steps run since the world was created.
+#[derive(Resource, Default, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimulationTick(pub u64);
+
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<SimulationRate>()
+            .init_resource::<SimulationTick>()
+            .register_type::<SimulationTick>()
+            .add_systems(Update, tick_simulation)
             .add_plugins(temperature::ThermalPlugin);
     }
 }
 
+/// Advances the simulation clock; simulation systems run when [`SimulationRate`] just finished.
+pub fn tick_simulation(
+    mut simulation_rate: ResMut<SimulationRate>,
+    mut simulation_tick: ResMut<SimulationTick>,
+    time: Res<Time>,
+) {
+    simulation_rate.rate.tick(time.delta());
+    if simulation_rate.rate.just_finished() {
+        simulation_tick.0 += 1;
+    }
+}
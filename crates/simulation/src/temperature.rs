@@ -1,12 +1,13 @@
 use std::time::Duration;
 
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_ecs_tilemap::tiles::TileStorage;
 use common::resources::MapSize;
 
-use crate::SimulationRate;
+use crate::{tick_simulation, SimulationRate};
 
-#[derive(Component, Default, Reflect, Debug, PartialEq, PartialOrd)]
+#[derive(Component, Default, Reflect, Clone, Debug, PartialEq, PartialOrd)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Temperature {
@@ -23,7 +24,7 @@ impl Temperature {
     }
 }
 
-#[derive(Component, Default, Reflect, Debug, PartialEq, PartialOrd)]
+#[derive(Component, Default, Reflect, Clone, Debug, PartialEq, PartialOrd)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HeatCell {
@@ -31,7 +32,7 @@ pub struct HeatCell {
     pub conductivity: ThermalConductivity,
 }
 
-#[derive(Component, Reflect, Debug, PartialEq, PartialOrd)]
+#[derive(Component, Reflect, Clone, Debug, PartialEq, PartialOrd)]
 #[reflect(Component)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ThermalConductivity {
@@ -89,6 +90,25 @@ pub fn calculate_heat_transfer(
     )
 }
 
+/// Moves heat between a body at `body` °C and its surroundings at `ambient` °C, with heat capacities
+/// in kJ/K, over `conductance` kJ/K of contact, i.e. the conductance times the time step.
+///
+/// # Returns
+/// Tuple of (new_body, new_ambient), which at most meet in the middle
+pub fn exchange_heat(body: f32, body_capacity: f32, ambient: f32, ambient_capacity: f32, conductance: f32) -> (f32, f32) {
+    if body_capacity <= 0.0 || ambient_capacity <= 0.0 {
+        return (body, ambient);
+    }
+    let equilibrium = (body * body_capacity + ambient * ambient_capacity) / (body_capacity + ambient_capacity);
+    // In kJ, from the body to its surroundings
+    let mut energy = conductance * (body - ambient);
+    let max_energy = (body - equilibrium) * body_capacity;
+    if energy.abs() > max_energy.abs() {
+        energy = max_energy;
+    }
+    (body - energy / body_capacity, ambient + energy / ambient_capacity)
+}
+
 pub struct ThermalPlugin;
 
 impl Plugin for ThermalPlugin {
@@ -97,45 +117,53 @@ impl Plugin for ThermalPlugin {
             .register_type::<Temperature>()
             .register_type::<HeatCell>()
             .register_type::<ThermalConductivity>()
-            .add_systems(Update, thermal_conduction);
+            .add_systems(Update, thermal_conduction.after(tick_simulation));
     }
 }
 
 fn thermal_conduction(
     mut tile_heat_query: Query<(&mut HeatCell, &bevy_ecs_tilemap::tiles::TilePos, &Parent)>,
     layer_query: Query<&TileStorage>,
-    mut simulation_rate: ResMut<SimulationRate>,
+    simulation_rate: Res<SimulationRate>,
     size: Res<MapSize>,
     time: Res<Time>,
 ) {
-    simulation_rate.rate.tick(time.delta());
     if simulation_rate.rate.just_finished() {
         use bevy_ecs_tilemap::helpers::square_grid::neighbors::Neighbors;
         let map_size = bevy_ecs_tilemap::map::TilemapSize::from(size.into_inner().0);
         
-        // First pass: collect all temperature changes
-        let mut temp_accumulators = vec![0.0; (map_size.x * map_size.y) as usize];
+        // First pass: collect all temperature changes, per layer as the layers share positions
+        let mut temp_accumulators: HashMap<Entity, Vec<f32>> = HashMap::new();
         
         for (heat_cell, tile_pos, parent) in tile_heat_query.iter() {
             if let Ok(tile_storage) = layer_query.get(parent.get()) {
+                let accumulator = temp_accumulators
+                    .entry(parent.get())
+                    .or_insert_with(|| vec![0.0; (map_size.x * map_size.y) as usize]);
                 let neighbors = Neighbors::get_square_neighboring_positions(tile_pos, &map_size, false)
                     .entities(tile_storage);
                 
                 for neighbor in neighbors.iter() {
                     if let Ok((neighbor_cell, neighbor_pos, _)) = tile_heat_query.get(*neighbor) {
                         let (new_temp1, new_temp2) = calculate_heat_transfer(heat_cell, neighbor_cell, time.delta(), 0.5);
-                        temp_accumulators[tile_pos.to_index(&map_size)] += new_temp1;
+                        accumulator[tile_pos.to_index(&map_size)] += new_temp1;
                         // Update neighbor changes
-                        temp_accumulators[neighbor_pos.to_index(&map_size)] += new_temp2;
+                        accumulator[neighbor_pos.to_index(&map_size)] += new_temp2;
                     }
                 }
             }
         }
         
         // Second pass: apply all temperature changes
-        for (mut heat_cell, tile_pos, _) in tile_heat_query.iter_mut() {
+        for (mut heat_cell, tile_pos, parent) in tile_heat_query.iter_mut() {
+            let Some(accumulator) = temp_accumulators.get(&parent.get()) else {
+                continue;
+            };
             let index = tile_pos.to_index(&map_size);
-            heat_cell.temperature.value += temp_accumulators[index];
+            // Only touch tiles that changed, so `Changed<HeatCell>` stays meaningful for the overlays
+            if accumulator[index] != 0.0 {
+                heat_cell.temperature.value += accumulator[index];
+            }
         }
     }
 }
@@ -197,6 +225,15 @@ mod tests {
     }
 
     fn setup_test_map(mut commands: Commands) {
+        spawn_test_map(&mut commands, 100.0);
+    }
+
+    fn setup_test_layers(mut commands: Commands) {
+        spawn_test_map(&mut commands, 100.0);
+        spawn_test_map(&mut commands, 20.0);
+    }
+
+    fn spawn_test_map(commands: &mut Commands, center_temperature: f32) {
         // Create a 3x3 tilemap
         let map_size = TilemapSize { x: 3, y: 3 };
         let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
@@ -214,7 +251,7 @@ mod tests {
                 
                 // Set center tile to hot, others to cool
                 if x == 1 && y == 1 {
-                    heat_cell.temperature.value = 100.0; // Center tile is hot
+                    heat_cell.temperature.value = center_temperature; // Center tile is hot
                 } else {
                     heat_cell.temperature.value = 20.0; // Surrounding tiles are cool
                 }
@@ -303,5 +340,54 @@ mod tests {
             }
         }
     }
-}
 
+    #[test]
+    fn test_thermal_conduction_keeps_layers_apart() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(SimulationPlugin);
+        app.insert_resource(MapSize(UVec2::new(3, 3)));
+        app.insert_resource(SimulationRate { rate: Timer::new(Duration::from_millis(1), TimerMode::Repeating) });
+        app.add_systems(Startup, setup_test_layers);
+        app.update();
+
+        app.add_systems(Update, thermal_conduction);
+        for _ in 0..5 {
+            app.update();
+        }
+
+        // The second layer is 20 °C everywhere, the hot center of the first one must not leak into it
+        let mut query = app.world_mut().query::<(&HeatCell, &TilemapId)>();
+        let mut layers: Vec<Entity> = query.iter(app.world()).map(|(_, tilemap_id)| tilemap_id.0).collect();
+        layers.sort();
+        layers.dedup();
+        assert_eq!(layers.len(), 2);
+        let temperatures: Vec<Vec<f32>> = layers
+            .iter()
+            .map(|layer| {
+                query
+                    .iter(app.world())
+                    .filter(|(_, tilemap_id)| tilemap_id.0 == *layer)
+                    .map(|(heat_cell, _)| heat_cell.temperature.value)
+                    .collect()
+            })
+            .collect();
+        let cool = temperatures.iter().filter(|layer| layer.iter().all(|value| *value == 20.0)).count();
+        assert_eq!(cool, 1, "{temperatures:?}");
+    }
+
+    #[test]
+    fn test_exchange_heat() {
+        let (body, ambient) = exchange_heat(37.0, 250.0, 20.0, 1000.0, 10.0);
+        assert_eq!(body, 37.0 - 170.0 / 250.0);
+        assert_eq!(ambient, 20.0 + 170.0 / 1000.0);
+
+        // A thin gas only warms up to the body
+        let (body, ambient) = exchange_heat(37.0, 250.0, 0.0, 1.0, 10.0);
+        assert!((body - ambient).abs() < 1e-3);
+        assert!(body > 36.8);
+
+        // Nothing to trade heat with
+        assert_eq!(exchange_heat(37.0, 250.0, -270.0, 0.0, 10.0), (37.0, -270.0));
+    }
+}
@@ -0,0 +1,248 @@
+//! Golden-snapshot regression tests for the simulation.
+//!
+//! Every `fixtures/*.map` file describes a small hand-authored map. It is simulated for the
+//! number of ticks it declares and the resulting temperatures are compared, tile by tile,
+//! against `snapshots/<name>.snap`.
+//!
+//! A fixture looks like this, with the top row of each grid being the top of the map:
+//!
+//! ```text
+//! size 3 2
+//! ticks 10
+//! tolerance 0.001   # optional, defaults to DEFAULT_TOLERANCE
+//!
+//! temperature
+//! 20 20 20
+//! 20 80 20
+//!
+//! conductivity      # optional, defaults to 1
+//! 1  1  0.5
+//! 1  1  0.5
+//! ```
+//!
+//! After an intentional change to the physics, re-bless the snapshots and review the diff:
+//!
+//! ```text
+//! BLESS=1 cargo test -p simulation --test snapshots
+//! ```
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy_ecs_tilemap::prelude::*;
+use common::resources::MapSize;
+use simulation::temperature::{HeatCell, Temperature, ThermalConductivity};
+use simulation::{SimulationPlugin, SimulationRate, SimulationTick};
+
+const DEFAULT_TOLERANCE: f32 = 1e-3;
+
+struct Fixture {
+    name: String,
+    size: UVec2,
+    ticks: u64,
+    tolerance: f32,
+    /// Row-major from the bottom row, like `TilePos::to_index`.
+    temperature: Vec<f32>,
+    conductivity: Vec<f32>,
+}
+
+fn test_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// Reads `size.y` lines of numbers into a row-major grid, flipping it so the first line is the top row.
+fn parse_grid<'a>(lines: &mut impl Iterator<Item = &'a str>, size: UVec2) -> Result<Vec<f32>, String> {
+    let mut rows = Vec::with_capacity(size.y as usize);
+    for _ in 0..size.y {
+        let line = lines.next().ok_or("grid has too few rows")?;
+        let row = line
+            .split_whitespace()
+            .map(|value| value.parse::<f32>().map_err(|error| format!("invalid value {value}: {error}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        if row.len() != size.x as usize {
+            return Err(format!("expected {} columns, found {} in {line:?}", size.x, row.len()));
+        }
+        rows.push(row);
+    }
+    Ok(rows.into_iter().rev().flatten().collect())
+}
+
+/// Iterates over the meaningful lines of a fixture or snapshot, without comments and blank lines.
+fn content_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+}
+
+fn parse_fixture(name: &str, text: &str) -> Result<Fixture, String> {
+    let mut size = None;
+    let mut ticks = None;
+    let mut tolerance = DEFAULT_TOLERANCE;
+    let mut temperature = None;
+    let mut conductivity = None;
+
+    let mut lines = content_lines(text);
+    while let Some(line) = lines.next() {
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap();
+        let mut value = || words.next().ok_or_else(|| format!("missing value in {line:?}"));
+        match keyword {
+            "size" => {
+                let width = value()?.parse().map_err(|error| format!("invalid width: {error}"))?;
+                let height = value()?.parse().map_err(|error| format!("invalid height: {error}"))?;
+                size = Some(UVec2::new(width, height));
+            }
+            "ticks" => ticks = Some(value()?.parse().map_err(|error| format!("invalid tick count: {error}"))?),
+            "tolerance" => tolerance = value()?.parse().map_err(|error| format!("invalid tolerance: {error}"))?,
+            "temperature" => temperature = Some(parse_grid(&mut lines, size.ok_or("size must come before the grids")?)?),
+            "conductivity" => conductivity = Some(parse_grid(&mut lines, size.ok_or("size must come before the grids")?)?),
+            _ => return Err(format!("unexpected line {line:?}")),
+        }
+    }
+
+    let size = size.ok_or("missing size")?;
+    Ok(Fixture {
+        name: name.to_string(),
+        size,
+        ticks: ticks.ok_or("missing tick count")?,
+        tolerance,
+        temperature: temperature.ok_or("missing temperature grid")?,
+        conductivity: conductivity.unwrap_or_else(|| vec![1.0; (size.x * size.y) as usize]),
+    })
+}
+
+/// Runs the fixture through the simulation and returns the final temperatures.
+fn simulate(fixture: &Fixture) -> Vec<f32> {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, SimulationPlugin))
+        .insert_resource(MapSize(fixture.size));
+    let step = app.world().resource::<SimulationRate>().rate.duration();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(step));
+
+    let map_size = TilemapSize { x: fixture.size.x, y: fixture.size.y };
+    let world = app.world_mut();
+    let tilemap_entity = world.spawn_empty().id();
+    let mut tile_storage = TileStorage::empty(map_size);
+    for y in 0..map_size.y {
+        for x in 0..map_size.x {
+            let tile_pos = TilePos { x, y };
+            let index = tile_pos.to_index(&map_size);
+            let heat_cell = HeatCell {
+                temperature: Temperature { value: fixture.temperature[index] },
+                conductivity: ThermalConductivity { value: fixture.conductivity[index] },
+            };
+            let tile_entity = world
+                .spawn((
+                    TileBundle {
+                        position: tile_pos,
+                        tilemap_id: TilemapId(tilemap_entity),
+                        ..default()
+                    },
+                    heat_cell,
+                ))
+                .set_parent(tilemap_entity)
+                .id();
+            tile_storage.set(&tile_pos, tile_entity);
+        }
+    }
+    world.entity_mut(tilemap_entity).insert(TilemapBundle {
+        size: map_size,
+        storage: tile_storage,
+        ..default()
+    });
+
+    // The first update has no delta, so this loops once more than there are ticks
+    let mut updates = 0;
+    while app.world().resource::<SimulationTick>().0 < fixture.ticks {
+        app.update();
+        updates += 1;
+        assert!(updates <= fixture.ticks + 1, "{}: simulation is not ticking every update", fixture.name);
+    }
+
+    let mut temperatures = vec![0.0; fixture.temperature.len()];
+    let mut query = app.world_mut().query::<(&HeatCell, &TilePos)>();
+    for (heat_cell, tile_pos) in query.iter(app.world()) {
+        temperatures[tile_pos.to_index(&map_size)] = heat_cell.temperature.value;
+    }
+    temperatures
+}
+
+fn format_snapshot(fixture: &Fixture, temperatures: &[f32]) -> String {
+    let mut snapshot = format!(
+        "# {} after {} ticks, generated by tests/snapshots.rs\ntemperature\n",
+        fixture.name, fixture.ticks
+    );
+    for row in temperatures.chunks(fixture.size.x as usize).rev() {
+        let row = row.iter().map(|value| format!("{value:.4}")).collect::<Vec<_>>();
+        writeln!(snapshot, "{}", row.join(" ")).unwrap();
+    }
+    snapshot
+}
+
+fn parse_snapshot(fixture: &Fixture, text: &str) -> Result<Vec<f32>, String> {
+    let mut lines = content_lines(text);
+    match lines.next() {
+        Some("temperature") => parse_grid(&mut lines, fixture.size),
+        line => Err(format!("expected a temperature grid, found {line:?}")),
+    }
+}
+
+/// Lists the tiles whose temperature is off by more than the tolerance, or `None` if all match.
+fn compare(fixture: &Fixture, expected: &[f32], actual: &[f32]) -> Option<String> {
+    let mut report = String::new();
+    for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        if (expected - actual).abs() > fixture.tolerance {
+            let x = index as u32 % fixture.size.x;
+            let y = index as u32 / fixture.size.x;
+            writeln!(report, "  tile ({x}, {y}): expected {expected:.4}, got {actual:.4}").unwrap();
+        }
+    }
+    (!report.is_empty()).then(|| format!("{} differs from its snapshot:\n{report}", fixture.name))
+}
+
+#[test]
+fn test_simulation_snapshots() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let fixtures_dir = test_dir().join("fixtures");
+    let snapshots_dir = test_dir().join("snapshots");
+
+    let mut paths = fs::read_dir(&fixtures_dir)
+        .expect("missing fixtures directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "map"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures found in {fixtures_dir:?}");
+
+    let mut failures = vec![];
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let fixture = parse_fixture(&name, &fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|error| panic!("invalid fixture {path:?}: {error}"));
+        let temperatures = simulate(&fixture);
+        let snapshot_path = snapshots_dir.join(format!("{name}.snap"));
+
+        if bless {
+            fs::create_dir_all(&snapshots_dir).unwrap();
+            fs::write(&snapshot_path, format_snapshot(&fixture, &temperatures)).unwrap();
+            continue;
+        }
+
+        let Ok(text) = fs::read_to_string(&snapshot_path) else {
+            failures.push(format!("{name} has no snapshot, run with BLESS=1 to create it"));
+            continue;
+        };
+        let expected = parse_snapshot(&fixture, &text)
+            .unwrap_or_else(|error| panic!("invalid snapshot {snapshot_path:?}: {error}"));
+        failures.extend(compare(&fixture, &expected, &temperatures));
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\nIf the change is intended, re-bless with BLESS=1 cargo test -p simulation --test snapshots",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,390 @@
+//! The inputs bound to every [`InputAction`], and their persistence to a config file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Everything the player can do with a key, a mouse button or a gamepad button.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InputAction {
+    /// Selects a tile, or uses the active sandbox tool or order.
+    Primary,
+    /// Clears the selection, or uses the active tool the other way.
+    Secondary,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    /// Pans by dragging the world while held.
+    DragPan,
+    ZoomIn,
+    ZoomOut,
+    CycleOverlay,
+    Pause,
+    SpeedUp,
+    SlowDown,
+    Quicksave,
+    /// Saves and leaves the world for the main menu.
+    ReturnToMenu,
+    Brush,
+    Eraser,
+    FloodFill,
+    HeatGun,
+    Ladder,
+    BrushShape,
+    BrushShrink,
+    BrushGrow,
+    PreviousElement,
+    NextElement,
+    CycleLayer,
+    Colder,
+    Warmer,
+    Lighter,
+    Heavier,
+    Dig,
+    Build,
+    BuildLadder,
+    Mop,
+    CancelOrder,
+    LowerPriority,
+    RaisePriority,
+    /// Shows or hides the `n`th layer of the grid from the back, counting from 0.
+    ToggleLayer(u8),
+}
+
+/// How many layers have a [`InputAction::ToggleLayer`] action.
+pub const TOGGLED_LAYERS: usize = 9;
+
+const TOGGLE_LAYER_LABELS: [&str; TOGGLED_LAYERS] = [
+    "Toggle layer 1",
+    "Toggle layer 2",
+    "Toggle layer 3",
+    "Toggle layer 4",
+    "Toggle layer 5",
+    "Toggle layer 6",
+    "Toggle layer 7",
+    "Toggle layer 8",
+    "Toggle layer 9",
+];
+
+impl InputAction {
+    /// In the order they are listed on the controls screen.
+    pub const ALL: [Self; 37 + TOGGLED_LAYERS] = [
+        Self::Primary,
+        Self::Secondary,
+        Self::PanUp,
+        Self::PanDown,
+        Self::PanLeft,
+        Self::PanRight,
+        Self::DragPan,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::CycleOverlay,
+        Self::Pause,
+        Self::SpeedUp,
+        Self::SlowDown,
+        Self::Quicksave,
+        Self::ReturnToMenu,
+        Self::Brush,
+        Self::Eraser,
+        Self::FloodFill,
+        Self::HeatGun,
+        Self::Ladder,
+        Self::BrushShape,
+        Self::BrushShrink,
+        Self::BrushGrow,
+        Self::PreviousElement,
+        Self::NextElement,
+        Self::CycleLayer,
+        Self::Colder,
+        Self::Warmer,
+        Self::Lighter,
+        Self::Heavier,
+        Self::Dig,
+        Self::Build,
+        Self::BuildLadder,
+        Self::Mop,
+        Self::CancelOrder,
+        Self::LowerPriority,
+        Self::RaisePriority,
+        Self::ToggleLayer(0),
+        Self::ToggleLayer(1),
+        Self::ToggleLayer(2),
+        Self::ToggleLayer(3),
+        Self::ToggleLayer(4),
+        Self::ToggleLayer(5),
+        Self::ToggleLayer(6),
+        Self::ToggleLayer(7),
+        Self::ToggleLayer(8),
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Primary => "Select / use tool",
+            Self::Secondary => "Deselect / alternate tool",
+            Self::PanUp => "Pan up",
+            Self::PanDown => "Pan down",
+            Self::PanLeft => "Pan left",
+            Self::PanRight => "Pan right",
+            Self::DragPan => "Drag to pan",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::CycleOverlay => "Cycle overlay",
+            Self::Pause => "Pause simulation",
+            Self::SpeedUp => "Speed up simulation",
+            Self::SlowDown => "Slow down simulation",
+            Self::Quicksave => "Quicksave",
+            Self::ReturnToMenu => "Return to menu",
+            Self::Brush => "Brush",
+            Self::Eraser => "Eraser",
+            Self::FloodFill => "Flood fill",
+            Self::HeatGun => "Heat gun",
+            Self::Ladder => "Ladder",
+            Self::BrushShape => "Brush shape",
+            Self::BrushShrink => "Shrink brush",
+            Self::BrushGrow => "Grow brush",
+            Self::PreviousElement => "Previous element",
+            Self::NextElement => "Next element",
+            Self::CycleLayer => "Cycle layer",
+            Self::Colder => "Colder",
+            Self::Warmer => "Warmer",
+            Self::Lighter => "Lighter",
+            Self::Heavier => "Heavier",
+            Self::Dig => "Dig order",
+            Self::Build => "Build tile order",
+            Self::BuildLadder => "Build ladder order",
+            Self::Mop => "Mop order",
+            Self::CancelOrder => "Cancel orders",
+            Self::LowerPriority => "Lower order priority",
+            Self::RaisePriority => "Raise order priority",
+            Self::ToggleLayer(index) => TOGGLE_LAYER_LABELS.get(index as usize).copied().unwrap_or("Toggle layer"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl InputBinding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Self::Gamepad(_))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Key(key) => {
+                let name = format!("{key:?}");
+                ["Key", "Digit", "Arrow"]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .map_or(name.clone(), str::to_string)
+            }
+            Self::Mouse(button) => format!("Mouse {button:?}"),
+            Self::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(std::io::Error),
+    Serialize(String),
+    Deserialize(String),
+}
+
+impl std::fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingsError::Io(error) => write!(f, "io error: {error}"),
+            BindingsError::Serialize(error) => write!(f, "failed to serialize: {error}"),
+            BindingsError::Deserialize(error) => write!(f, "failed to deserialize: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+impl From<std::io::Error> for BindingsError {
+    fn from(error: std::io::Error) -> Self {
+        BindingsError::Io(error)
+    }
+}
+
+/// Where the bindings are stored.
+#[derive(Resource)]
+pub struct BindingsSettings {
+    pub path: PathBuf,
+}
+
+impl Default for BindingsSettings {
+    fn default() -> Self {
+        Self { path: PathBuf::from("config/bindings.ron") }
+    }
+}
+
+/// The inputs triggering every action. An action is active while any of its bindings is pressed.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InputBindings {
+    bindings: BTreeMap<InputAction, Vec<InputBinding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use InputAction::*;
+        use InputBinding::*;
+
+        // The gamepad pans with the left stick, see `super::gamepad`
+
+        let bindings = [
+            (Primary, vec![Mouse(MouseButton::Left), Gamepad(GamepadButton::South)]),
+            (Secondary, vec![Mouse(MouseButton::Right), Gamepad(GamepadButton::East)]),
+            (PanUp, vec![Key(KeyCode::KeyW), Key(KeyCode::ArrowUp)]),
+            (PanDown, vec![Key(KeyCode::KeyS), Key(KeyCode::ArrowDown)]),
+            (PanLeft, vec![Key(KeyCode::KeyA), Key(KeyCode::ArrowLeft)]),
+            (PanRight, vec![Key(KeyCode::KeyD), Key(KeyCode::ArrowRight)]),
+            (DragPan, vec![Mouse(MouseButton::Middle)]),
+            (ZoomIn, vec![Key(KeyCode::KeyX), Gamepad(GamepadButton::RightTrigger2)]),
+            (ZoomOut, vec![Key(KeyCode::KeyZ), Gamepad(GamepadButton::LeftTrigger2)]),
+            (CycleOverlay, vec![Key(KeyCode::Tab), Gamepad(GamepadButton::Select)]),
+            (Pause, vec![Key(KeyCode::Space), Gamepad(GamepadButton::Start)]),
+            (SpeedUp, vec![Key(KeyCode::NumpadAdd)]),
+            (SlowDown, vec![Key(KeyCode::NumpadSubtract)]),
+            (Quicksave, vec![Key(KeyCode::F5)]),
+            (ReturnToMenu, vec![Key(KeyCode::Escape)]),
+            (Brush, vec![Key(KeyCode::KeyB), Gamepad(GamepadButton::North)]),
+            (Eraser, vec![Key(KeyCode::KeyE), Gamepad(GamepadButton::West)]),
+            (FloodFill, vec![Key(KeyCode::KeyF), Gamepad(GamepadButton::LeftThumb)]),
+            (HeatGun, vec![Key(KeyCode::KeyH), Gamepad(GamepadButton::RightThumb)]),
+            (Ladder, vec![Key(KeyCode::KeyK)]),
+            (BrushShape, vec![Key(KeyCode::KeyR)]),
+            (BrushShrink, vec![Key(KeyCode::BracketLeft), Gamepad(GamepadButton::DPadDown)]),
+            (BrushGrow, vec![Key(KeyCode::BracketRight), Gamepad(GamepadButton::DPadUp)]),
+            (PreviousElement, vec![Key(KeyCode::Comma), Gamepad(GamepadButton::LeftTrigger)]),
+            (NextElement, vec![Key(KeyCode::Period), Gamepad(GamepadButton::RightTrigger)]),
+            (CycleLayer, vec![Key(KeyCode::KeyL)]),
+            (Colder, vec![Key(KeyCode::Minus), Gamepad(GamepadButton::DPadLeft)]),
+            (Warmer, vec![Key(KeyCode::Equal), Gamepad(GamepadButton::DPadRight)]),
+            (Lighter, vec![Key(KeyCode::PageDown)]),
+            (Heavier, vec![Key(KeyCode::PageUp)]),
+            (Dig, vec![Key(KeyCode::KeyG)]),
+            (Build, vec![Key(KeyCode::KeyT)]),
+            (BuildLadder, vec![Key(KeyCode::KeyY)]),
+            (Mop, vec![Key(KeyCode::KeyM)]),
+            (CancelOrder, vec![Key(KeyCode::KeyC)]),
+            (LowerPriority, vec![Key(KeyCode::KeyJ)]),
+            (RaisePriority, vec![Key(KeyCode::KeyU)]),
+            (ToggleLayer(0), vec![Key(KeyCode::Digit1)]),
+            (ToggleLayer(1), vec![Key(KeyCode::Digit2)]),
+            (ToggleLayer(2), vec![Key(KeyCode::Digit3)]),
+            (ToggleLayer(3), vec![Key(KeyCode::Digit4)]),
+            (ToggleLayer(4), vec![Key(KeyCode::Digit5)]),
+            (ToggleLayer(5), vec![Key(KeyCode::Digit6)]),
+            (ToggleLayer(6), vec![Key(KeyCode::Digit7)]),
+            (ToggleLayer(7), vec![Key(KeyCode::Digit8)]),
+            (ToggleLayer(8), vec![Key(KeyCode::Digit9)]),
+        ];
+        Self { bindings: bindings.into_iter().collect() }
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Assigns `binding` to `action` in place of its other bindings of the same kind, keyboard and
+    /// mouse or gamepad. The binding is taken away from any other action, so they never conflict.
+    pub fn rebind(&mut self, action: InputAction, binding: InputBinding) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|other| *other != binding);
+        }
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|other| other.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+    }
+
+    /// Removes the bindings of an action of one kind, keyboard and mouse or gamepad.
+    pub fn clear(&mut self, action: InputAction, gamepad: bool) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|binding| binding.is_gamepad() != gamepad);
+        }
+    }
+
+    /// The bindings of an action of one kind, for display.
+    pub fn label(&self, action: InputAction, gamepad: bool) -> String {
+        let labels: Vec<String> = self
+            .get(action)
+            .iter()
+            .filter(|binding| binding.is_gamepad() == gamepad)
+            .map(InputBinding::label)
+            .collect();
+        if labels.is_empty() {
+            "-".to_string()
+        } else {
+            labels.join(" / ")
+        }
+    }
+
+    /// Reads the bindings stored at `path`. Actions missing from the file keep their default bindings.
+    pub fn load(path: &Path) -> Result<Self, BindingsError> {
+        let stored: Self = ron::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|error| BindingsError::Deserialize(error.to_string()))?;
+        let mut bindings = Self::default();
+        bindings.bindings.extend(stored.bindings);
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BindingsError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| BindingsError::Serialize(error.to_string()))?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_bindings_dont_conflict() {
+        let bindings = InputBindings::default();
+        for action in InputAction::ALL {
+            assert!(!bindings.get(action).is_empty(), "{action:?} is not bound");
+            for binding in bindings.get(action) {
+                let actions = InputAction::ALL.iter().filter(|other| bindings.get(**other).contains(binding)).count();
+                assert_eq!(actions, 1, "{binding:?} is bound twice");
+            }
+        }
+    }
+
+    #[test]
+    fn test_rebind_takes_the_binding_away() {
+        let mut bindings = InputBindings::default();
+        bindings.rebind(InputAction::Pause, InputBinding::Key(KeyCode::KeyW));
+
+        assert_eq!(bindings.get(InputAction::Pause), [InputBinding::Gamepad(GamepadButton::Start), InputBinding::Key(KeyCode::KeyW)]);
+        assert_eq!(bindings.label(InputAction::PanUp, false), "Up");
+        assert!(!bindings.get(InputAction::PanUp).contains(&InputBinding::Key(KeyCode::KeyW)));
+    }
+
+    #[test]
+    fn test_bindings_round_trip() {
+        let path = std::env::temp_dir().join(format!("bindings-{}.ron", std::process::id()));
+        let mut bindings = InputBindings::default();
+        bindings.rebind(InputAction::CycleOverlay, InputBinding::Mouse(MouseButton::Back));
+        bindings.save(&path).unwrap();
+
+        assert_eq!(InputBindings::load(&path).unwrap(), bindings);
+        std::fs::remove_file(path).unwrap();
+    }
+}
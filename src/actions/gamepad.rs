@@ -0,0 +1,145 @@
+//! Analog gamepad input: the left stick pans the camera and the right stick moves a virtual cursor
+//! that stands in for the mouse when picking tiles.
+//!
+//! Gamepad buttons are bound to actions like any other input, see [`super::InputBindings`]. The
+//! triggers zoom through their bindings, as far as they are pressed.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::Actions;
+
+#[derive(Resource, Debug)]
+pub struct GamepadSettings {
+    /// Stick deflection below which the stick counts as centred, from 0 to 1.
+    pub dead_zone: f32,
+    /// Speed of the virtual cursor at full deflection, in logical px/s.
+    pub cursor_speed: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self { dead_zone: 0.15, cursor_speed: 800.0 }
+    }
+}
+
+/// A cursor driven by the right stick. While active, it replaces the mouse cursor for picking.
+#[derive(Resource, Default, Debug)]
+pub struct VirtualCursor {
+    /// Position in the window, in logical pixels.
+    pub position: Vec2,
+    /// Set when the right stick moves, cleared when the mouse does.
+    pub active: bool,
+}
+
+impl VirtualCursor {
+    /// The position of the cursor while it is in use.
+    pub fn get(&self) -> Option<Vec2> {
+        self.active.then_some(self.position)
+    }
+}
+
+#[derive(Component)]
+pub struct VirtualCursorMarker;
+
+/// Ignores deflections within the dead zone and rescales the rest, so movement starts smoothly from 0.
+pub fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= dead_zone || dead_zone >= 1.0 {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    stick / length * scaled
+}
+
+/// Adds the left stick to the camera actions.
+pub fn update_gamepad_axes(settings: Res<GamepadSettings>, gamepads: Query<&Gamepad>, mut actions: ResMut<Actions>) {
+    for gamepad in gamepads.iter() {
+        actions.camera_pan += apply_dead_zone(gamepad.left_stick(), settings.dead_zone);
+    }
+    actions.camera_pan = actions.camera_pan.clamp_length_max(1.0);
+}
+
+pub fn move_virtual_cursor(
+    time: Res<Time>,
+    settings: Res<GamepadSettings>,
+    gamepads: Query<&Gamepad>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut cursor: ResMut<VirtualCursor>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    // Moving the mouse takes over again
+    if cursor_moved.read().last().is_some() {
+        cursor.active = false;
+    }
+
+    let stick = gamepads
+        .iter()
+        .map(|gamepad| apply_dead_zone(gamepad.right_stick(), settings.dead_zone))
+        .sum::<Vec2>()
+        .clamp_length_max(1.0);
+    if stick == Vec2::ZERO {
+        return;
+    }
+    if !cursor.active {
+        cursor.position = window.cursor_position().unwrap_or(window.size() / 2.0);
+        cursor.active = true;
+    }
+    // The window's y axis points down
+    let delta = Vec2::new(stick.x, -stick.y) * settings.cursor_speed * time.delta_secs();
+    cursor.position = (cursor.position + delta).clamp(Vec2::ZERO, window.size());
+}
+
+/// Draws the virtual cursor while it is active.
+pub fn draw_virtual_cursor(
+    mut commands: Commands,
+    cursor: Res<VirtualCursor>,
+    mut cursor_query: Query<(&mut Node, &mut Visibility), With<VirtualCursorMarker>>,
+) {
+    const SIZE: f32 = 14.0;
+    let Ok((mut node, mut visibility)) = cursor_query.get_single_mut() else {
+        if cursor.active {
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(SIZE),
+                    height: Val::Px(SIZE),
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                BorderColor(Color::WHITE),
+                BorderRadius::MAX,
+                GlobalZIndex(i32::MAX),
+                PickingBehavior::IGNORE,
+                Visibility::Hidden,
+                VirtualCursorMarker,
+            ));
+        }
+        return;
+    };
+
+    match cursor.get() {
+        Some(position) => {
+            node.left = Val::Px(position.x - SIZE / 2.0);
+            node.top = Val::Px(position.y - SIZE / 2.0);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_dead_zone() {
+        assert_eq!(apply_dead_zone(Vec2::new(0.1, 0.0), 0.2), Vec2::ZERO);
+        assert_eq!(apply_dead_zone(Vec2::new(0.0, -1.0), 0.2), Vec2::new(0.0, -1.0));
+        let half = apply_dead_zone(Vec2::new(0.6, 0.0), 0.2);
+        assert!((half.x - 0.5).abs() < 1e-6);
+    }
+}
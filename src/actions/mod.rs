@@ -1,59 +1,168 @@
-use bevy::math::Vec3Swizzles;
+use bevy::input::InputSystem;
 use bevy::prelude::*;
 
-use crate::actions::game_control::{get_movement, GameControl};
-use crate::player::Player;
-use crate::GameState;
+pub use bindings::{BindingsSettings, InputAction, InputBinding, InputBindings, TOGGLED_LAYERS};
+pub use gamepad::{GamepadSettings, VirtualCursor};
+pub use touch::{TouchGesture, TouchSettings};
 
-mod game_control;
-
-pub const FOLLOW_EPSILON: f32 = 5.;
+mod bindings;
+mod gamepad;
+mod touch;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions.
+// This plugin listens for keyboard, mouse, gamepad buttons and sticks and converts them into Actions.
+// Touch input is recognised as `TouchGesture` events instead.
 // Actions can then be used as a resource in other systems to act on the player input.
+// Which inputs trigger which action is configured by `InputBindings`, loaded from `BindingsSettings::path`.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Actions>().add_systems(
-            Update,
-            set_movement_actions.run_if(in_state(GameState::Playing)),
-        );
+        app.init_resource::<Actions>()
+            .init_resource::<BindingsSettings>()
+            .init_resource::<InputBindings>()
+            .init_resource::<GamepadSettings>()
+            .init_resource::<VirtualCursor>()
+            .init_resource::<TouchSettings>()
+            .init_resource::<touch::TouchGestureState>()
+            .add_event::<TouchGesture>()
+            .add_systems(Startup, load_bindings)
+            .add_systems(
+                PreUpdate,
+                (
+                    update_actions,
+                    gamepad::update_gamepad_axes,
+                    gamepad::move_virtual_cursor,
+                    touch::recognize_gestures,
+                )
+                    .chain()
+                    .in_set(ActionsSystems)
+                    .after(InputSystem),
+            )
+            .add_systems(Update, gamepad::draw_virtual_cursor);
     }
 }
 
+/// Updates [`Actions`] from the raw input, before `Update`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionsSystems;
+
 #[derive(Default, Resource)]
 pub struct Actions {
-    pub player_movement: Option<Vec2>,
+    /// From the pan actions and the left stick, no longer than 1.
+    pub camera_pan: Vec2,
+    /// From the zoom actions, as far as their analog bindings are pressed, between -1 and 1.
+    /// Positive zooms out.
+    pub camera_zoom: f32,
+    buttons: ButtonInput<InputAction>,
 }
 
-pub fn set_movement_actions(
+impl Actions {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.buttons.pressed(action)
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.buttons.just_pressed(action)
+    }
+
+    /// `1.0` while only `positive` is held, `-1.0` while only `negative` is.
+    pub fn axis(&self, negative: InputAction, positive: InputAction) -> f32 {
+        self.pressed(positive) as u8 as f32 - self.pressed(negative) as u8 as f32
+    }
+
+    /// Marks an action as held or released, e.g. from input that isn't bound to buttons.
+    pub fn set(&mut self, action: InputAction, active: bool) {
+        match (active, self.buttons.pressed(action)) {
+            (true, false) => self.buttons.press(action),
+            (false, true) => self.buttons.release(action),
+            _ => {}
+        }
+    }
+}
+
+fn load_bindings(settings: Res<BindingsSettings>, mut bindings: ResMut<InputBindings>) {
+    if !settings.path.exists() {
+        return;
+    }
+    match InputBindings::load(&settings.path) {
+        Ok(loaded) => *bindings = loaded,
+        Err(error) => error!("Failed to load bindings from {:?}: {error}", settings.path),
+    }
+}
+
+/// How far any binding of `action` is pressed, from 0 to 1. Only analog gamepad buttons such as the
+/// triggers go in between.
+fn action_value(
+    action: InputAction,
+    bindings: &InputBindings,
+    keyboard_input: &ButtonInput<KeyCode>,
+    mouse_input: &ButtonInput<MouseButton>,
+    gamepads: &Query<&Gamepad>,
+) -> f32 {
+    bindings
+        .get(action)
+        .iter()
+        .map(|binding| match binding {
+            InputBinding::Key(key) => keyboard_input.pressed(*key) as u8 as f32,
+            InputBinding::Mouse(button) => mouse_input.pressed(*button) as u8 as f32,
+            InputBinding::Gamepad(button) => gamepads
+                .iter()
+                .map(|gamepad| gamepad.get(*button).unwrap_or(gamepad.pressed(*button) as u8 as f32))
+                .fold(0.0, f32::max),
+        })
+        .fold(0.0, f32::max)
+}
+
+pub fn update_actions(
     mut actions: ResMut<Actions>,
+    bindings: Res<InputBindings>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    touch_input: Res<Touches>,
-    player: Query<&Transform, With<Player>>,
-    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
 ) {
-    let mut player_movement = Vec2::new(
-        get_movement(GameControl::Right, &keyboard_input)
-            - get_movement(GameControl::Left, &keyboard_input),
-        get_movement(GameControl::Up, &keyboard_input)
-            - get_movement(GameControl::Down, &keyboard_input),
-    );
-
-    if let Some(touch_position) = touch_input.first_pressed_position() {
-        let (camera, camera_transform) = camera.single();
-        if let Ok(touch_position) = camera.viewport_to_world_2d(camera_transform, touch_position) {
-            let diff = touch_position - player.single().translation.xy();
-            if diff.length() > FOLLOW_EPSILON {
-                player_movement = diff.normalize();
-            }
-        }
+    actions.buttons.clear();
+    for action in InputAction::ALL {
+        let active = bindings.get(action).iter().any(|binding| match binding {
+            InputBinding::Key(key) => keyboard_input.pressed(*key),
+            InputBinding::Mouse(button) => mouse_input.pressed(*button),
+            InputBinding::Gamepad(button) => gamepads.iter().any(|gamepad| gamepad.pressed(*button)),
+        });
+        actions.set(action, active);
     }
 
-    if player_movement != Vec2::ZERO {
-        actions.player_movement = Some(player_movement.normalize());
-    } else {
-        actions.player_movement = None;
+    actions.camera_pan = Vec2::new(
+        actions.axis(InputAction::PanLeft, InputAction::PanRight),
+        actions.axis(InputAction::PanDown, InputAction::PanUp),
+    )
+    .normalize_or_zero();
+    let zoom = |action| action_value(action, &bindings, &keyboard_input, &mouse_input, &gamepads);
+    actions.camera_zoom = (zoom(InputAction::ZoomOut) - zoom(InputAction::ZoomIn)).clamp(-1.0, 1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn test_triggers_zoom_as_far_as_bound() {
+        let mut world = World::new();
+        world.init_resource::<Actions>();
+        world.init_resource::<InputBindings>();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        let mut gamepad = Gamepad::default();
+        gamepad.analog_mut().set(GamepadButton::RightTrigger2, 0.5);
+        world.spawn(gamepad);
+
+        world.run_system_once(update_actions).unwrap();
+        assert_eq!(world.resource::<Actions>().camera_zoom, -0.5);
+
+        world
+            .resource_mut::<InputBindings>()
+            .rebind(InputAction::ZoomIn, InputBinding::Gamepad(GamepadButton::DPadUp));
+        world.run_system_once(update_actions).unwrap();
+        assert_eq!(world.resource::<Actions>().camera_zoom, 0.0);
     }
 }
@@ -0,0 +1,212 @@
+//! Recognises touch gestures from [`Touches`] and sends them as [`TouchGesture`] events.
+//!
+//! One finger taps or long-presses, two fingers pan and pinch. A sequence of touches counts as a
+//! single gesture, so lifting one finger of a pinch doesn't turn into a tap.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+#[derive(Resource, Debug)]
+pub struct TouchSettings {
+    /// How long a finger has to stay down to long-press.
+    pub long_press: Duration,
+    /// How far a finger can move, in logical pixels, and still tap or long-press.
+    pub slop: f32,
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        Self { long_press: Duration::from_millis(500), slop: 10.0 }
+    }
+}
+
+/// Positions are in the window, in logical pixels.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum TouchGesture {
+    /// A finger went down and up again without moving.
+    Tap { position: Vec2 },
+    /// A finger stayed down without moving for [`TouchSettings::long_press`].
+    LongPress { position: Vec2 },
+    /// Two fingers moved together, by `delta` since the last frame.
+    Pan { delta: Vec2 },
+    /// Two fingers moved apart when `scale` is above 1, closer together below 1.
+    Pinch { center: Vec2, scale: f32 },
+}
+
+/// The gesture in progress.
+#[derive(Resource, Default, Debug)]
+pub struct TouchGestureState {
+    /// The finger that may tap or long-press, and when it went down.
+    primary: Option<(u64, Vec2, Duration)>,
+    /// Moved past the slop, or long-pressed already.
+    consumed: bool,
+    /// A second finger joined since the first one went down.
+    multi_touch: bool,
+    /// Midpoint and distance of the two fingers last frame.
+    pair: Option<(Vec2, f32)>,
+}
+
+pub fn recognize_gestures(
+    time: Res<Time<Real>>,
+    touches: Res<Touches>,
+    settings: Res<TouchSettings>,
+    mut state: ResMut<TouchGestureState>,
+    mut gestures: EventWriter<TouchGesture>,
+) {
+    let now = time.elapsed();
+    let mut pressed: Vec<_> = touches.iter().collect();
+    pressed.sort_by_key(|touch| touch.id());
+
+    // Touches lifted within the frame they went down in are only in the just pressed ones
+    if state.primary.is_none() {
+        if let Some(touch) = touches.iter_just_pressed().next() {
+            *state = TouchGestureState { primary: Some((touch.id(), touch.position(), now)), ..default() };
+        }
+    }
+    if pressed.len() >= 2 || touches.iter_just_pressed().count() >= 2 {
+        state.multi_touch = true;
+    }
+
+    match pressed.as_slice() {
+        [first, second, ..] => {
+            let center = (first.position() + second.position()) / 2.0;
+            let distance = first.position().distance(second.position());
+            if let Some((last_center, last_distance)) = state.pair {
+                if center != last_center {
+                    gestures.send(TouchGesture::Pan { delta: center - last_center });
+                }
+                if distance != last_distance && last_distance > 0.0 {
+                    gestures.send(TouchGesture::Pinch { center, scale: distance / last_distance });
+                }
+            }
+            state.pair = Some((center, distance));
+        }
+        [touch] => {
+            state.pair = None;
+            if let Some((id, start, pressed_at)) = state.primary {
+                if touch.id() == id && !state.consumed && !state.multi_touch {
+                    if touch.position().distance(start) > settings.slop {
+                        state.consumed = true;
+                    } else if now.saturating_sub(pressed_at) >= settings.long_press {
+                        state.consumed = true;
+                        gestures.send(TouchGesture::LongPress { position: touch.position() });
+                    }
+                }
+            }
+        }
+        [] => state.pair = None,
+    }
+
+    for touch in touches.iter_just_released() {
+        let Some((id, start, _)) = state.primary else {
+            continue;
+        };
+        if touch.id() == id && !state.consumed && !state.multi_touch && touch.position().distance(start) <= settings.slop {
+            gestures.send(TouchGesture::Tap { position: touch.position() });
+        }
+    }
+    if pressed.is_empty() {
+        *state = TouchGestureState::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::touch::{TouchInput, TouchPhase};
+    use bevy::input::InputPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+            .init_resource::<TouchSettings>()
+            .init_resource::<TouchGestureState>()
+            .add_event::<TouchGesture>()
+            .add_systems(PreUpdate, recognize_gestures.after(bevy::input::InputSystem));
+        app
+    }
+
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, x: f32, y: f32) {
+        app.world_mut().send_event(TouchInput {
+            phase,
+            position: Vec2::new(x, y),
+            window: Entity::PLACEHOLDER,
+            force: None,
+            id,
+        });
+    }
+
+    fn gestures(app: &App) -> Vec<TouchGesture> {
+        app.world().resource::<Events<TouchGesture>>().iter_current_update_events().copied().collect()
+    }
+
+    #[test]
+    fn test_tap() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, 100.0, 100.0);
+        app.update();
+        touch(&mut app, 0, TouchPhase::Moved, 104.0, 101.0);
+        touch(&mut app, 0, TouchPhase::Ended, 104.0, 101.0);
+        app.update();
+
+        assert_eq!(gestures(&app), [TouchGesture::Tap { position: Vec2::new(104.0, 101.0) }]);
+    }
+
+    #[test]
+    fn test_tap_within_a_frame() {
+        let mut app = app();
+        app.update();
+        touch(&mut app, 0, TouchPhase::Started, 100.0, 100.0);
+        touch(&mut app, 0, TouchPhase::Moved, 101.0, 100.0);
+        touch(&mut app, 0, TouchPhase::Ended, 101.0, 100.0);
+        app.update();
+
+        assert_eq!(gestures(&app), [TouchGesture::Tap { position: Vec2::new(101.0, 100.0) }]);
+    }
+
+    #[test]
+    fn test_long_press_doesnt_tap() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, 50.0, 50.0);
+        let mut long_presses = 0;
+        for _ in 0..10 {
+            app.update();
+            long_presses += gestures(&app).len();
+        }
+        touch(&mut app, 0, TouchPhase::Ended, 50.0, 50.0);
+        app.update();
+
+        assert_eq!(long_presses, 1);
+        assert!(gestures(&app).is_empty());
+    }
+
+    #[test]
+    fn test_two_finger_pan_and_pinch() {
+        let mut app = app();
+        touch(&mut app, 0, TouchPhase::Started, 100.0, 100.0);
+        touch(&mut app, 1, TouchPhase::Started, 200.0, 100.0);
+        app.update();
+        touch(&mut app, 0, TouchPhase::Moved, 90.0, 110.0);
+        touch(&mut app, 1, TouchPhase::Moved, 230.0, 110.0);
+        app.update();
+
+        assert_eq!(
+            gestures(&app),
+            [
+                TouchGesture::Pan { delta: Vec2::new(10.0, 10.0) },
+                TouchGesture::Pinch { center: Vec2::new(160.0, 110.0), scale: 1.4 },
+            ]
+        );
+
+        // Lifting the fingers one after the other is not a tap
+        touch(&mut app, 1, TouchPhase::Ended, 230.0, 110.0);
+        app.update();
+        touch(&mut app, 0, TouchPhase::Ended, 90.0, 110.0);
+        app.update();
+        assert!(gestures(&app).is_empty());
+    }
+}
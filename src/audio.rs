@@ -1,4 +1,4 @@
-use crate::actions::{set_movement_actions, Actions};
+use crate::actions::{update_actions, Actions};
 use crate::loading::AudioAssets;
 use crate::GameState;
 use bevy::prelude::*;
@@ -11,10 +11,11 @@ impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(AudioPlugin)
             .add_systems(OnEnter(GameState::Playing), start_audio)
+            .add_systems(OnExit(GameState::Playing), stop_audio)
             .add_systems(
                 Update,
                 control_flying_sound
-                    .after(set_movement_actions)
+                    .after(update_actions)
                     .run_if(in_state(GameState::Playing)),
             );
     }
@@ -33,6 +34,10 @@ fn start_audio(mut commands: Commands, audio_assets: Res<AudioAssets>, audio: Re
     commands.insert_resource(FlyingAudio(handle));
 }
 
+fn stop_audio(audio: Res<Audio>) {
+    audio.stop();
+}
+
 fn control_flying_sound(
     actions: Res<Actions>,
     audio: Res<FlyingAudio>,
@@ -40,15 +45,11 @@ fn control_flying_sound(
 ) {
     if let Some(instance) = audio_instances.get_mut(&audio.0) {
         match instance.state() {
-            PlaybackState::Paused { .. } => {
-                if actions.player_movement.is_some() {
-                    instance.resume(AudioTween::default());
-                }
+            PlaybackState::Paused { .. } if actions.camera_pan != Vec2::ZERO => {
+                instance.resume(AudioTween::default());
             }
-            PlaybackState::Playing { .. } => {
-                if actions.player_movement.is_none() {
-                    instance.pause(AudioTween::default());
-                }
+            PlaybackState::Playing { .. } if actions.camera_pan == Vec2::ZERO => {
+                instance.pause(AudioTween::default());
             }
             _ => {}
         }
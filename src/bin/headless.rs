@@ -0,0 +1,105 @@
+//! Generates a world and runs the simulation without a window, for CI and balancing.
+//!
+//! ```text
+//! cargo run --bin headless -- --seed 42 --size 64x64 --ticks 1000 --save saves/batch.sav
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use bevy::prelude::*;
+use tile_game::headless::{self, HeadlessPlugin, WorldStats};
+
+const USAGE: &str = "usage: headless [--seed <u32>] [--size <width>x<height>] [--ticks <n>] [--save <path>] [--quiet]";
+
+struct Args {
+    seed: u32,
+    size: UVec2,
+    ticks: u64,
+    save: Option<PathBuf>,
+    quiet: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Self {
+            seed: 0,
+            size: UVec2::new(64, 64),
+            ticks: 100,
+            save: None,
+            quiet: false,
+        };
+
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().ok_or_else(|| format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--seed" => args.seed = value()?.parse().map_err(|error| format!("invalid seed: {error}"))?,
+                "--ticks" => args.ticks = value()?.parse().map_err(|error| format!("invalid tick count: {error}"))?,
+                "--size" => {
+                    let size = value()?;
+                    let (width, height) = size.split_once('x').ok_or_else(|| format!("invalid size: {size}"))?;
+                    args.size = UVec2::new(
+                        width.parse().map_err(|error| format!("invalid width: {error}"))?,
+                        height.parse().map_err(|error| format!("invalid height: {error}"))?,
+                    );
+                }
+                "--save" => args.save = Some(PathBuf::from(value()?)),
+                "--quiet" => args.quiet = true,
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                _ => return Err(format!("unknown argument {arg}\n{USAGE}")),
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut app = App::new();
+    app.add_plugins(HeadlessPlugin);
+    app.finish();
+    app.cleanup();
+
+    let start = Instant::now();
+    if let Err(error) = headless::generate(&mut app, args.seed, args.size) {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+    let generated = start.elapsed();
+
+    if let Err(error) = headless::run_ticks(&mut app, args.ticks) {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+    let simulated = start.elapsed() - generated;
+
+    if !args.quiet {
+        println!(
+            "seed {} size {}x{}: generated in {:.3}s, {} ticks in {:.3}s",
+            args.seed,
+            args.size.x,
+            args.size.y,
+            generated.as_secs_f64(),
+            args.ticks,
+            simulated.as_secs_f64()
+        );
+        print!("{}", WorldStats::collect(&mut app));
+    }
+
+    if let Some(path) = args.save {
+        if let Err(error) = headless::save(&mut app, &path) {
+            eprintln!("failed to save {path:?}: {error}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,319 @@
+//! Colonists, the agents living on the [`LayerType::NPC`] layer.
+//!
+//! A colonist is two tiles tall, its [`TilePos`] being the tile of its feet, and falls like
+//! anything else standing on the tiles, see [`crate::gravity`]. Colonists are printed in the start
+//! area when a new world is entered, with a random name, traits and stats.
+//!
+//! Selecting a tile a colonist occupies selects the colonist, the inspector then lists it. What
+//! colonists need to stay alive is tracked in [`Needs`].
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::gravity::Fall;
+use crate::navigation::NavAgent;
+use crate::picking::{SelectedTile, TilePickingSystems};
+use crate::states::generation::GenerationState;
+use crate::world::features::start_area;
+use crate::world::layer::{LayerType, LAYER_Z_STEP};
+use crate::world::tiles::WorldTiles;
+use crate::world::SeededRng;
+use crate::GameState;
+
+pub use needs::{Asleep, Needs, Stressed};
+
+mod needs;
+
+/// Height of a colonist, in tiles.
+pub const COLONIST_HEIGHT: u32 = 2;
+
+/// Walking speed of a colonist without athletics, in world units/s.
+const BASE_SPEED: f32 = 40.0;
+
+/// Walking speed gained per level of athletics, in world units/s.
+const SPEED_PER_ATHLETICS: f32 = 6.0;
+
+const NAMES: [&str; 16] = [
+    "Ada", "Bram", "Cleo", "Dario", "Edda", "Finn", "Greta", "Hugo", "Ines", "Jonas", "Kira", "Lev", "Mira", "Nils",
+    "Odile", "Pavel",
+];
+
+pub struct ColonistPlugin;
+
+impl Plugin for ColonistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColonistSettings>()
+            .init_resource::<SelectedColonist>()
+            .register_type::<Colonist>()
+            .register_type::<Stats>()
+            .add_plugins(needs::NeedsPlugin)
+            .add_systems(OnEnter(GenerationState::Done), spawn_colonists.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (select_colonist, draw_selected_colonist)
+                    .chain()
+                    .after(TilePickingSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_colonists);
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ColonistSettings {
+    /// Colonists printed when a world is entered.
+    pub count: u32,
+}
+
+impl Default for ColonistSettings {
+    fn default() -> Self {
+        Self { count: 3 }
+    }
+}
+
+/// The colonist standing on the [`SelectedTile`].
+#[derive(Resource, Default, Debug)]
+pub struct SelectedColonist(pub Option<Entity>);
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Skill {
+    Digging,
+    Building,
+    Athletics,
+    Strength,
+}
+
+impl Skill {
+    pub const ALL: [Self; 4] = [Self::Digging, Self::Building, Self::Athletics, Self::Strength];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Digging => "Digging",
+            Self::Building => "Building",
+            Self::Athletics => "Athletics",
+            Self::Strength => "Strength",
+        }
+    }
+}
+
+/// A quirk of a colonist, raising or lowering one of its skills.
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trait {
+    Mole,
+    Handy,
+    Sprinter,
+    Strongarm,
+    SoftHands,
+    Butterfingers,
+    Slowpoke,
+    NoodleArms,
+}
+
+impl Trait {
+    const POSITIVE: [Self; 4] = [Self::Mole, Self::Handy, Self::Sprinter, Self::Strongarm];
+    const NEGATIVE: [Self; 4] = [Self::SoftHands, Self::Butterfingers, Self::Slowpoke, Self::NoodleArms];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Mole => "Mole",
+            Self::Handy => "Handy",
+            Self::Sprinter => "Sprinter",
+            Self::Strongarm => "Strongarm",
+            Self::SoftHands => "Soft Hands",
+            Self::Butterfingers => "Butterfingers",
+            Self::Slowpoke => "Slowpoke",
+            Self::NoodleArms => "Noodle Arms",
+        }
+    }
+
+    /// The skill affected and by how much.
+    pub fn modifier(self) -> (Skill, i32) {
+        match self {
+            Self::Mole => (Skill::Digging, 3),
+            Self::Handy => (Skill::Building, 3),
+            Self::Sprinter => (Skill::Athletics, 3),
+            Self::Strongarm => (Skill::Strength, 3),
+            Self::SoftHands => (Skill::Digging, -2),
+            Self::Butterfingers => (Skill::Building, -2),
+            Self::Slowpoke => (Skill::Athletics, -2),
+            Self::NoodleArms => (Skill::Strength, -2),
+        }
+    }
+}
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Colonist {
+    pub name: String,
+    pub traits: Vec<Trait>,
+    /// Of its sprite, in degrees.
+    pub hue: f32,
+}
+
+/// Skill levels of a colonist, traits included.
+#[derive(Component, Reflect, Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct Stats {
+    pub digging: u32,
+    pub building: u32,
+    pub athletics: u32,
+    pub strength: u32,
+}
+
+impl Stats {
+    pub fn get(&self, skill: Skill) -> u32 {
+        match skill {
+            Skill::Digging => self.digging,
+            Skill::Building => self.building,
+            Skill::Athletics => self.athletics,
+            Skill::Strength => self.strength,
+        }
+    }
+
+    fn get_mut(&mut self, skill: Skill) -> &mut u32 {
+        match skill {
+            Skill::Digging => &mut self.digging,
+            Skill::Building => &mut self.building,
+            Skill::Athletics => &mut self.athletics,
+            Skill::Strength => &mut self.strength,
+        }
+    }
+
+    /// Applies the modifiers of `traits`, skills don't go below 0.
+    pub fn with_traits(mut self, traits: &[Trait]) -> Self {
+        for (skill, modifier) in traits.iter().map(|colonist_trait| colonist_trait.modifier()) {
+            let value = self.get_mut(skill);
+            *value = value.saturating_add_signed(modifier);
+        }
+        self
+    }
+}
+
+impl Colonist {
+    /// A colonist with a random name and color, a good trait and maybe a bad one about another skill.
+    pub fn random(rng: &mut impl Rng) -> (Self, Stats) {
+        let name = NAMES[rng.random_range(0..NAMES.len())].to_string();
+        let positive = Trait::POSITIVE[rng.random_range(0..Trait::POSITIVE.len())];
+        let mut traits = vec![positive];
+        if rng.random_bool(0.5) {
+            let negative: Vec<_> = Trait::NEGATIVE
+                .into_iter()
+                .filter(|negative| negative.modifier().0 != positive.modifier().0)
+                .collect();
+            traits.push(negative[rng.random_range(0..negative.len())]);
+        }
+
+        let stats = Stats {
+            digging: rng.random_range(0..=3),
+            building: rng.random_range(0..=3),
+            athletics: rng.random_range(0..=3),
+            strength: rng.random_range(0..=3),
+        }
+        .with_traits(&traits);
+        (Self { name, traits, hue: rng.random_range(0.0..360.0) }, stats)
+    }
+}
+
+/// The tiles a colonist standing in `feet` takes up, from the bottom.
+pub fn body_tiles(feet: &TilePos) -> [TilePos; COLONIST_HEIGHT as usize] {
+    [*feet, TilePos { x: feet.x, y: feet.y + 1 }]
+}
+
+/// Spawns a colonist with its feet in `position`, whose center is at `center` in world space.
+pub fn spawn_colonist<'a>(
+    commands: &'a mut Commands,
+    colonist: Colonist,
+    stats: Stats,
+    needs: Needs,
+    position: TilePos,
+    center: Vec2,
+) -> EntityCommands<'a> {
+    let color = Color::hsl(colonist.hue, 0.5, 0.6);
+    let mut entity = commands.spawn((
+        Name::new(colonist.name.clone()),
+        colonist,
+        stats,
+        needs,
+        LayerType::NPC,
+        Fall::default(),
+        NavAgent { speed: BASE_SPEED + SPEED_PER_ATHLETICS * stats.athletics as f32, height: COLONIST_HEIGHT },
+        position,
+        Transform::from_translation(center.extend(LayerType::NPC.id() as f32 * LAYER_Z_STEP)),
+        Visibility::default(),
+    ));
+    // From the bottom of the feet tile to the top of the head one
+    entity.with_child((Sprite::from_color(color, Vec2::new(10.0, 28.0)), Transform::from_xyz(0.0, 6.0, 0.0)));
+    entity
+}
+
+/// Prints a new crew, unless a loaded world brought its own.
+fn spawn_colonists(
+    mut commands: Commands,
+    settings: Res<ColonistSettings>,
+    mut rng: ResMut<SeededRng<StdRng>>,
+    tiles: WorldTiles,
+    colonist_query: Query<(), With<Colonist>>,
+) {
+    if !colonist_query.is_empty() {
+        return;
+    }
+    let Some(map_size) = tiles.map_size() else {
+        return;
+    };
+    let area = start_area(map_size);
+    let rng = rng.rng();
+
+    for index in 0..settings.count {
+        let position = TilePos { x: area.min.x + (1 + index * 2) % area.width().max(1), y: area.min.y };
+        let Some(center) = tiles.world_position(LayerType::Solid, &position) else {
+            continue;
+        };
+        let (colonist, stats) = Colonist::random(rng);
+
+        info!("Printing {} at {position:?}", colonist.name);
+        spawn_colonist(&mut commands, colonist, stats, Needs::default(), position, center);
+    }
+}
+
+fn despawn_colonists(
+    mut commands: Commands,
+    mut selected: ResMut<SelectedColonist>,
+    colonist_query: Query<Entity, With<Colonist>>,
+) {
+    selected.0 = None;
+    for entity in colonist_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn select_colonist(
+    selected_tile: Res<SelectedTile>,
+    colonist_query: Query<(Entity, &TilePos), With<Colonist>>,
+    mut selected: ResMut<SelectedColonist>,
+) {
+    if !selected_tile.is_changed() {
+        return;
+    }
+    selected.0 = selected_tile.0.and_then(|tile| {
+        colonist_query
+            .iter()
+            .find(|(_, feet)| body_tiles(feet).contains(&tile))
+            .map(|(entity, _)| entity)
+    });
+}
+
+fn draw_selected_colonist(
+    mut gizmos: Gizmos,
+    selected: Res<SelectedColonist>,
+    colonist_query: Query<&GlobalTransform, With<Colonist>>,
+) {
+    let Some(transform) = selected.0.and_then(|entity| colonist_query.get(entity).ok()) else {
+        return;
+    };
+    let center = transform.translation().truncate() + Vec2::new(0.0, 8.0);
+    gizmos.rect_2d(Isometry2d::from_translation(center), Vec2::new(16.0, 32.0), Color::WHITE);
+}
@@ -0,0 +1,278 @@
+//! What keeps a colonist alive: breath, calories, stamina and body temperature.
+//!
+//! Colonists breathe the gas in their head tile and hold their breath anywhere else. The oxygen
+//! is not used up while gas doesn't flow, a single tile would run out within minutes. They burn
+//! calories all the time, but can't starve or go hungry until there is food to restore them.
+//! Stamina drains while awake, tired colonists fall asleep once they are done with their job,
+//! exhausted ones wherever they are, and wake up rested. The body trades heat with the gas or liquid around it,
+//! warming or cooling the tiles, while the colonist keeps itself close to [`BODY_TEMPERATURE`].
+//!
+//! Every need out of its comfortable range adds [`Needs::stress`]. [`Stressed`] colonists drop their
+//! job and refuse work until they calm down, and a colonist dies when a need reaches its deadly
+//! level.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+use serde::{Deserialize, Serialize};
+
+use super::{body_tiles, Colonist};
+use crate::jobs::{Assignment, JobSystems};
+use crate::navigation::{NavGoal, Route};
+use crate::resources::{elements, ElementConfigs};
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::GameState;
+
+/// Seconds a colonist can hold its breath.
+pub const MAX_BREATH: f32 = 60.0;
+/// In kcal.
+pub const MAX_CALORIES: f32 = 4000.0;
+pub const MAX_STAMINA: f32 = 100.0;
+pub const MAX_STRESS: f32 = 100.0;
+/// What a colonist keeps its body at, in °C.
+pub const BODY_TEMPERATURE: f32 = 37.0;
+
+/// Gases a colonist can breathe.
+const BREATHABLE: [u32; 1] = [elements::OXYGEN];
+/// Thinner gas than this, in kg, is as good as none.
+const MIN_BREATHABLE_MASS: f32 = 0.05;
+/// Breath regained per second of breathing.
+const BREATH_RECOVERY: f32 = 10.0;
+/// In kcal/s.
+const CALORIE_BURN: f32 = 1.0;
+/// Lost per second awake, a full bar lasting 15 minutes.
+const STAMINA_DRAIN: f32 = MAX_STAMINA / 900.0;
+/// Regained per second asleep, a full night taking 3 minutes.
+const STAMINA_RECOVERY: f32 = MAX_STAMINA / 180.0;
+/// Colonists without a job go to sleep below this.
+const TIRED: f32 = 15.0;
+/// In kJ/K, about 70 kg of mostly water.
+const BODY_HEAT_CAPACITY: f32 = 250.0;
+/// Heat flowing between the body and its surroundings, in kW/K.
+const SKIN_CONDUCTANCE: f32 = 0.1;
+/// Share of the gap to [`BODY_TEMPERATURE`] closed every second by the colonist itself.
+const REGULATION: f32 = 0.01;
+/// Stress gained per second for each need out of its comfortable range.
+const STRESS_GAIN: f32 = 0.5;
+/// Stress lost per second when every need is met.
+const STRESS_RECOVERY: f32 = 1.0;
+/// Colonists are [`Stressed`] from this much stress on...
+const STRESSED: f32 = 80.0;
+/// ...until they are back under this much.
+const CALM: f32 = 50.0;
+/// Comfortable body temperatures, in °C.
+const COMFORTABLE_TEMPERATURE: (f32, f32) = (35.0, 39.0);
+/// Body temperatures a colonist dies outside of, in °C.
+const SURVIVABLE_TEMPERATURE: (f32, f32) = (30.0, 45.0);
+
+pub struct NeedsPlugin;
+
+impl Plugin for NeedsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Needs>().add_systems(
+            Update,
+            (update_needs, apply_needs)
+                .chain()
+                .before(JobSystems)
+                .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+        );
+    }
+}
+
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct Needs {
+    /// Seconds of air left, up to [`MAX_BREATH`].
+    pub breath: f32,
+    /// In kcal, up to [`MAX_CALORIES`].
+    pub calories: f32,
+    /// Up to [`MAX_STAMINA`].
+    pub stamina: f32,
+    /// In °C.
+    pub body_temperature: f32,
+    /// Up to [`MAX_STRESS`].
+    pub stress: f32,
+}
+
+impl Default for Needs {
+    fn default() -> Self {
+        Self {
+            breath: MAX_BREATH,
+            calories: MAX_CALORIES,
+            stamina: MAX_STAMINA,
+            body_temperature: BODY_TEMPERATURE,
+            stress: 0.0,
+        }
+    }
+}
+
+/// What a colonist died of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cause {
+    Suffocation,
+    Hypothermia,
+    Hyperthermia,
+}
+
+impl Cause {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Suffocation => "suffocation",
+            Self::Hypothermia => "hypothermia",
+            Self::Hyperthermia => "hyperthermia",
+        }
+    }
+}
+
+impl Needs {
+    /// The need that killed the colonist, if one reached its deadly level.
+    pub fn death(&self) -> Option<Cause> {
+        if self.breath <= 0.0 {
+            Some(Cause::Suffocation)
+        } else if self.body_temperature < SURVIVABLE_TEMPERATURE.0 {
+            Some(Cause::Hypothermia)
+        } else if self.body_temperature > SURVIVABLE_TEMPERATURE.1 {
+            Some(Cause::Hyperthermia)
+        } else {
+            None
+        }
+    }
+
+    /// How many needs are out of their comfortable range.
+    pub fn discomforts(&self) -> u32 {
+        let (cold, hot) = COMFORTABLE_TEMPERATURE;
+        [
+            self.breath < MAX_BREATH / 2.0,
+            self.stamina < TIRED,
+            !(cold..=hot).contains(&self.body_temperature),
+        ]
+        .into_iter()
+        .filter(|discomfort| *discomfort)
+        .count() as u32
+    }
+}
+
+/// A sleeping colonist, getting its stamina back.
+#[derive(Component, Debug)]
+pub struct Asleep;
+
+/// A colonist too stressed to work.
+#[derive(Component, Debug)]
+pub struct Stressed;
+
+fn update_needs(
+    time: Res<Time>,
+    element_configs: Res<ElementConfigs>,
+    mut tiles: WorldTiles,
+    mut colonist_query: Query<(&mut Needs, &TilePos, Has<Asleep>), With<Colonist>>,
+) {
+    let delta = time.delta_secs();
+    let map_size = tiles.map_size().unwrap_or_default();
+    for (mut needs, feet, asleep) in colonist_query.iter_mut() {
+        let body = body_tiles(feet);
+        // Standing on the top row leaves the head out of the map, with no air to breathe
+        let inside = |tile: &&TilePos| tile.x < map_size.x && tile.y < map_size.y;
+
+        let head = body[body.len() - 1];
+        let breathing = Some(&head)
+            .filter(inside)
+            .and_then(|head| tiles.get(LayerType::Gas, head))
+            .is_some_and(|gas| BREATHABLE.contains(&gas.element) && gas.mass >= MIN_BREATHABLE_MASS);
+        needs.breath = if breathing {
+            (needs.breath + BREATH_RECOVERY * delta).min(MAX_BREATH)
+        } else {
+            (needs.breath - delta).max(0.0)
+        };
+
+        needs.calories = (needs.calories - CALORIE_BURN * delta).max(0.0);
+        needs.stamina = if asleep {
+            (needs.stamina + STAMINA_RECOVERY * delta).min(MAX_STAMINA)
+        } else {
+            (needs.stamina - STAMINA_DRAIN * delta).max(0.0)
+        };
+
+        // Each body tile takes its share of the skin
+        let contact = SKIN_CONDUCTANCE * delta / body.len() as f32;
+        for position in body.iter().filter(inside) {
+            needs.body_temperature =
+                tiles.exchange_heat(&element_configs, position, needs.body_temperature, BODY_HEAT_CAPACITY, contact);
+        }
+        needs.body_temperature += (BODY_TEMPERATURE - needs.body_temperature) * (REGULATION * delta).min(1.0);
+
+        let discomforts = needs.discomforts();
+        needs.stress = if discomforts > 0 {
+            (needs.stress + STRESS_GAIN * discomforts as f32 * delta).min(MAX_STRESS)
+        } else {
+            (needs.stress - STRESS_RECOVERY * delta).max(0.0)
+        };
+    }
+}
+
+/// Kills, stresses, calms, sends to sleep and wakes up colonists as their needs say.
+fn apply_needs(
+    mut commands: Commands,
+    colonist_query: Query<(Entity, &Colonist, &Needs, Has<Asleep>, Has<Stressed>, Has<Assignment>)>,
+) {
+    for (entity, colonist, needs, asleep, stressed, working) in colonist_query.iter() {
+        if let Some(cause) = needs.death() {
+            info!("{} died of {}", colonist.name, cause.label());
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        if !stressed && needs.stress >= STRESSED {
+            info!("{} is stressed out", colonist.name);
+            commands.entity(entity).insert(Stressed).remove::<(Assignment, NavGoal, Route)>();
+        } else if stressed && needs.stress < CALM {
+            commands.entity(entity).remove::<Stressed>();
+        }
+
+        if asleep && needs.stamina >= MAX_STAMINA {
+            commands.entity(entity).remove::<Asleep>();
+        } else if !asleep && (needs.stamina <= 0.0 || (needs.stamina < TIRED && !working)) {
+            commands.entity(entity).insert(Asleep).remove::<(Assignment, NavGoal, Route)>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{generate, run_ticks, HeadlessPlugin};
+    use crate::world::features::start_area;
+
+    #[test]
+    fn test_needs_thresholds() {
+        let mut needs = Needs::default();
+        assert_eq!(needs.death(), None);
+        assert_eq!(needs.discomforts(), 0);
+
+        needs.breath = 10.0;
+        needs.body_temperature = 33.0;
+        assert_eq!(needs.discomforts(), 2);
+        needs.body_temperature = 29.0;
+        assert_eq!(needs.death(), Some(Cause::Hypothermia));
+        needs.breath = 0.0;
+        assert_eq!(needs.death(), Some(Cause::Suffocation));
+    }
+
+    #[test]
+    fn test_colonist_survives_in_the_start_area() {
+        let size = UVec2::new(32, 24);
+        let mut app = App::new();
+        app.add_plugins((HeadlessPlugin, NeedsPlugin));
+        generate(&mut app, 42, size).unwrap();
+
+        let area = start_area(size);
+        let colonist = Colonist { name: "Test".into(), traits: Vec::new(), hue: 0.0 };
+        let feet = TilePos { x: area.min.x, y: area.min.y };
+        let entity = app.world_mut().spawn((colonist, Needs::default(), feet)).id();
+
+        // Ten minutes at 5 ticks a second
+        run_ticks(&mut app, 10 * 60 * 5).unwrap();
+        let needs = app.world().get::<Needs>(entity).expect("the colonist died");
+        assert_eq!(needs.breath, MAX_BREATH);
+        assert_eq!(needs.death(), None);
+    }
+}
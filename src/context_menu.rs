@@ -0,0 +1,179 @@
+//! A menu of actions on a tile, opened by long-pressing it on a touch screen.
+//!
+//! Options are picked by tapping them. Tapping anywhere else closes the menu; the tap still
+//! selects the tile under it, see [`crate::picking`].
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+use crate::actions::TouchGesture;
+use crate::orders::{OrderTool, Orders};
+use crate::picking::{is_on_ui, window_to_world, SelectedTile, TilePickingSystems};
+use crate::sandbox::{Sandbox, SandboxTool};
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::GameState;
+
+pub struct ContextMenuPlugin;
+
+impl Plugin for ContextMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            // After the picking, so taps on the menu are still seen as taps on the UI
+            handle_gestures
+                .after(TilePickingSystems)
+                .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+        )
+        .add_systems(OnExit(GameState::Playing), close_context_menu);
+    }
+}
+
+/// The open menu, and the tile it was opened on.
+#[derive(Component)]
+struct ContextMenu(TilePos);
+
+#[derive(Component, Clone, Copy)]
+enum ContextMenuOption {
+    /// Selects the tile and puts the tools away.
+    Inspect,
+    Order(OrderTool),
+    Tool(SandboxTool),
+}
+
+impl ContextMenuOption {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Inspect => "Inspect",
+            Self::Order(tool) => tool.label(),
+            Self::Tool(tool) => tool.label(),
+        }
+    }
+}
+
+/// Finds the tile under a touch and selects tiles.
+#[derive(SystemParam)]
+struct TouchPicking<'w, 's> {
+    camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<Camera2d>>,
+    node_query: Query<'w, 's, (&'static ComputedNode, &'static GlobalTransform, &'static InheritedVisibility)>,
+    tiles: WorldTiles<'w, 's>,
+    selected: ResMut<'w, SelectedTile>,
+}
+
+impl TouchPicking<'_, '_> {
+    /// The tile under `position` in the window, unless the UI is over it.
+    fn tile_at(&self, position: Vec2) -> Option<TilePos> {
+        if is_on_ui(&self.node_query, position) {
+            return None;
+        }
+        window_to_world(&self.camera_query, position)
+            .and_then(|world_position| self.tiles.tile_at(LayerType::Solid, world_position))
+    }
+}
+
+fn spawn_context_menu(commands: &mut Commands, position: Vec2, tile: TilePos) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(6.0)),
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0.05, 0.05, 0.05, 0.9)),
+            GlobalZIndex(1),
+            ContextMenu(tile),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(format!("Tile {}, {}", tile.x, tile.y)),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::linear_rgb(0.6, 0.6, 0.6)),
+            ));
+            let orders = OrderTool::ALL.map(ContextMenuOption::Order);
+            let tools = SandboxTool::ALL.map(ContextMenuOption::Tool);
+            for option in std::iter::once(ContextMenuOption::Inspect).chain(orders).chain(tools) {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::axes(Val::Px(12.0), Val::Px(8.0)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::linear_rgb(0.15, 0.15, 0.15)),
+                        option,
+                    ))
+                    .with_child((
+                        Text::new(option.label()),
+                        TextFont {
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+        });
+}
+
+fn handle_gestures(
+    mut commands: Commands,
+    mut gestures: EventReader<TouchGesture>,
+    mut picking: TouchPicking,
+    menu_query: Query<(Entity, &ContextMenu)>,
+    option_query: Query<(&ContextMenuOption, &ComputedNode, &GlobalTransform)>,
+    mut sandbox: ResMut<Sandbox>,
+    mut orders: ResMut<Orders>,
+) {
+    for gesture in gestures.read() {
+        match *gesture {
+            TouchGesture::LongPress { position } => {
+                let Some(tile) = picking.tile_at(position) else {
+                    continue;
+                };
+                for (entity, _) in menu_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+                spawn_context_menu(&mut commands, position, tile);
+            }
+            TouchGesture::Tap { position } => {
+                let Ok((entity, menu)) = menu_query.get_single() else {
+                    continue;
+                };
+                let option = option_query.iter().find_map(|(option, node, transform)| {
+                    let scale = node.inverse_scale_factor();
+                    let rect = Rect::from_center_size(transform.translation().truncate() * scale, node.size() * scale);
+                    rect.contains(position).then_some(*option)
+                });
+                match option {
+                    Some(ContextMenuOption::Inspect) => {
+                        sandbox.tool = SandboxTool::None;
+                        orders.tool = OrderTool::None;
+                        picking.selected.0 = Some(menu.0);
+                    }
+                    Some(ContextMenuOption::Order(tool)) => {
+                        sandbox.tool = SandboxTool::None;
+                        orders.tool = tool;
+                    }
+                    Some(ContextMenuOption::Tool(tool)) => sandbox.tool = tool,
+                    None => {}
+                }
+                commands.entity(entity).despawn_recursive();
+            }
+            TouchGesture::Pan { .. } | TouchGesture::Pinch { .. } => {}
+        }
+    }
+}
+
+fn close_context_menu(mut commands: Commands, menu_query: Query<Entity, With<ContextMenu>>) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
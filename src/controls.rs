@@ -0,0 +1,293 @@
+//! The controls screen, listing the bindings of every [`InputAction`] and remapping them.
+//!
+//! Every action has a keyboard and mouse column and a gamepad column. Clicking a binding waits
+//! for the next input of that kind; Escape cancels and Delete clears the binding. Changes are
+//! written to [`BindingsSettings::path`] right away.
+
+use bevy::prelude::*;
+
+use crate::actions::{BindingsSettings, InputAction, InputBinding, InputBindings};
+use crate::GameState;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Controls), spawn_controls_screen)
+            .add_systems(
+                Update,
+                (click_controls_buttons, capture_binding, update_binding_buttons)
+                    .chain()
+                    .run_if(in_state(GameState::Controls)),
+            )
+            .add_systems(OnExit(GameState::Controls), despawn_controls_screen);
+    }
+}
+
+/// The binding waiting for an input.
+#[derive(Resource, Default, Debug)]
+struct Rebinding(Option<PendingRebinding>);
+
+#[derive(Debug)]
+struct PendingRebinding {
+    action: InputAction,
+    gamepad: bool,
+    /// Set once the click that started the rebinding is released, so it doesn't get bound itself.
+    armed: bool,
+}
+
+#[derive(Component)]
+struct ControlsScreen;
+
+#[derive(Component)]
+struct ControlsHint;
+
+/// Shows and rebinds the bindings of one kind of an action.
+#[derive(Component)]
+struct BindingButton {
+    action: InputAction,
+    gamepad: bool,
+}
+
+#[derive(Component)]
+enum ControlsButton {
+    ResetDefaults,
+    Back,
+}
+
+const NORMAL: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const HOVERED: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+const WAITING: Color = Color::linear_rgb(0.35, 0.45, 0.25);
+
+fn text(value: impl Into<String>, font_size: f32) -> impl Bundle {
+    (
+        Text::new(value),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+    )
+}
+
+fn spawn_controls_screen(mut commands: Commands, bindings: Res<InputBindings>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ControlsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(text("Controls", 32.0));
+            parent.spawn((text("Click a binding to change it", 14.0), ControlsHint));
+
+            // Two tables side by side, each with an action, keyboard and gamepad column
+            let rows = InputAction::ALL.len().div_ceil(2);
+            parent
+                .spawn(Node {
+                    display: Display::Grid,
+                    grid_template_columns: (0..2)
+                        .flat_map(|_| [RepeatedGridTrack::auto(1), RepeatedGridTrack::px(1, 170.0), RepeatedGridTrack::px(1, 150.0)])
+                        .collect(),
+                    column_gap: Val::Px(8.0),
+                    row_gap: Val::Px(3.0),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for row in 0..rows {
+                        for action in [InputAction::ALL.get(row), InputAction::ALL.get(row + rows)] {
+                            let Some(action) = action else {
+                                continue;
+                            };
+                            parent.spawn(text(action.label(), 14.0));
+                            for gamepad in [false, true] {
+                                parent
+                                    .spawn((
+                                        Button,
+                                        Node {
+                                            padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                                            ..default()
+                                        },
+                                        BackgroundColor(NORMAL),
+                                        BindingButton { action: *action, gamepad },
+                                    ))
+                                    .with_child(text(bindings.label(*action, gamepad), 14.0));
+                            }
+                        }
+                    }
+                });
+
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (label, button) in [("Reset to defaults", ControlsButton::ResetDefaults), ("Back", ControlsButton::Back)] {
+                        parent
+                            .spawn((
+                                Button,
+                                Node {
+                                    padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(NORMAL),
+                                button,
+                            ))
+                            .with_child(text(label, 20.0));
+                    }
+                });
+        });
+}
+
+fn despawn_controls_screen(
+    mut commands: Commands,
+    mut rebinding: ResMut<Rebinding>,
+    screen_query: Query<Entity, With<ControlsScreen>>,
+) {
+    rebinding.0 = None;
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn save_bindings(bindings: &InputBindings, settings: &BindingsSettings) {
+    if let Err(error) = bindings.save(&settings.path) {
+        error!("Failed to save bindings to {:?}: {error}", settings.path);
+    }
+}
+
+fn click_controls_buttons(
+    binding_query: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+    button_query: Query<(&Interaction, &ControlsButton), Changed<Interaction>>,
+    settings: Res<BindingsSettings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if rebinding.0.is_some() {
+        return;
+    }
+    for (interaction, button) in binding_query.iter() {
+        if *interaction == Interaction::Pressed {
+            rebinding.0 = Some(PendingRebinding { action: button.action, gamepad: button.gamepad, armed: false });
+        }
+    }
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            ControlsButton::ResetDefaults => {
+                *bindings = InputBindings::default();
+                save_bindings(&bindings, &settings);
+            }
+            ControlsButton::Back => next_state.set(GameState::Menu),
+        }
+    }
+}
+
+fn capture_binding(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    settings: Res<BindingsSettings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(pending) = rebinding.0.as_mut() else {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            next_state.set(GameState::Menu);
+        }
+        return;
+    };
+    if !pending.armed {
+        pending.armed = mouse_input.get_pressed().next().is_none();
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Delete) {
+        bindings.clear(pending.action, pending.gamepad);
+        save_bindings(&bindings, &settings);
+        rebinding.0 = None;
+        return;
+    }
+
+    let binding = if pending.gamepad {
+        gamepads
+            .iter()
+            .find_map(|gamepad| gamepad.get_just_pressed().next().copied())
+            .map(InputBinding::Gamepad)
+    } else {
+        keyboard_input
+            .get_just_pressed()
+            .next()
+            .map(|key| InputBinding::Key(*key))
+            .or_else(|| mouse_input.get_just_pressed().next().map(|button| InputBinding::Mouse(*button)))
+    };
+    if let Some(binding) = binding {
+        bindings.rebind(pending.action, binding);
+        save_bindings(&bindings, &settings);
+        rebinding.0 = None;
+    }
+}
+
+fn update_binding_buttons(
+    bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+    mut button_query: Query<(&BindingButton, &Interaction, &mut BackgroundColor, &Children)>,
+    mut controls_button_query: Query<(&Interaction, &mut BackgroundColor), (With<ControlsButton>, Without<BindingButton>)>,
+    mut hint_query: Query<&mut Text, With<ControlsHint>>,
+    mut text_query: Query<&mut Text, Without<ControlsHint>>,
+) {
+    let waiting = |button: &BindingButton| {
+        rebinding
+            .0
+            .as_ref()
+            .is_some_and(|pending| pending.action == button.action && pending.gamepad == button.gamepad)
+    };
+    for (interaction, mut color) in controls_button_query.iter_mut() {
+        color.0 = if *interaction == Interaction::Hovered { HOVERED } else { NORMAL };
+    }
+    for (button, interaction, mut color, children) in button_query.iter_mut() {
+        color.0 = match (waiting(button), interaction) {
+            (true, _) => WAITING,
+            (false, Interaction::Hovered) => HOVERED,
+            (false, _) => NORMAL,
+        };
+        if !bindings.is_changed() && !rebinding.is_changed() {
+            continue;
+        }
+        let label = if waiting(button) { "...".to_string() } else { bindings.label(button.action, button.gamepad) };
+        if let Some(mut text) = children.first().and_then(|child| text_query.get_mut(*child).ok()) {
+            text.0 = label;
+        }
+    }
+
+    if rebinding.is_changed() {
+        if let Ok(mut hint) = hint_query.get_single_mut() {
+            hint.0 = match &rebinding.0 {
+                Some(pending) => format!(
+                    "Press a {} for \"{}\", Escape to cancel, Delete to clear",
+                    if pending.gamepad { "gamepad button" } else { "key or mouse button" },
+                    pending.action.label()
+                ),
+                None => "Click a binding to change it".to_string(),
+            };
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Makes entities living on the tiles, such as colonists, fall until they stand on a solid tile.
+//!
+//! Such entities have a [`Fall`] and a [`TilePos`], the tile they stand in. Their `Transform` is
+//! moved down while the tile below is free in the Solid layer, and snapped to the center of their
+//! tile when they land. The bottom row of the map holds everything up, and ladders hold up agents.
+//! Agents following a [`Route`] are moved by it instead.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+use crate::navigation::{NavAgent, NavGrid, Route};
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::GameState;
+
+pub struct GravityPlugin;
+
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GravitySettings>()
+            .register_type::<Fall>()
+            .add_systems(
+                Update,
+                apply_gravity
+                    .in_set(GravitySystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            );
+    }
+}
+
+/// Moves falling entities and updates their [`TilePos`].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GravitySystems;
+
+#[derive(Resource, Debug)]
+pub struct GravitySettings {
+    /// In world units/s², a tile being 16 units wide.
+    pub acceleration: f32,
+    /// Terminal velocity, in world units/s.
+    pub max_speed: f32,
+}
+
+impl Default for GravitySettings {
+    fn default() -> Self {
+        Self { acceleration: 600.0, max_speed: 480.0 }
+    }
+}
+
+/// Makes an entity fall when the tile below it is free.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Fall {
+    /// Downwards, in world units/s.
+    pub speed: f32,
+    /// Standing on a solid tile.
+    pub grounded: bool,
+}
+
+/// Moves `y` down towards `floor` for `delta` seconds, returning the new height and speed.
+///
+/// Entities below the floor, e.g. buried by a new tile, are pushed up onto it.
+pub fn fall_step(y: f32, speed: f32, floor: f32, settings: &GravitySettings, delta: f32) -> (f32, f32) {
+    if y <= floor {
+        return (floor, 0.0);
+    }
+    let speed = (speed + settings.acceleration * delta).min(settings.max_speed);
+    let y = y - speed * delta;
+    if y <= floor {
+        (floor, 0.0)
+    } else {
+        (y, speed)
+    }
+}
+
+/// The lowest tile an entity in `position` can fall to, the one above the first solid tile below.
+pub fn floor_below(tiles: &WorldTiles, position: &TilePos) -> TilePos {
+    let y = (0..=position.y)
+        .rev()
+        .find(|y| tiles.is_solid(&TilePos { x: position.x, y: *y }))
+        .map_or(0, |y| y + 1);
+    TilePos { x: position.x, y }
+}
+
+fn apply_gravity(
+    time: Res<Time>,
+    settings: Res<GravitySettings>,
+    tiles: WorldTiles,
+    grid: Res<NavGrid>,
+    mut fall_query: Query<(&mut Transform, &mut Fall, &mut TilePos, Has<NavAgent>), Without<Route>>,
+) {
+    for (mut transform, mut fall, mut position, is_agent) in fall_query.iter_mut() {
+        let Some(tile) = tiles.tile_at(LayerType::Solid, transform.translation.truncate()) else {
+            continue;
+        };
+        let floor_tile = if is_agent && grid.is_on_ladder(&tile) { tile } else { floor_below(&tiles, &tile) };
+        let Some(floor) = tiles.world_position(LayerType::Solid, &floor_tile) else {
+            continue;
+        };
+
+        let (y, speed) = fall_step(transform.translation.y, fall.speed, floor.y, &settings, time.delta_secs());
+        if y != transform.translation.y {
+            transform.translation.y = y;
+        }
+        fall.speed = speed;
+        fall.grounded = y == floor.y;
+
+        if let Some(tile) = tiles.tile_at(LayerType::Solid, transform.translation.truncate()) {
+            if *position != tile {
+                *position = tile;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fall_step() {
+        let settings = GravitySettings { acceleration: 100.0, max_speed: 50.0 };
+
+        let (y, speed) = fall_step(100.0, 0.0, 0.0, &settings, 0.1);
+        assert_eq!((y, speed), (99.0, 10.0));
+        // Speed is capped
+        let (_, speed) = fall_step(100.0, 45.0, 0.0, &settings, 0.1);
+        assert_eq!(speed, 50.0);
+        // Landing stops on the floor
+        assert_eq!(fall_step(2.0, 40.0, 0.0, &settings, 0.1), (0.0, 0.0));
+        // Buried entities pop out
+        assert_eq!(fall_step(-16.0, 0.0, 0.0, &settings, 0.1), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy_ecs_tilemap::tiles::TileStorage;
+use common::resources::MapSize;
+use simulation::temperature::HeatCell;
+use simulation::{SimulationRate, SimulationTick};
+
+use crate::resources::ElementConfigs;
+use crate::save::{write_save, Compression, SaveError, WorldSnapshot};
+use crate::states::generation::GenerationState;
+use crate::world::layer::{Layer, LayerType};
+use crate::world::tile::{TileElement, TileMass};
+use crate::world::{GenerationSeed, WorldPlugin};
+use crate::GameState;
+
+/// Frames to wait for the world generation before giving up.
+const MAX_GENERATION_FRAMES: u32 = 100;
+
+/// Frames to wait for the simulation to advance a tick before giving up.
+const MAX_TICK_FRAMES: u32 = 10;
+
+/// This plugin runs world generation and the simulation without windowing, rendering, audio or input.
+/// Every `App::update` advances the simulation by exactly one tick, so runs are reproducible for a given seed.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<GameState>()
+            .init_state::<GenerationState>()
+            .add_plugins((WorldPlugin, simulation::SimulationPlugin));
+
+        let step = app.world().resource::<SimulationRate>().rate.duration();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(step));
+    }
+}
+
+/// Generates a world of the given size from a seed, blocking until the generation is done.
+pub fn generate(app: &mut App, seed: u32, size: UVec2) -> Result<(), String> {
+    app.insert_resource(MapSize(size))
+        .insert_resource(GenerationSeed::new(seed));
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Playing);
+    app.world_mut()
+        .resource_mut::<NextState<GenerationState>>()
+        .set(GenerationState::Initializing);
+
+    for _ in 0..MAX_GENERATION_FRAMES {
+        app.update();
+        if app.world().resource::<State<GenerationState>>().is_done() {
+            return Ok(());
+        }
+    }
+    Err(format!("world generation did not finish within {MAX_GENERATION_FRAMES} frames"))
+}
+
+/// Runs the simulation for the given number of ticks.
+pub fn run_ticks(app: &mut App, ticks: u64) -> Result<(), String> {
+    let target = app.world().resource::<SimulationTick>().0 + ticks;
+    let mut stalled = 0;
+    while app.world().resource::<SimulationTick>().0 < target {
+        let tick = app.world().resource::<SimulationTick>().0;
+        app.update();
+        if app.world().resource::<SimulationTick>().0 == tick {
+            stalled += 1;
+            if stalled == MAX_TICK_FRAMES {
+                return Err(format!("the simulation did not advance past tick {tick} within {MAX_TICK_FRAMES} frames"));
+            }
+        } else {
+            stalled = 0;
+        }
+    }
+    Ok(())
+}
+
+pub fn save(app: &mut App, path: &Path) -> Result<(), SaveError> {
+    let mut snapshot = SystemState::<WorldSnapshot>::new(app.world_mut());
+    let save = snapshot.get(app.world()).capture()?;
+    write_save(path, &save, Compression::default())
+}
+
+#[derive(Default)]
+pub struct ElementStats {
+    pub tiles: u32,
+    pub mass: f32,
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+    temperature_sum: f32,
+}
+
+impl ElementStats {
+    pub fn mean_temperature(&self) -> f32 {
+        self.temperature_sum / self.tiles.max(1) as f32
+    }
+}
+
+/// Per-layer, per-element totals of the current world.
+pub struct WorldStats {
+    pub tick: u64,
+    pub layers: BTreeMap<String, BTreeMap<String, ElementStats>>,
+}
+
+impl WorldStats {
+    pub fn collect(app: &mut App) -> Self {
+        let mut layer_query = app.world_mut().query::<(&Layer, &TileStorage)>();
+        let mut tile_query = app.world_mut().query::<(&TileElement, &TileMass, &HeatCell)>();
+        let world = app.world();
+        let element_configs = world.resource::<ElementConfigs>();
+
+        let mut layers = BTreeMap::new();
+        for (layer, storage) in layer_query.iter(world) {
+            if layer.layer_type == LayerType::Background {
+                continue;
+            }
+            let elements: &mut BTreeMap<String, ElementStats> = layers.entry(format!("{:?}", layer.layer_type)).or_default();
+            for tile_entity in storage.iter().flatten() {
+                let Ok((element, mass, heat)) = tile_query.get(world, *tile_entity) else {
+                    continue;
+                };
+                let name = element_configs
+                    .get(element.0)
+                    .map_or_else(|| format!("#{}", element.0), |element| element.name.clone());
+                let temperature = heat.temperature.get_temperature();
+
+                let stats = elements.entry(name).or_insert_with(|| ElementStats {
+                    min_temperature: temperature,
+                    max_temperature: temperature,
+                    ..default()
+                });
+                stats.tiles += 1;
+                stats.mass += mass.0;
+                stats.temperature_sum += temperature;
+                stats.min_temperature = stats.min_temperature.min(temperature);
+                stats.max_temperature = stats.max_temperature.max(temperature);
+            }
+        }
+
+        Self { tick: world.resource::<SimulationTick>().0, layers }
+    }
+}
+
+impl fmt::Display for WorldStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "tick {}", self.tick)?;
+        writeln!(
+            f,
+            "{:<8} {:<16} {:>6} {:>12} {:>9} {:>9} {:>9}",
+            "layer", "element", "tiles", "mass (kg)", "min °C", "mean °C", "max °C"
+        )?;
+        for (layer, elements) in self.layers.iter() {
+            for (element, stats) in elements.iter() {
+                writeln!(
+                    f,
+                    "{:<8} {:<16} {:>6} {:>12.2} {:>9.2} {:>9.2} {:>9.2}",
+                    layer,
+                    element,
+                    stats.tiles,
+                    stats.mass,
+                    stats.min_temperature,
+                    stats.mean_temperature(),
+                    stats.max_temperature
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::read_save;
+
+    #[test]
+    fn test_generate_and_save() {
+        let path = std::env::temp_dir().join(format!("headless-{}.sav", std::process::id()));
+        let mut app = App::new();
+        app.add_plugins(HeadlessPlugin);
+        generate(&mut app, 42, UVec2::new(32, 24)).unwrap();
+        save(&mut app, &path).unwrap();
+
+        let save = read_save(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!((save.map_size, save.seed), ((32, 24), 42));
+        assert!(save.entities.orders.is_empty());
+    }
+}
@@ -1,70 +1,266 @@
-use bevy::{input::{mouse::MouseWheel, ButtonInput}, math::Vec3, prelude::*, render::camera::Camera};
+use bevy::{input::mouse::MouseWheel, prelude::*, window::PrimaryWindow};
 
-// A simple camera system for moving and zooming the camera.
-#[allow(dead_code)]
-pub fn movement(
-    time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera>>,
-) {
-    for (mut transform, mut ortho) in query.iter_mut() {
-        let mut direction = Vec3::ZERO;
+use crate::actions::{Actions, InputAction, TouchGesture};
+use crate::world::layer::Layer;
+use crate::GameState;
 
-        if keyboard_input.pressed(KeyCode::KeyA) {
-            direction -= Vec3::new(1.0, 0.0, 0.0);
-        }
+/// Zoom speed of the zoom actions, in scroll lines per second.
+const KEY_ZOOM_LINES_PER_SECOND: f32 = 10.0;
 
-        if keyboard_input.pressed(KeyCode::KeyD) {
-            direction += Vec3::new(1.0, 0.0, 0.0);
-        }
+// A simple camera for moving around the world and zooming, kept within the world's extents.
+pub struct CameraPlugin;
 
-        if keyboard_input.pressed(KeyCode::KeyW) {
-            direction += Vec3::new(0.0, 1.0, 0.0);
-        }
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraSettings>()
+            .init_resource::<CameraZoom>()
+            .add_systems(OnEnter(GameState::Playing), reset_zoom)
+            .add_systems(
+                Update,
+                (movement, drag_pan, touch_camera, zoom_scroll, smooth_zoom, clamp_camera)
+                    .chain()
+                    .in_set(CameraSystems)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
 
-        if keyboard_input.pressed(KeyCode::KeyS) {
-            direction -= Vec3::new(0.0, 1.0, 0.0);
-        }
+/// Moves the camera; systems converting the cursor into world space run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraSystems;
 
-        if keyboard_input.pressed(KeyCode::KeyZ) {
-            ortho.scale += 0.1;
-        }
+#[derive(Resource, Debug)]
+pub struct CameraSettings {
+    /// Keyboard panning speed at a zoom of 1, in px/s. Zoomed out, the camera pans faster.
+    pub pan_speed: f32,
+    /// Smallest `OrthographicProjection::scale`, the closest the camera gets.
+    pub min_zoom: f32,
+    /// Largest `OrthographicProjection::scale`, the farthest the camera gets.
+    pub max_zoom: f32,
+    /// Zoom factor per scroll line.
+    pub zoom_step: f32,
+    /// How fast the zoom catches up with its target, per second. Higher is snappier.
+    pub zoom_smoothing: f32,
+}
 
-        if keyboard_input.pressed(KeyCode::KeyX) {
-            ortho.scale -= 0.1;
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            pan_speed: 500.0,
+            min_zoom: 0.25,
+            max_zoom: 4.0,
+            zoom_step: 0.1,
+            zoom_smoothing: 12.0,
         }
+    }
+}
+
+/// The zoom the camera is easing towards, and the cursor position it is anchored on.
+#[derive(Resource, Debug)]
+pub struct CameraZoom {
+    pub target: f32,
+    /// Cursor position in the window, `None` zooms around the centre of the screen.
+    pub anchor: Option<Vec2>,
+}
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self { target: 1.0, anchor: None }
+    }
+}
 
-        if ortho.scale < 0.5 {
-            ortho.scale = 0.5;
+impl CameraZoom {
+    /// Multiplies the target zoom by `factor`, within the limits of the settings.
+    pub fn zoom_by(&mut self, factor: f32, settings: &CameraSettings) {
+        self.target = (self.target * factor).clamp(settings.min_zoom, settings.max_zoom);
+    }
+}
+
+/// Offset of a cursor position from the centre of the window, in world units at a zoom of 1.
+fn cursor_offset(cursor: Vec2, window_size: Vec2) -> Vec2 {
+    let offset = cursor - window_size / 2.0;
+    // The window's y axis points down, the world's up
+    Vec2::new(offset.x, -offset.y)
+}
+
+/// Camera position after zooming from `old_scale` to `new_scale`, keeping the point under `offset` in place.
+pub fn zoom_around(translation: Vec2, offset: Vec2, old_scale: f32, new_scale: f32) -> Vec2 {
+    translation + offset * (old_scale - new_scale)
+}
+
+/// Keeps the view inside `bounds`, or centred on them when they are smaller than the view.
+pub fn clamp_to_bounds(center: Vec2, half_view: Vec2, bounds: Rect) -> Vec2 {
+    let min = bounds.min + half_view;
+    let max = bounds.max - half_view;
+    Vec2::new(
+        if min.x <= max.x { center.x.clamp(min.x, max.x) } else { bounds.center().x },
+        if min.y <= max.y { center.y.clamp(min.y, max.y) } else { bounds.center().y },
+    )
+}
+
+fn reset_zoom(settings: Res<CameraSettings>, mut zoom: ResMut<CameraZoom>, query: Query<&OrthographicProjection, With<Camera2d>>) {
+    let scale = query.get_single().map_or(1.0, |ortho| ortho.scale);
+    *zoom = CameraZoom { target: scale.clamp(settings.min_zoom, settings.max_zoom), anchor: None };
+}
+
+pub fn movement(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    settings: Res<CameraSettings>,
+    mut zoom: ResMut<CameraZoom>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    for (mut transform, ortho) in query.iter_mut() {
+        let direction = actions.camera_pan;
+
+        // Zooming with buttons and triggers goes around the centre of the screen
+        if actions.camera_zoom != 0.0 {
+            zoom.zoom_by((1.0 + settings.zoom_step).powf(actions.camera_zoom * time.delta_secs() * KEY_ZOOM_LINES_PER_SECOND), &settings);
+            zoom.anchor = None;
         }
 
-        
+        // Only x and y change, the camera has to keep its z to see the layers
+        transform.translation += (time.delta_secs() * direction * settings.pan_speed * ortho.scale).extend(0.0);
+    }
+}
 
-        let z = transform.translation.z;
-        transform.translation += time.delta_secs() * direction * 500.;
-        // Important! We need to restore the Z values when moving the camera around.
-        // Bevy has a specific camera setup and this can mess with how our layers are shown.
-        transform.translation.z = z;
+/// Grabs the world while [`InputAction::DragPan`] is held, so the point under the cursor follows it.
+pub fn drag_pan(
+    actions: Res<Actions>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut last_cursor: Local<Option<Vec2>>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    let cursor = window_query.get_single().ok().and_then(|window| window.cursor_position());
+    if !actions.pressed(InputAction::DragPan) {
+        *last_cursor = None;
+        return;
+    }
+
+    if let (Some(cursor), Some(last)) = (cursor, *last_cursor) {
+        let delta = cursor - last;
+        for (mut transform, ortho) in query.iter_mut() {
+            transform.translation.x -= delta.x * ortho.scale;
+            transform.translation.y += delta.y * ortho.scale;
+        }
+    }
+    *last_cursor = cursor;
+}
+
+/// Two fingers pan the camera and pinch to zoom around their midpoint.
+pub fn touch_camera(
+    mut gestures: EventReader<TouchGesture>,
+    settings: Res<CameraSettings>,
+    mut zoom: ResMut<CameraZoom>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    for gesture in gestures.read() {
+        match *gesture {
+            TouchGesture::Pan { delta } => {
+                for (mut transform, ortho) in query.iter_mut() {
+                    transform.translation.x -= delta.x * ortho.scale;
+                    transform.translation.y += delta.y * ortho.scale;
+                }
+            }
+            TouchGesture::Pinch { center, scale } => {
+                zoom.zoom_by(1.0 / scale, &settings);
+                zoom.anchor = Some(center);
+            }
+            TouchGesture::Tap { .. } | TouchGesture::LongPress { .. } => {}
+        }
     }
 }
 
 pub fn zoom_scroll(
     mut evr_scroll: EventReader<MouseWheel>,
-    mut query: Query<&mut OrthographicProjection, With<Camera>>,
+    settings: Res<CameraSettings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut zoom: ResMut<CameraZoom>,
 ) {
     use bevy::input::mouse::MouseScrollUnit;
     for ev in evr_scroll.read() {
-        match ev.unit {
-            MouseScrollUnit::Line => {
-                for mut ortho in query.iter_mut() {
-                    ortho.scale += ev.y * 0.1;
-                }
-            }
-            MouseScrollUnit::Pixel => {
-                for mut ortho in query.iter_mut() {
-                    ortho.scale += ev.y * 0.01;
-                }
-            }
+        let lines = match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y * 0.1,
+        };
+        zoom.zoom_by((1.0 + settings.zoom_step).powf(lines), &settings);
+        zoom.anchor = window_query.get_single().ok().and_then(|window| window.cursor_position());
+    }
+}
+
+/// Eases the zoom towards its target, keeping the world point under the anchor in place.
+pub fn smooth_zoom(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    zoom: Res<CameraZoom>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    let window_size = window_query.get_single().map_or(Vec2::ZERO, |window| window.size());
+    let offset = zoom
+        .anchor
+        .map_or(Vec2::ZERO, |anchor| cursor_offset(anchor, window_size));
+    let blend = 1.0 - (-settings.zoom_smoothing * time.delta_secs()).exp();
+
+    for (mut transform, mut ortho) in query.iter_mut() {
+        if ortho.scale == zoom.target {
+            continue;
+        }
+        let mut scale = ortho.scale.lerp(zoom.target, blend);
+        if (scale - zoom.target).abs() < 1e-4 {
+            scale = zoom.target;
+        }
+        let translation = zoom_around(transform.translation.truncate(), offset, ortho.scale, scale);
+        transform.translation = translation.extend(transform.translation.z);
+        ortho.scale = scale;
+    }
+}
+
+/// Keeps the camera over the world.
+pub fn clamp_camera(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    layer_query: Query<(&bevy_ecs_tilemap::map::TilemapSize, &bevy_ecs_tilemap::map::TilemapGridSize, &GlobalTransform), With<Layer>>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    // Every layer covers the same area
+    let Some((size, grid_size, layer_transform)) = layer_query.iter().next() else {
+        return;
+    };
+    let window_size = window_query.get_single().map_or(Vec2::ZERO, |window| window.size());
+    // Tiles are centred on their position, the first one on the layer's origin
+    let grid = Vec2::new(grid_size.x, grid_size.y);
+    let origin = layer_transform.translation().truncate() - grid / 2.0;
+    let bounds = Rect::from_corners(origin, origin + Vec2::new(size.x as f32, size.y as f32) * grid);
+
+    for (mut transform, ortho) in query.iter_mut() {
+        let center = clamp_to_bounds(transform.translation.truncate(), window_size * ortho.scale / 2.0, bounds);
+        if center != transform.translation.truncate() {
+            transform.translation = center.extend(transform.translation.z);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_keeps_the_point_under_the_cursor() {
+        let offset = cursor_offset(Vec2::new(700.0, 100.0), Vec2::new(800.0, 600.0));
+        let translation = Vec2::new(50.0, 20.0);
+        let world_point = translation + offset * 2.0;
+
+        let zoomed = zoom_around(translation, offset, 2.0, 0.5);
+        assert_eq!(zoomed + offset * 0.5, world_point);
+    }
+
+    #[test]
+    fn test_clamp_to_bounds() {
+        let bounds = Rect::new(0.0, 0.0, 1000.0, 500.0);
+
+        assert_eq!(clamp_to_bounds(Vec2::new(-50.0, 250.0), Vec2::new(100.0, 100.0), bounds), Vec2::new(100.0, 250.0));
+        assert_eq!(clamp_to_bounds(Vec2::new(980.0, 0.0), Vec2::new(100.0, 100.0), bounds), Vec2::new(900.0, 100.0));
+        // The view is taller than the world
+        assert_eq!(clamp_to_bounds(Vec2::new(500.0, 0.0), Vec2::new(100.0, 400.0), bounds), Vec2::new(500.0, 250.0));
+    }
+}
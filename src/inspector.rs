@@ -0,0 +1,173 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+use simulation::temperature::Temperature;
+use std::fmt::Write;
+
+use crate::colonist::{body_tiles, Asleep, Colonist, Needs, Skill, Stats, Stressed};
+use crate::items::Item;
+use crate::jobs::JobBoard;
+use crate::picking::{HoveredTile, SelectedTile, TilePickingSystems};
+use crate::resources::ElementConfigs;
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::world::Grid;
+use crate::GameState;
+
+/// This plugin shows what is at the selected tile, or the hovered one, on every layer.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GenerationState::Done), spawn_inspector.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                update_inspector
+                    .after(TilePickingSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_inspector);
+    }
+}
+
+#[derive(Component)]
+struct Inspector;
+
+#[derive(Component)]
+struct InspectorText;
+
+fn spawn_inspector(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                top: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0.05, 0.05, 0.05, 0.8)),
+            Visibility::Hidden,
+            Inspector,
+        ))
+        .with_child((
+            Text::default(),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            InspectorText,
+        ));
+}
+
+fn despawn_inspector(mut commands: Commands, inspector_query: Query<Entity, With<Inspector>>) {
+    for entity in inspector_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// The layers of a tile.
+#[derive(SystemParam)]
+struct TileLayers<'w, 's> {
+    element_configs: Res<'w, ElementConfigs>,
+    grid_query: Query<'w, 's, &'static Grid>,
+    tiles: WorldTiles<'w, 's>,
+}
+
+impl TileLayers<'_, '_> {
+    /// One line per layer, from the front.
+    fn describe(&self, position: &TilePos, description: &mut String) {
+        let Ok(grid) = self.grid_query.get_single() else {
+            return;
+        };
+        for layer in grid.get_layers().iter().rev() {
+            if layer.layer_type == LayerType::Background {
+                continue;
+            }
+            let Some(tile) = self.tiles.get(layer.layer_type, position) else {
+                continue;
+            };
+            let name = self.element_configs.get(tile.element).map_or("Unknown", |element| element.name.as_str());
+            write!(
+                description,
+                "\n{:?}: {name}, {:.1} kg, {:.1} °C, conductivity {:.2}",
+                layer.layer_type, tile.mass, tile.temperature, tile.conductivity
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// The colonists, items and orders in a tile.
+#[derive(SystemParam)]
+struct TileOccupants<'w, 's> {
+    element_configs: Res<'w, ElementConfigs>,
+    board: Res<'w, JobBoard>,
+    colonist_query: Query<
+        'w,
+        's,
+        (&'static Colonist, &'static Stats, &'static Needs, &'static TilePos, Has<Asleep>, Has<Stressed>),
+    >,
+    item_query: Query<'w, 's, (&'static Item, &'static Temperature, &'static TilePos)>,
+    name_query: Query<'w, 's, &'static Name>,
+}
+
+impl TileOccupants<'_, '_> {
+    fn describe(&self, position: &TilePos, description: &mut String) {
+        let colonists = self.colonist_query.iter().filter(|(.., feet, _, _)| body_tiles(feet).contains(position));
+        for (colonist, stats, needs, _, asleep, stressed) in colonists {
+            let traits: Vec<_> = colonist.traits.iter().map(|colonist_trait| colonist_trait.label()).collect();
+            let skills: Vec<_> = Skill::ALL.iter().map(|skill| format!("{} {}", skill.label(), stats.get(*skill))).collect();
+            write!(description, "\nNPC: {} ({})\n  {}", colonist.name, traits.join(", "), skills.join(", ")).unwrap();
+            write!(
+                description,
+                "\n  Breath {:.0} s, {:.0} kcal, stamina {:.0}, {:.1} °C, stress {:.0}",
+                needs.breath, needs.calories, needs.stamina, needs.body_temperature, needs.stress
+            )
+            .unwrap();
+            if asleep {
+                write!(description, ", asleep").unwrap();
+            }
+            if stressed {
+                write!(description, ", stressed").unwrap();
+            }
+        }
+        for (item, temperature, _) in self.item_query.iter().filter(|(.., item_position)| *item_position == position) {
+            let name = self.element_configs.get(item.element).map_or("Unknown", |element| element.name.as_str());
+            write!(description, "\nItem: {name}, {:.1} kg, {:.1} °C", item.mass, temperature.value).unwrap();
+        }
+        for (_, job) in self.board.at(position) {
+            let worker = job.worker.and_then(|worker| self.name_query.get(worker).ok());
+            write!(description, "\nOrder: {}, priority {}", job.kind.label(), job.priority).unwrap();
+            if let Some(worker) = worker {
+                write!(description, ", {worker} on it").unwrap();
+            }
+        }
+    }
+}
+
+fn update_inspector(
+    hovered: Res<HoveredTile>,
+    selected: Res<SelectedTile>,
+    layers: TileLayers,
+    occupants: TileOccupants,
+    mut inspector_query: Query<&mut Visibility, With<Inspector>>,
+    mut text_query: Query<&mut Text, With<InspectorText>>,
+) {
+    let (Ok(mut visibility), Ok(mut text)) = (inspector_query.get_single_mut(), text_query.get_single_mut()) else {
+        return;
+    };
+    let Some(position) = selected.0.or(hovered.position) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let mut description = format!("Tile ({}, {}){}", position.x, position.y, if selected.0.is_some() { " [selected]" } else { "" });
+    layers.describe(&position, &mut description);
+    occupants.describe(&position, &mut description);
+
+    text.0 = description;
+    *visibility = Visibility::Inherited;
+}
@@ -0,0 +1,199 @@
+//! Loose items lying on the map, such as the debris left by digging.
+//!
+//! An item is a lump of one element with its own [`Temperature`], standing in a tile like the
+//! colonists and falling with [`crate::gravity`]. Colonists carry items by taking their [`Fall`]
+//! away, the item then follows them until it is dropped. Saved items are loaded lying where they were.
+//!
+//! Items of the same element lying in the same tile stack into one, and items trade heat with the
+//! liquid or gas of their tile.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_tilemap::tiles::TilePos;
+use simulation::temperature::Temperature;
+
+use crate::gravity::{Fall, GravitySystems};
+use crate::jobs::JobBoard;
+use crate::navigation::NavigationSystems;
+use crate::resources::ElementConfigs;
+use crate::states::generation::GenerationState;
+use crate::world::layer::{LayerType, LAYER_Z_STEP};
+use crate::world::tiles::WorldTiles;
+use crate::GameState;
+
+/// Where a carried item is held, from the feet of the colonist.
+const CARRY_OFFSET: Vec2 = Vec2::new(0.0, 14.0);
+
+/// Heat flowing between an item and its tile, in kW/K.
+const ITEM_CONDUCTANCE: f32 = 0.05;
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Item>()
+            .add_systems(
+                Update,
+                (carry_items, stack_items, exchange_item_heat)
+                    .chain()
+                    .after(NavigationSystems)
+                    .after(GravitySystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_items);
+    }
+}
+
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Item {
+    pub element: u32,
+    /// In kg.
+    pub mass: f32,
+}
+
+/// An item held by a colonist.
+#[derive(Component, Debug)]
+pub struct Carried {
+    pub by: Entity,
+}
+
+/// Spawns `mass` kg of `element` at `temperature` °C in `position`, whose center is at `center` in
+/// world space.
+pub fn spawn_item(
+    commands: &mut Commands,
+    element: u32,
+    mass: f32,
+    temperature: f32,
+    color: Color,
+    position: TilePos,
+    center: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            Name::new("Item"),
+            Item { element, mass },
+            Temperature { value: temperature },
+            Fall::default(),
+            position,
+            // In front of the ladders
+            Transform::from_translation(center.extend((LayerType::Solid.id() as f32 + 0.75) * LAYER_Z_STEP)),
+            Visibility::default(),
+        ))
+        // Lying at the bottom of the tile
+        .with_child((Sprite::from_color(color, Vec2::new(12.0, 6.0)), Transform::from_xyz(0.0, -5.0, 0.0)))
+        .id()
+}
+
+/// Picks `item` up for `carrier`.
+pub fn pick_up(commands: &mut Commands, item: Entity, carrier: Entity) {
+    commands.entity(item).remove::<Fall>().insert(Carried { by: carrier });
+}
+
+/// Lets go of `item`, which falls from where it was held.
+pub fn drop_item(commands: &mut Commands, item: Entity) {
+    commands.entity(item).remove::<Carried>().insert(Fall::default());
+}
+
+/// Mass and temperature of two lumps of the same element put together.
+pub fn combine(mass: f32, temperature: f32, other_mass: f32, other_temperature: f32) -> (f32, f32) {
+    let total = mass + other_mass;
+    if total <= 0.0 {
+        return (0.0, temperature);
+    }
+    (total, (mass * temperature + other_mass * other_temperature) / total)
+}
+
+/// Keeps carried items in the hands of their carrier, dropping those whose carrier is gone.
+fn carry_items(
+    mut commands: Commands,
+    tiles: WorldTiles,
+    carrier_query: Query<&Transform, Without<Item>>,
+    mut item_query: Query<(Entity, &Carried, &mut Transform, &mut TilePos), With<Item>>,
+) {
+    for (entity, carried, mut transform, mut position) in item_query.iter_mut() {
+        let Ok(carrier) = carrier_query.get(carried.by) else {
+            drop_item(&mut commands, entity);
+            continue;
+        };
+        let held = carrier.translation.truncate() + CARRY_OFFSET;
+        transform.translation.x = held.x;
+        transform.translation.y = held.y;
+        if let Some(tile) = tiles.tile_at(LayerType::Solid, carrier.translation.truncate()) {
+            if *position != tile {
+                *position = tile;
+            }
+        }
+    }
+}
+
+/// Merges the items of the same element lying in the same tile. Items some job is after are kept,
+/// the others going into them.
+fn stack_items(
+    mut commands: Commands,
+    board: Res<JobBoard>,
+    mut item_query: Query<(Entity, &mut Item, &mut Temperature, &TilePos, &Fall), Without<Carried>>,
+) {
+    let mut items: Vec<_> = item_query
+        .iter()
+        .filter(|(.., fall)| fall.grounded)
+        .map(|(entity, item, _, position, _)| {
+            let reserved = board.iter().any(|(_, job)| job.item == Some(entity));
+            (entity, item.element, *position, reserved)
+        })
+        .collect();
+    items.sort_by_key(|(entity, .., reserved)| (!reserved, *entity));
+
+    let mut stacks: HashMap<(TilePos, u32), Entity> = HashMap::default();
+    for (entity, element, position, reserved) in items {
+        let Some(&stack) = stacks.get(&(position, element)) else {
+            stacks.insert((position, element), entity);
+            continue;
+        };
+        if reserved {
+            continue;
+        }
+        let Ok([(_, mut stack_item, mut stack_temperature, ..), (_, item, temperature, ..)]) =
+            item_query.get_many_mut([stack, entity])
+        else {
+            continue;
+        };
+        (stack_item.mass, stack_temperature.value) =
+            combine(stack_item.mass, stack_temperature.value, item.mass, temperature.value);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Warms or cools items towards the liquid or gas of their tile, and the tile towards them.
+fn exchange_item_heat(
+    time: Res<Time>,
+    element_configs: Res<ElementConfigs>,
+    mut tiles: WorldTiles,
+    mut item_query: Query<(&Item, &mut Temperature, &TilePos)>,
+) {
+    let contact = ITEM_CONDUCTANCE * time.delta_secs();
+    for (item, mut temperature, position) in item_query.iter_mut() {
+        let heat_capacity = element_configs.get(item.element).map_or(0.0, |config| item.mass * config.specific_heat);
+        let value = tiles.exchange_heat(&element_configs, position, temperature.value, heat_capacity, contact);
+        if value != temperature.value {
+            temperature.value = value;
+        }
+    }
+}
+
+fn despawn_items(mut commands: Commands, item_query: Query<Entity, With<Item>>) {
+    for entity in item_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combine() {
+        assert_eq!(combine(100.0, 10.0, 300.0, 30.0), (400.0, 25.0));
+        assert_eq!(combine(0.0, 10.0, 0.0, 30.0), (0.0, 10.0));
+    }
+}
@@ -0,0 +1,256 @@
+//! The jobs waiting for a colonist, and who works on which.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+use serde::{Deserialize, Serialize};
+
+use crate::colonist::{Skill, Stats};
+
+/// Lowest priority, jobs are taken from the highest.
+pub const MIN_PRIORITY: u32 = 1;
+pub const MAX_PRIORITY: u32 = 9;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JobKind {
+    /// Digs out the Solid tile.
+    Dig,
+    /// Builds in the tile once its materials are there.
+    Build,
+    /// Brings an item to a tile.
+    Deliver,
+    /// Clears the liquid off the tile.
+    Mop,
+}
+
+impl JobKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Dig => "Dig",
+            Self::Build => "Build",
+            Self::Deliver => "Deliver",
+            Self::Mop => "Mop",
+        }
+    }
+
+    /// The skill speeding the job up.
+    pub fn skill(self) -> Skill {
+        match self {
+            Self::Dig => Skill::Digging,
+            Self::Build => Skill::Building,
+            Self::Deliver | Self::Mop => Skill::Strength,
+        }
+    }
+
+    /// Seconds of work for a colonist with no skill.
+    pub fn work(self) -> f32 {
+        match self {
+            Self::Dig => 6.0,
+            Self::Build => 8.0,
+            Self::Deliver => 0.5,
+            Self::Mop => 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Job {
+    pub kind: JobKind,
+    /// The tile worked on.
+    pub position: TilePos,
+    /// From [`MIN_PRIORITY`] to [`MAX_PRIORITY`].
+    pub priority: u32,
+    pub skill: Skill,
+    /// Colonists below this level in [`Self::skill`] leave the job to others.
+    pub min_level: u32,
+    /// An [`crate::items::Item`] to fetch before going to work.
+    pub item: Option<Entity>,
+    /// The colonist who claimed the job.
+    pub worker: Option<Entity>,
+}
+
+impl Job {
+    /// An unclaimed job anyone can take.
+    pub fn new(kind: JobKind, position: TilePos, priority: u32) -> Self {
+        Self {
+            kind,
+            position,
+            priority: priority.clamp(MIN_PRIORITY, MAX_PRIORITY),
+            skill: kind.skill(),
+            min_level: 0,
+            item: None,
+            worker: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.worker.is_none()
+    }
+
+    /// Whether a colonist with `stats` is skilled enough.
+    pub fn accepts(&self, stats: &Stats) -> bool {
+        stats.get(self.skill) >= self.min_level
+    }
+}
+
+/// Every job posted by the player, by id. A tile has at most one job of each kind.
+#[derive(Resource, Default, Debug)]
+pub struct JobBoard {
+    jobs: BTreeMap<JobId, Job>,
+    next_id: u64,
+}
+
+impl JobBoard {
+    /// Adds `job`, or updates the priority of the same job on the same tile.
+    pub fn post(&mut self, job: Job) -> JobId {
+        if let Some((id, existing)) = self
+            .jobs
+            .iter_mut()
+            .find(|(_, existing)| existing.kind == job.kind && existing.position == job.position)
+        {
+            existing.priority = job.priority;
+            return *id;
+        }
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        self.jobs.insert(id, job);
+        id
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&Job> {
+        self.jobs.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (JobId, &Job)> {
+        self.jobs.iter().map(|(id, job)| (*id, job))
+    }
+
+    pub fn at(&self, position: &TilePos) -> impl Iterator<Item = (JobId, &Job)> {
+        let position = *position;
+        self.iter().filter(move |(_, job)| job.position == position)
+    }
+
+    /// Takes the job off the board, whether it was done or cancelled.
+    pub fn remove(&mut self, id: JobId) -> Option<Job> {
+        self.jobs.remove(&id)
+    }
+
+    pub fn clear(&mut self) {
+        self.jobs.clear();
+    }
+
+    /// Gives an open job to `worker`, returning whether it was open.
+    pub fn claim(&mut self, id: JobId, worker: Entity) -> bool {
+        match self.jobs.get_mut(&id) {
+            Some(job) if job.is_open() => {
+                job.worker = Some(worker);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Puts a job back on offer.
+    pub fn release(&mut self, id: JobId) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.worker = None;
+        }
+    }
+
+    /// The open jobs a colonist with `stats` standing in `position` can take, highest priority
+    /// first, then closest as the crow flies.
+    pub fn available(&self, stats: &Stats, position: &TilePos) -> Vec<(JobId, &Job)> {
+        let distance = |job: &Job| {
+            let dx = job.position.x.abs_diff(position.x);
+            let dy = job.position.y.abs_diff(position.y);
+            dx * dx + dy * dy
+        };
+        let mut jobs: Vec<_> = self.iter().filter(|(_, job)| job.is_open() && job.accepts(stats)).collect();
+        jobs.sort_by_key(|(_, job)| (std::cmp::Reverse(job.priority), distance(job)));
+        jobs
+    }
+}
+
+/// The tiles a worker `height` tiles tall can stand in to work on `target`: in the neighbouring
+/// columns, with the target anywhere from below its feet to above its head. Builders don't stand
+/// in what they build.
+pub fn work_spots(kind: JobKind, target: &TilePos, height: u32) -> Vec<TilePos> {
+    let mut spots = Vec::new();
+    for dx in -1..=1 {
+        for dy in -(height as i32)..=1 {
+            let in_body = dx == 0 && dy <= 0 && dy > -(height as i32);
+            let (x, y) = (target.x as i32 + dx, target.y as i32 + dy);
+            if x >= 0 && y >= 0 && !(kind == JobKind::Build && in_body) {
+                spots.push(TilePos { x: x as u32, y: y as u32 });
+            }
+        }
+    }
+    spots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_and_claim() {
+        let mut board = JobBoard::default();
+        let position = TilePos { x: 3, y: 4 };
+        let dig = board.post(Job::new(JobKind::Dig, position, 5));
+        // Posting again only changes the priority
+        assert_eq!(board.post(Job::new(JobKind::Dig, position, 12)), dig);
+        assert_eq!(board.get(dig).unwrap().priority, MAX_PRIORITY);
+        let mop = board.post(Job::new(JobKind::Mop, position, 5));
+        assert_ne!(mop, dig);
+        assert_eq!(board.at(&position).count(), 2);
+
+        let worker = Entity::from_raw(1);
+        assert!(board.claim(dig, worker));
+        assert!(!board.claim(dig, Entity::from_raw(2)));
+        board.release(dig);
+        assert!(board.get(dig).unwrap().is_open());
+        assert!(board.remove(dig).is_some());
+        assert!(!board.claim(dig, worker));
+    }
+
+    #[test]
+    fn test_available_jobs_are_sorted() {
+        let mut board = JobBoard::default();
+        let far = board.post(Job::new(JobKind::Dig, TilePos { x: 20, y: 0 }, 5));
+        let near = board.post(Job::new(JobKind::Dig, TilePos { x: 2, y: 0 }, 5));
+        let urgent = board.post(Job::new(JobKind::Mop, TilePos { x: 40, y: 0 }, 8));
+        let mut skilled = Job::new(JobKind::Dig, TilePos { x: 1, y: 0 }, 9);
+        skilled.min_level = 3;
+        board.post(skilled);
+        let claimed = board.post(Job::new(JobKind::Dig, TilePos { x: 0, y: 1 }, 9));
+        board.claim(claimed, Entity::from_raw(1));
+
+        let stats = Stats { digging: 2, ..default() };
+        let ids: Vec<JobId> = board
+            .available(&stats, &TilePos { x: 0, y: 0 })
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, [urgent, near, far]);
+    }
+
+    #[test]
+    fn test_work_spots() {
+        let target = TilePos { x: 5, y: 5 };
+        let spots = work_spots(JobKind::Dig, &target, 2);
+        assert_eq!(spots.len(), 12);
+        // Standing on it, or with it at head height
+        assert!(spots.contains(&TilePos { x: 5, y: 6 }));
+        assert!(spots.contains(&TilePos { x: 5, y: 4 }));
+        assert!(!spots.contains(&TilePos { x: 5, y: 2 }));
+
+        let spots = work_spots(JobKind::Build, &target, 2);
+        assert_eq!(spots.len(), 10);
+        assert!(!spots.contains(&TilePos { x: 5, y: 4 }));
+        assert_eq!(work_spots(JobKind::Mop, &TilePos { x: 0, y: 0 }, 2).len(), 4);
+    }
+}
@@ -0,0 +1,304 @@
+//! Gives the work posted on the [`JobBoard`] to the colonists.
+//!
+//! Idle colonists who are awake and not [`Stressed`] claim the open job with the highest priority
+//! they are skilled enough for, the closest by path among equals, and walk to a tile from which
+//! they can reach it, fetching its item on the way if it has one. Once there, they work at a pace
+//! set by their skill and a [`JobCompleted`] is sent when they are done; what the job does to the
+//! world is up to whoever posted it, see [`crate::orders`].
+//!
+//! A job is given back to the board when its colonist can't find a way to it anymore, or is
+//! interrupted: knocked off its feet, despawned, or its [`Assignment`] taken away. The item it
+//! carried for the job is dropped.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+use crate::colonist::{Asleep, Colonist, Stats, Stressed};
+use crate::gravity::Fall;
+use crate::items::{drop_item, pick_up, Carried, Item};
+use crate::navigation::{NavAgent, NavGoal, NavGrid, NavPath, NavigationSystems, Route};
+use crate::states::generation::GenerationState;
+use crate::GameState;
+
+pub use board::{work_spots, Job, JobBoard, JobId, JobKind, MAX_PRIORITY, MIN_PRIORITY};
+
+mod board;
+
+/// Jobs tried per idle colonist at every claim, the closest as the crow flies first.
+const MAX_SEARCHES: usize = 16;
+
+/// Work speed gained per skill level, relative to an unskilled colonist.
+const SPEED_PER_LEVEL: f32 = 0.25;
+
+pub struct JobsPlugin;
+
+impl Plugin for JobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<JobBoard>()
+            .init_resource::<JobSettings>()
+            .add_event::<JobCompleted>()
+            .add_systems(
+                Update,
+                (release_jobs, claim_jobs, work_jobs)
+                    .chain()
+                    .in_set(JobSystems)
+                    .before(NavigationSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_jobs);
+    }
+}
+
+/// Hands out jobs and works on them, sending [`JobCompleted`].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JobSystems;
+
+#[derive(Resource, Debug)]
+pub struct JobSettings {
+    /// How often idle colonists look for a job, each look costing a few path searches.
+    pub claim: Timer,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self { claim: Timer::from_seconds(0.5, TimerMode::Repeating) }
+    }
+}
+
+/// The job a colonist claimed, and the tile it is heading to.
+#[derive(Component, Debug)]
+pub struct Assignment {
+    pub job: JobId,
+    /// The item of the job while it is still to be picked up, `spot` being next to it until then.
+    pub fetch: Option<Entity>,
+    pub spot: TilePos,
+    /// Seconds of work done, skill included.
+    pub progress: f32,
+}
+
+/// A job was done, and taken off the board.
+#[derive(Event, Debug)]
+pub struct JobCompleted {
+    pub job: Job,
+    pub worker: Entity,
+}
+
+/// Work speed of a colonist with `level` in the skill of the job.
+pub fn work_rate(level: u32) -> f32 {
+    1.0 + SPEED_PER_LEVEL * level as f32
+}
+
+pub fn clear_jobs(mut commands: Commands, mut board: ResMut<JobBoard>, worker_query: Query<Entity, With<Assignment>>) {
+    board.clear();
+    for entity in worker_query.iter() {
+        commands.entity(entity).remove::<Assignment>();
+    }
+}
+
+/// Drops the items `worker` is carrying.
+fn drop_carried(commands: &mut Commands, carried_query: &Query<(Entity, &Carried)>, worker: Entity) {
+    for (item, _) in carried_query.iter().filter(|(_, carried)| carried.by == worker) {
+        drop_item(commands, item);
+    }
+}
+
+/// Puts the job of `worker` back on offer, dropping the item it carries for it.
+fn give_up(
+    commands: &mut Commands,
+    board: &mut JobBoard,
+    carried_query: &Query<(Entity, &Carried)>,
+    id: JobId,
+    worker: Entity,
+) {
+    if let Some(item) = board.get(id).and_then(|job| job.item) {
+        if carried_query.get(item).is_ok_and(|(_, carried)| carried.by == worker) {
+            drop_item(commands, item);
+        }
+    }
+    board.release(id);
+}
+
+/// Gives back the jobs whose colonist stopped working on them, and stops colonists whose job is gone.
+fn release_jobs(
+    mut commands: Commands,
+    mut board: ResMut<JobBoard>,
+    carried_query: Query<(Entity, &Carried)>,
+    worker_query: Query<(Entity, &Assignment, &TilePos, Has<NavGoal>, Has<Route>, Option<&Fall>)>,
+) {
+    for (entity, assignment, position, has_goal, has_route, fall) in worker_query.iter() {
+        if board.get(assignment.job).is_none_or(|job| job.worker != Some(entity)) {
+            // Cancelled, the job and what it said to carry are gone
+            drop_carried(&mut commands, &carried_query, entity);
+            commands.entity(entity).remove::<(Assignment, NavGoal, Route)>();
+            continue;
+        }
+        let knocked_off = !has_route && fall.is_some_and(|fall| !fall.grounded);
+        // The goal is dropped when there's no path to it
+        let unreachable = !has_goal && *position != assignment.spot;
+        if knocked_off || unreachable {
+            debug!("{entity} gives up on {:?}", assignment.job);
+            give_up(&mut commands, &mut board, &carried_query, assignment.job, entity);
+            commands.entity(entity).remove::<(Assignment, NavGoal, Route)>();
+        }
+    }
+
+    // Colonists despawned or interrupted since they claimed
+    let orphaned: Vec<(JobId, Entity)> = board
+        .iter()
+        .filter_map(|(id, job)| job.worker.map(|worker| (id, worker)))
+        .filter(|(id, worker)| {
+            !worker_query
+                .get(*worker)
+                .is_ok_and(|(_, assignment, ..)| assignment.job == *id)
+        })
+        .collect();
+    for (id, worker) in orphaned {
+        give_up(&mut commands, &mut board, &carried_query, id, worker);
+    }
+}
+
+/// Where a job is worked from, or where its item is fetched from.
+fn job_spots(job: &Job, item_query: &Query<&TilePos, (With<Item>, Without<Carried>)>, height: u32) -> Option<Vec<TilePos>> {
+    match job.item {
+        Some(item) => item_query
+            .get(item)
+            .ok()
+            .map(|position| work_spots(JobKind::Deliver, position, height)),
+        None => Some(work_spots(job.kind, &job.position, height)),
+    }
+}
+
+/// The open job a colonist with `stats` takes: the highest priority it can reach, the closest by
+/// path among equals.
+fn find_job(
+    board: &JobBoard,
+    grid: &NavGrid,
+    item_query: &Query<&TilePos, (With<Item>, Without<Carried>)>,
+    stats: &Stats,
+    position: &TilePos,
+    height: u32,
+) -> Option<(JobId, NavPath)> {
+    let mut best: Option<(u32, JobId, NavPath)> = None;
+    for (id, job) in board.available(stats, position).into_iter().take(MAX_SEARCHES) {
+        if best.as_ref().is_some_and(|(priority, ..)| job.priority < *priority) {
+            break;
+        }
+        let Some(path) = job_spots(job, item_query, height).and_then(|spots| grid.find_path_to_any(position, &spots, height))
+        else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|(_, _, best)| path.cost < best.cost) {
+            best = Some((job.priority, id, path));
+        }
+    }
+    best.map(|(_, id, path)| (id, path))
+}
+
+fn claim_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut settings: ResMut<JobSettings>,
+    mut board: ResMut<JobBoard>,
+    grid: Res<NavGrid>,
+    item_query: Query<&TilePos, (With<Item>, Without<Carried>)>,
+    idle_query: Query<
+        (Entity, &NavAgent, &Stats, &TilePos, Option<&Fall>),
+        (With<Colonist>, Without<Assignment>, Without<NavGoal>, Without<Asleep>, Without<Stressed>),
+    >,
+) {
+    if !settings.claim.tick(time.delta()).just_finished() {
+        return;
+    }
+    for (entity, agent, stats, position, fall) in idle_query.iter() {
+        if fall.is_some_and(|fall| !fall.grounded) {
+            continue;
+        }
+        let Some((id, path)) = find_job(&board, &grid, &item_query, stats, position, agent.height) else {
+            continue;
+        };
+        board.claim(id, entity);
+        let fetch = board.get(id).and_then(|job| job.item);
+        let spot = path.steps.last().map_or(*position, |step| step.position);
+        commands
+            .entity(entity)
+            .insert((Assignment { job: id, fetch, spot, progress: 0.0 }, NavGoal(spot)));
+    }
+}
+
+fn work_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut board: ResMut<JobBoard>,
+    grid: Res<NavGrid>,
+    item_query: Query<&TilePos, (With<Item>, Without<Carried>)>,
+    mut completed: EventWriter<JobCompleted>,
+    mut worker_query: Query<(Entity, &NavAgent, &mut Assignment, &TilePos, &Stats), (Without<NavGoal>, Without<Route>)>,
+) {
+    for (entity, agent, mut assignment, position, stats) in worker_query.iter_mut() {
+        if *position != assignment.spot {
+            continue;
+        }
+        let Some(job) = board.get(assignment.job) else {
+            continue;
+        };
+
+        if let Some(item) = assignment.fetch {
+            // The item may have been moved or taken by someone else meanwhile
+            let within_reach = item_query
+                .get(item)
+                .is_ok_and(|item_position| work_spots(JobKind::Deliver, item_position, agent.height).contains(position));
+            let spots = work_spots(job.kind, &job.position, agent.height);
+            match grid.find_path_to_any(position, &spots, agent.height).filter(|_| within_reach) {
+                Some(path) => {
+                    pick_up(&mut commands, item, entity);
+                    assignment.fetch = None;
+                    assignment.spot = path.steps.last().map_or(*position, |step| step.position);
+                    commands.entity(entity).insert(NavGoal(assignment.spot));
+                }
+                None => {
+                    board.release(assignment.job);
+                    commands.entity(entity).remove::<Assignment>();
+                }
+            }
+            continue;
+        }
+
+        assignment.progress += time.delta_secs() * work_rate(stats.get(job.skill));
+        if assignment.progress < job.kind.work() {
+            continue;
+        }
+        if let Some(job) = board.remove(assignment.job) {
+            completed.send(JobCompleted { job, worker: entity });
+        }
+        commands.entity(entity).remove::<Assignment>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn test_cancelled_delivery_drops_the_item() {
+        let mut world = World::new();
+        let mut board = JobBoard::default();
+        let position = TilePos { x: 2, y: 1 };
+        let worker = world.spawn(position).id();
+        let item = world.spawn((Item { element: 0, mass: 10.0 }, Carried { by: worker }, position)).id();
+        let job = board.post(Job { item: Some(item), ..Job::new(JobKind::Deliver, TilePos { x: 5, y: 1 }, 5) });
+        board.claim(job, worker);
+        world
+            .entity_mut(worker)
+            .insert((Assignment { job, fetch: None, spot: position, progress: 0.0 }, NavGoal(position)));
+        // Cancelled while carrying
+        board.remove(job);
+        world.insert_resource(board);
+
+        world.run_system_once(release_jobs).unwrap();
+        assert!(!world.entity(worker).contains::<Assignment>());
+        assert!(!world.entity(item).contains::<Carried>());
+        assert!(world.entity(item).contains::<Fall>());
+    }
+}
@@ -2,24 +2,53 @@
 
 mod actions;
 mod audio;
+mod colonist;
+mod context_menu;
+mod controls;
+mod gravity;
+pub mod headless;
 mod loading;
 mod menu;
-mod player;
+mod navigation;
+mod orders;
+mod overlay;
 mod world;
 mod helpers;
+mod inspector;
+mod items;
+mod jobs;
+mod picking;
 mod resources;
+mod sandbox;
+mod save;
+mod speed;
 mod states;
 
 use crate::actions::ActionsPlugin;
 use crate::audio::InternalAudioPlugin;
+use crate::colonist::ColonistPlugin;
+use crate::context_menu::ContextMenuPlugin;
+use crate::controls::ControlsPlugin;
+use crate::gravity::GravityPlugin;
+use crate::helpers::camera::CameraPlugin;
+use crate::inspector::InspectorPlugin;
+use crate::items::ItemPlugin;
+use crate::jobs::JobsPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
-use crate::player::PlayerPlugin;
+use crate::navigation::NavigationPlugin;
+use crate::orders::OrdersPlugin;
+use crate::overlay::OverlayPlugin;
+use crate::picking::TilePickingPlugin;
+use crate::sandbox::SandboxPlugin;
+use crate::save::SavePlugin;
+use crate::speed::SimulationSpeedPlugin;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
+use bevy_ecs_tilemap::TilemapPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use states::generation::GenerationState;
 
@@ -35,6 +64,8 @@ enum GameState {
     Playing,
     // Here the menu is drawn and waiting for player interaction
     Menu,
+    // Here the input bindings are listed and remapped, reached from the menu
+    Controls,
 }
 
 pub struct GamePlugin;
@@ -47,9 +78,28 @@ impl Plugin for GamePlugin {
             MenuPlugin,
             ActionsPlugin,
             InternalAudioPlugin,
-            PlayerPlugin,
+            CameraPlugin,
+            TilemapPlugin,
             world::WorldPlugin,
             simulation::SimulationPlugin,
+            SavePlugin,
+            OverlayPlugin,
+            world::visibility::LayerVisibilityPlugin,
+        ))
+        // Plugin tuples are limited to 15 elements
+        .add_plugins((
+            TilePickingPlugin,
+            InspectorPlugin,
+            SandboxPlugin,
+            SimulationSpeedPlugin,
+            ControlsPlugin,
+            ContextMenuPlugin,
+            GravityPlugin,
+            NavigationPlugin,
+            ColonistPlugin,
+            ItemPlugin,
+            JobsPlugin,
+            OrdersPlugin,
         ));
 
         #[cfg(debug_assertions)]
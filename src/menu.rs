@@ -1,4 +1,6 @@
+use crate::actions::{Actions, InputAction};
 use crate::loading::TextureAssets;
+use crate::save::{LoadWorld, SaveSettings};
 use crate::states::generation::GenerationState;
 use crate::GameState;
 use bevy::prelude::*;
@@ -11,6 +13,7 @@ impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Menu), setup_menu)
             .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .add_systems(Update, return_to_menu.run_if(in_state(GameState::Playing)))
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
@@ -33,9 +36,18 @@ impl Default for ButtonColors {
 #[derive(Component)]
 struct Menu;
 
-fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
+fn setup_menu(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    save_settings: Res<SaveSettings>,
+    camera_query: Query<(), With<Camera2d>>,
+) {
     info!("menu");
-    commands.spawn((Camera2d, Msaa::Off));
+    // The camera is kept around when coming back from `GameState::Playing`
+    if camera_query.is_empty() {
+        commands.spawn((Camera2d, Msaa::Off));
+    }
+    let has_save = save_settings.path.exists();
     commands
         .spawn((
             Node {
@@ -61,7 +73,7 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                         ..Default::default()
                     },
                     BackgroundColor(button_colors.normal),
-                    button_colors,
+                    ButtonColors::default(),
                     ChangeState(GameState::Playing),
                 ))
                 .with_child((
@@ -72,6 +84,54 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                     },
                     TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
                 ));
+            if has_save {
+                children
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(140.0),
+                            height: Val::Px(50.0),
+                            margin: UiRect::top(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        BackgroundColor(button_colors.normal),
+                        ButtonColors::default(),
+                        LoadGame,
+                    ))
+                    .with_child((
+                        Text::new("Load"),
+                        TextFont {
+                            font_size: 40.0,
+                            ..default()
+                        },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+            children
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(50.0),
+                        margin: UiRect::top(Val::Px(10.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    BackgroundColor(button_colors.normal),
+                    ButtonColors::default(),
+                    ChangeState(GameState::Controls),
+                ))
+                .with_child((
+                    Text::new("Controls"),
+                    TextFont {
+                        font_size: 30.0,
+                        ..default()
+                    },
+                    TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ));
         });
     commands
         .spawn((
@@ -169,9 +229,13 @@ struct ChangeState(GameState);
 #[derive(Component)]
 struct OpenLink(&'static str);
 
+#[derive(Component)]
+struct LoadGame;
+
 fn click_play_button(
     mut next_state: ResMut<NextState<GameState>>,
     mut next_generation_state: ResMut<NextState<GenerationState>>,
+    mut load_events: EventWriter<LoadWorld>,
     mut interaction_query: Query<
         (
             &Interaction,
@@ -179,20 +243,25 @@ fn click_play_button(
             &ButtonColors,
             Option<&ChangeState>,
             Option<&OpenLink>,
+            Option<&LoadGame>,
         ),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
+    for (interaction, mut color, button_colors, change_state, open_link, load_game) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 if let Some(state) = change_state {
                     next_state.set(state.0.clone());
-                    next_generation_state.set(GenerationState::Initializing);
+                    if state.0 == GameState::Playing {
+                        next_generation_state.set(GenerationState::Initializing);
+                    }
                 } else if let Some(link) = open_link {
                     if let Err(error) = webbrowser::open(link.0) {
                         warn!("Failed to open link {error:?}");
                     }
+                } else if load_game.is_some() {
+                    load_events.send_default();
                 }
             }
             Interaction::Hovered => {
@@ -205,6 +274,13 @@ fn click_play_button(
     }
 }
 
+// The world is saved and dropped when leaving `GameState::Playing`
+fn return_to_menu(actions: Res<Actions>, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(InputAction::ReturnToMenu) {
+        next_state.set(GameState::Menu);
+    }
+}
+
 fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
     for entity in menu.iter() {
         commands.entity(entity).despawn_recursive();
@@ -0,0 +1,391 @@
+//! What agents can walk through, and the A* search finding their way across it.
+//!
+//! Moves follow platformer rules: agents walk along floors, climb ladders, jump one tile up or
+//! down to a neighbouring column and fall off ledges. Jump point search only prunes grids of
+//! uniform cost, so liquids slowing agents down rule it out here.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+pub const WALK_COST: f32 = 1.0;
+/// Per tile climbed, up or down.
+pub const CLIMB_COST: f32 = 1.5;
+/// Jumping up to a neighbouring column.
+pub const JUMP_COST: f32 = 2.0;
+/// Per tile dropped, on top of stepping off the ledge.
+pub const FALL_COST: f32 = 0.5;
+/// Agents don't drop further than this on purpose.
+pub const MAX_DROP: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    Walk,
+    Climb,
+    /// One tile up or down to a neighbouring column.
+    Jump,
+    /// Off a ledge, more than one tile down.
+    Fall,
+}
+
+/// A move along a path, and the tile it ends in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NavStep {
+    pub position: TilePos,
+    pub kind: MoveKind,
+}
+
+/// The moves from a tile to another, and their total cost.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavPath {
+    pub steps: Vec<NavStep>,
+    pub cost: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct NavCell {
+    solid: bool,
+    ladder: bool,
+    /// How full of liquid the tile is, from 0 to 1.
+    liquid: f32,
+}
+
+/// The walkable space of the map, kept in sync with the Solid and Liquid layers and the ladders.
+#[derive(Resource, Default, Debug)]
+pub struct NavGrid {
+    size: UVec2,
+    cells: Vec<NavCell>,
+    /// Bumped on every change, so paths found earlier can be checked again.
+    revision: u64,
+}
+
+/// An open node of the search, ordered by lowest estimated cost first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OpenNode {
+    estimate: f32,
+    index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl NavGrid {
+    pub fn new(size: UVec2) -> Self {
+        Self { size, cells: vec![NavCell::default(); (size.x * size.y) as usize], revision: 0 }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && (x as u32) < self.size.x && (y as u32) < self.size.y)
+            .then(|| (y as u32 * self.size.x + x as u32) as usize)
+    }
+
+    fn position(&self, index: usize) -> TilePos {
+        TilePos { x: index as u32 % self.size.x, y: index as u32 / self.size.x }
+    }
+
+    fn update(&mut self, position: &TilePos, update: impl FnOnce(&mut NavCell)) {
+        let Some(index) = self.index(position.x as i32, position.y as i32) else {
+            return;
+        };
+        let before = self.cells[index];
+        update(&mut self.cells[index]);
+        let after = self.cells[index];
+        if (before.solid, before.ladder, before.liquid) != (after.solid, after.ladder, after.liquid) {
+            self.revision += 1;
+        }
+    }
+
+    pub fn set_solid(&mut self, position: &TilePos, solid: bool) {
+        self.update(position, |cell| cell.solid = solid);
+    }
+
+    pub fn set_ladder(&mut self, position: &TilePos, ladder: bool) {
+        self.update(position, |cell| cell.ladder = ladder);
+    }
+
+    /// `fill` is the share of the tile taken by liquid.
+    pub fn set_liquid(&mut self, position: &TilePos, fill: f32) {
+        self.update(position, |cell| cell.liquid = fill.clamp(0.0, 1.0));
+    }
+
+    /// Removes every ladder, before adding them back.
+    pub fn clear_ladders(&mut self) {
+        if self.cells.iter().any(|cell| cell.ladder) {
+            self.cells.iter_mut().for_each(|cell| cell.ladder = false);
+            self.revision += 1;
+        }
+    }
+
+    /// Tiles outside the map are solid.
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_none_or(|index| self.cells[index].solid)
+    }
+
+    fn is_ladder(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_some_and(|index| self.cells[index].ladder)
+    }
+
+    /// Whether an agent `height` tiles tall fits with its feet in `(x, y)`.
+    fn fits(&self, x: i32, y: i32, height: u32) -> bool {
+        (0..height as i32).all(|dy| !self.is_solid(x, y + dy))
+    }
+
+    /// Held up by a solid tile, a ladder, or the bottom of the map.
+    fn is_supported(&self, x: i32, y: i32) -> bool {
+        y == 0 || self.is_solid(x, y - 1) || self.is_on_ladder(&TilePos { x: x as u32, y: y as u32 })
+    }
+
+    /// Whether an agent in `position` holds on to a ladder, in it or right below.
+    pub fn is_on_ladder(&self, position: &TilePos) -> bool {
+        let (x, y) = (position.x as i32, position.y as i32);
+        self.is_ladder(x, y) || self.is_ladder(x, y - 1)
+    }
+
+    /// Whether an agent `height` tiles tall can stay in `position`.
+    pub fn can_stand(&self, position: &TilePos, height: u32) -> bool {
+        let (x, y) = (position.x as i32, position.y as i32);
+        self.fits(x, y, height) && self.is_supported(x, y)
+    }
+
+    fn slowdown(&self, x: i32, y: i32) -> f32 {
+        1.0 + self.index(x, y).map_or(0.0, |index| self.cells[index].liquid)
+    }
+
+    /// How much slower moving into a tile is, from its liquid.
+    pub fn slowdown_at(&self, position: &TilePos) -> f32 {
+        self.slowdown(position.x as i32, position.y as i32)
+    }
+
+    /// Every move from `position`, with its cost.
+    pub fn moves(&self, position: &TilePos, height: u32) -> Vec<(NavStep, f32)> {
+        let (x, y) = (position.x as i32, position.y as i32);
+        let mut moves = vec![];
+        if !self.fits(x, y, height) {
+            return moves;
+        }
+        let mut push = |x: i32, y: i32, kind: MoveKind, cost: f32| {
+            moves.push((NavStep { position: TilePos { x: x as u32, y: y as u32 }, kind }, cost * self.slowdown(x, y)));
+        };
+
+        if self.is_ladder(x, y + 1) && self.fits(x, y + 1, height) {
+            push(x, y + 1, MoveKind::Climb, CLIMB_COST);
+        }
+        if self.is_ladder(x, y - 1) && self.fits(x, y - 1, height) {
+            push(x, y - 1, MoveKind::Climb, CLIMB_COST);
+        }
+
+        for dx in [-1, 1] {
+            let nx = x + dx;
+            if self.fits(nx, y, height) {
+                if self.is_supported(nx, y) {
+                    push(nx, y, MoveKind::Walk, WALK_COST);
+                } else if let Some(drop) = (1..=MAX_DROP as i32)
+                    .take_while(|drop| self.fits(nx, y - drop, height))
+                    .find(|drop| self.is_supported(nx, y - drop))
+                {
+                    let kind = if drop == 1 { MoveKind::Jump } else { MoveKind::Fall };
+                    push(nx, y - drop, kind, WALK_COST + FALL_COST * drop as f32);
+                }
+            }
+            // Jumping up needs room above the head first
+            if self.fits(nx, y + 1, height) && !self.is_solid(x, y + height as i32) && self.is_supported(nx, y + 1) {
+                push(nx, y + 1, MoveKind::Jump, JUMP_COST);
+            }
+        }
+        moves
+    }
+
+    /// Whether `step` can still be taken from `from`, after the grid changed.
+    pub fn is_valid(&self, from: &TilePos, step: &NavStep, height: u32) -> bool {
+        self.moves(from, height).iter().any(|(candidate, _)| candidate == step)
+    }
+
+    /// A lower bound of the cost between two tiles, every move covering at least as much.
+    fn heuristic(from: &TilePos, to: &TilePos) -> f32 {
+        let dx = from.x.abs_diff(to.x) as f32;
+        let dy = from.y.abs_diff(to.y) as f32;
+        dx * WALK_COST + dy * FALL_COST
+    }
+
+    /// The cheapest moves from `start` to `goal`, empty if already there. `None` when unreachable.
+    pub fn find_path(&self, start: &TilePos, goal: &TilePos, height: u32) -> Option<NavPath> {
+        self.find_path_to_any(start, std::slice::from_ref(goal), height)
+    }
+
+    /// The cheapest moves from `start` to whichever of `goals` is the closest.
+    pub fn find_path_to_any(&self, start: &TilePos, goals: &[TilePos], height: u32) -> Option<NavPath> {
+        let start_index = self.index(start.x as i32, start.y as i32)?;
+        let goals: Vec<TilePos> = goals
+            .iter()
+            .filter(|goal| *goal == start || self.can_stand(goal, height))
+            .copied()
+            .collect();
+        if goals.is_empty() {
+            return None;
+        }
+        let heuristic = |position: &TilePos| {
+            goals
+                .iter()
+                .map(|goal| Self::heuristic(position, goal))
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        let mut costs = vec![f32::INFINITY; self.cells.len()];
+        let mut came_from: Vec<Option<(usize, MoveKind)>> = vec![None; self.cells.len()];
+        let mut open = BinaryHeap::new();
+        costs[start_index] = 0.0;
+        open.push(OpenNode { estimate: heuristic(start), index: start_index });
+
+        while let Some(OpenNode { estimate, index }) = open.pop() {
+            let position = self.position(index);
+            if goals.contains(&position) {
+                let mut steps = vec![];
+                let mut current = index;
+                while let Some((previous, kind)) = came_from[current] {
+                    steps.push(NavStep { position: self.position(current), kind });
+                    current = previous;
+                }
+                steps.reverse();
+                return Some(NavPath { steps, cost: costs[index] });
+            }
+            // Stale entry, a cheaper way here was found since
+            if estimate > costs[index] + heuristic(&position) {
+                continue;
+            }
+            for (step, cost) in self.moves(&position, height) {
+                let Some(next) = self.index(step.position.x as i32, step.position.y as i32) else {
+                    continue;
+                };
+                let cost = costs[index] + cost;
+                if cost < costs[next] {
+                    costs[next] = cost;
+                    came_from[next] = Some((index, step.kind));
+                    open.push(OpenNode { estimate: cost + heuristic(&step.position), index: next });
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid from rows of text, top row first: `#` is solid and `H` a ladder.
+    fn parse(rows: &[&str]) -> NavGrid {
+        let size = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+        let mut grid = NavGrid::new(size);
+        for (row, line) in rows.iter().enumerate() {
+            for (x, tile) in line.chars().enumerate() {
+                let position = TilePos { x: x as u32, y: size.y - 1 - row as u32 };
+                match tile {
+                    '#' => grid.set_solid(&position, true),
+                    'H' => grid.set_ladder(&position, true),
+                    _ => {}
+                }
+            }
+        }
+        grid
+    }
+
+    fn kinds(path: &NavPath) -> Vec<MoveKind> {
+        path.steps.iter().map(|step| step.kind).collect()
+    }
+
+    #[test]
+    fn test_walk_and_jump() {
+        let grid = parse(&[
+            "......",
+            "......",
+            "....##",
+            "######",
+        ]);
+        let path = grid.find_path(&TilePos { x: 0, y: 1 }, &TilePos { x: 5, y: 2 }, 2).unwrap();
+        use MoveKind::*;
+        assert_eq!(kinds(&path), [Walk, Walk, Walk, Jump, Walk]);
+        assert_eq!(path.steps.last().unwrap().position, TilePos { x: 5, y: 2 });
+        assert_eq!(path.cost, 6.0);
+        assert_eq!(grid.find_path(&TilePos { x: 0, y: 1 }, &TilePos { x: 0, y: 1 }, 2), Some(NavPath::default()));
+        // The closest goal wins, goals in the ground are skipped
+        let goals = [TilePos { x: 5, y: 2 }, TilePos { x: 2, y: 0 }, TilePos { x: 3, y: 1 }];
+        let path = grid.find_path_to_any(&TilePos { x: 0, y: 1 }, &goals, 2).unwrap();
+        assert_eq!(path.steps.last().unwrap().position, TilePos { x: 3, y: 1 });
+    }
+
+    #[test]
+    fn test_ladders_and_falls() {
+        let grid = parse(&[
+            "......",
+            "......",
+            "###H..",
+            "...H..",
+            "...H..",
+            "...H..",
+            "######",
+        ]);
+        // Too high to jump, but not to climb
+        let up = grid.find_path(&TilePos { x: 5, y: 1 }, &TilePos { x: 0, y: 5 }, 2).unwrap();
+        assert!(kinds(&up).contains(&MoveKind::Climb));
+        // Dropping off the ledge is quicker than climbing back down
+        let down = grid.find_path(&TilePos { x: 0, y: 5 }, &TilePos { x: 5, y: 1 }, 2).unwrap();
+        assert_eq!(down.steps[3], NavStep { position: TilePos { x: 4, y: 1 }, kind: MoveKind::Fall });
+
+        let walled = parse(&[
+            "......",
+            "......",
+            "..#...",
+            "######",
+        ]);
+        // Taller agents have no room to jump over
+        assert!(walled.find_path(&TilePos { x: 0, y: 1 }, &TilePos { x: 5, y: 1 }, 3).is_none());
+        assert!(walled.find_path(&TilePos { x: 0, y: 1 }, &TilePos { x: 5, y: 1 }, 2).is_some());
+    }
+
+    #[test]
+    fn test_liquid_and_changes() {
+        let mut grid = parse(&[
+            "........",
+            "........",
+            "........",
+            "########",
+        ]);
+        let start = TilePos { x: 0, y: 1 };
+        let goal = TilePos { x: 7, y: 1 };
+        let dry = grid.find_path(&start, &goal, 2).unwrap();
+        assert_eq!(dry.cost, 7.0);
+
+        let revision = grid.revision();
+        for x in 1..6 {
+            grid.set_liquid(&TilePos { x, y: 1 }, 1.0);
+        }
+        assert!(grid.revision() > revision);
+        assert_eq!(grid.find_path(&start, &goal, 2).unwrap().cost, 12.0);
+
+        grid.set_solid(&TilePos { x: 3, y: 1 }, true);
+        grid.set_solid(&TilePos { x: 3, y: 2 }, true);
+        assert!(!grid.is_valid(&TilePos { x: 2, y: 1 }, &dry.steps[2], 2));
+        assert!(grid.find_path(&start, &goal, 2).is_none());
+    }
+}
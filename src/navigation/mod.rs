@@ -0,0 +1,312 @@
+//! Moves agents across the map along paths found on the [`NavGrid`].
+//!
+//! The grid mirrors the Solid and Liquid layers, updated from [`TileChanged`] events, and the
+//! [`Ladder`]s, placed by the sandbox or built by the colonists. Giving an agent a [`NavGoal`] plans a [`Route`]
+//! to it once the agent stands on something; routes broken by later changes are planned again.
+//! While following a route, an agent is moved by it rather than by gravity.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+use crate::gravity::{Fall, GravitySystems};
+use crate::resources::ElementConfigs;
+use crate::states::generation::GenerationState;
+use crate::world::layer::{LayerType, LAYER_Z_STEP};
+use crate::world::tiles::{TileChangeKind, TileChanged, WorldTiles};
+use crate::GameState;
+
+pub use grid::{MoveKind, NavGrid, NavPath, NavStep};
+
+mod grid;
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .init_resource::<NavigationSettings>()
+            .register_type::<Ladder>()
+            .add_systems(OnEnter(GenerationState::Done), update_nav_grid.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (invalidate_routes, plan_routes, follow_routes)
+                    .chain()
+                    .in_set(NavigationSystems)
+                    .before(GravitySystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(
+                Update,
+                draw_routes
+                    .after(NavigationSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(
+                PostUpdate,
+                (update_ladders, update_nav_grid)
+                    .chain()
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_nav_grid);
+    }
+}
+
+/// Plans and follows routes, before gravity moves whatever isn't on one.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NavigationSystems;
+
+#[derive(Resource, Debug)]
+pub struct NavigationSettings {
+    /// Draws the routes agents follow.
+    pub debug_routes: bool,
+}
+
+impl Default for NavigationSettings {
+    fn default() -> Self {
+        Self { debug_routes: cfg!(debug_assertions) }
+    }
+}
+
+/// Lets agents climb through a tile, see [`NavGrid`].
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component)]
+pub struct Ladder;
+
+/// Something that finds its way across the map.
+#[derive(Component, Debug)]
+pub struct NavAgent {
+    /// Walking speed, in world units/s.
+    pub speed: f32,
+    /// In tiles, from the tile of its feet.
+    pub height: u32,
+}
+
+/// Where an agent is heading. Removed once it gets there, or when there's no way there.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NavGoal(pub TilePos);
+
+/// The moves left to reach the [`NavGoal`].
+#[derive(Component, Debug)]
+pub struct Route {
+    pub steps: Vec<NavStep>,
+    /// Index of the step being taken.
+    pub next: usize,
+}
+
+impl Route {
+    pub fn remaining(&self) -> &[NavStep] {
+        &self.steps[self.next.min(self.steps.len())..]
+    }
+}
+
+/// A ladder in `position`, whose center is at `center` in world space.
+pub fn ladder_bundle(position: TilePos, center: Vec2) -> impl Bundle {
+    (
+        Name::new("Ladder"),
+        Ladder,
+        position,
+        // Between the Solid layer and the colonists
+        Transform::from_translation(center.extend((LayerType::Solid.id() as f32 + 0.5) * LAYER_Z_STEP)),
+        Sprite::from_color(Color::linear_rgb(0.45, 0.3, 0.15), Vec2::new(12.0, 16.0)),
+    )
+}
+
+/// How full of liquid a tile is, from 0 to 1.
+fn liquid_fill(tiles: &WorldTiles, element_configs: &ElementConfigs, position: &TilePos) -> f32 {
+    let Some(tile) = tiles.get(LayerType::Liquid, position) else {
+        return 0.0;
+    };
+    element_configs
+        .get(tile.element)
+        .filter(|config| config.density > 0.0)
+        .map_or(0.0, |config| tile.mass / config.density)
+}
+
+/// Rebuilds the grid when the map changed size, e.g. for a new world, or updates the changed tiles.
+fn update_nav_grid(
+    // `WorldTiles` sends the events read here
+    mut tile_params: ParamSet<(WorldTiles, EventReader<TileChanged>)>,
+    element_configs: Res<ElementConfigs>,
+    ladder_query: Query<&TilePos, With<Ladder>>,
+    mut grid: ResMut<NavGrid>,
+) {
+    let changes: Vec<TileChanged> = tile_params.p1().read().copied().collect();
+    let tiles = tile_params.p0();
+    let Some(size) = tiles.map_size() else {
+        return;
+    };
+    if grid.size() != size {
+        *grid = NavGrid::new(size);
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let position = TilePos { x, y };
+                grid.set_solid(&position, tiles.is_solid(&position));
+                grid.set_liquid(&position, liquid_fill(&tiles, &element_configs, &position));
+            }
+        }
+        for position in ladder_query.iter() {
+            grid.set_ladder(position, true);
+        }
+        return;
+    }
+
+    for change in changes {
+        match (change.layer, change.kind) {
+            (LayerType::Solid, TileChangeKind::Element) => grid.set_solid(&change.position, tiles.is_solid(&change.position)),
+            (LayerType::Liquid, TileChangeKind::Element | TileChangeKind::Mass) => {
+                grid.set_liquid(&change.position, liquid_fill(&tiles, &element_configs, &change.position));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_ladders(
+    added_query: Query<(), Added<Ladder>>,
+    mut removed: RemovedComponents<Ladder>,
+    ladder_query: Query<&TilePos, With<Ladder>>,
+    mut grid: ResMut<NavGrid>,
+) {
+    if added_query.is_empty() && removed.read().next().is_none() {
+        return;
+    }
+    grid.clear_ladders();
+    for position in ladder_query.iter() {
+        grid.set_ladder(position, true);
+    }
+}
+
+fn clear_nav_grid(mut commands: Commands, mut grid: ResMut<NavGrid>, ladder_query: Query<Entity, With<Ladder>>) {
+    *grid = NavGrid::default();
+    for entity in ladder_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Drops the routes that can't be followed anymore since the grid changed, to plan them again.
+fn invalidate_routes(
+    mut commands: Commands,
+    grid: Res<NavGrid>,
+    mut checked_revision: Local<u64>,
+    agent_query: Query<(Entity, &NavAgent, &Route, &TilePos)>,
+) {
+    if grid.revision() == *checked_revision {
+        return;
+    }
+    *checked_revision = grid.revision();
+    for (entity, agent, route, position) in agent_query.iter() {
+        let mut from = *position;
+        for step in route.remaining() {
+            if !grid.is_valid(&from, step, agent.height) {
+                commands.entity(entity).remove::<Route>();
+                break;
+            }
+            from = step.position;
+        }
+    }
+}
+
+fn plan_routes(
+    mut commands: Commands,
+    grid: Res<NavGrid>,
+    agent_query: Query<(Entity, &NavAgent, Ref<NavGoal>, &TilePos, Option<&Fall>, Has<Route>)>,
+) {
+    for (entity, agent, goal, position, fall, has_route) in agent_query.iter() {
+        if has_route && !goal.is_changed() {
+            continue;
+        }
+        // Falling agents plan once they land
+        if !has_route && fall.is_some_and(|fall| !fall.grounded) {
+            continue;
+        }
+
+        match grid.find_path(position, &goal.0, agent.height) {
+            Some(path) if path.steps.is_empty() => {
+                commands.entity(entity).remove::<(NavGoal, Route)>();
+            }
+            Some(path) => {
+                commands.entity(entity).insert(Route { steps: path.steps, next: 0 });
+            }
+            None => {
+                debug!("No path from {position:?} to {:?} for {entity}", goal.0);
+                commands.entity(entity).remove::<(NavGoal, Route)>();
+            }
+        }
+    }
+}
+
+/// Speed of a move relative to walking.
+fn move_speed(kind: MoveKind) -> f32 {
+    match kind {
+        MoveKind::Walk => 1.0,
+        MoveKind::Climb => 0.6,
+        MoveKind::Jump => 0.8,
+        MoveKind::Fall => 1.5,
+    }
+}
+
+fn follow_routes(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<NavGrid>,
+    tiles: WorldTiles,
+    mut agent_query: Query<(Entity, &NavAgent, &mut Route, &mut Transform, &mut TilePos, Option<&mut Fall>)>,
+) {
+    for (entity, agent, mut route, mut transform, mut position, fall) in agent_query.iter_mut() {
+        // Time left to move this frame, in seconds
+        let mut time_left = time.delta_secs();
+        while let Some(step) = route.steps.get(route.next).copied() {
+            let Some(target) = tiles.world_position(LayerType::Solid, &step.position) else {
+                break;
+            };
+            let speed = agent.speed * move_speed(step.kind) / grid.slowdown_at(&step.position);
+            let offset = target - transform.translation.truncate();
+            let reach = speed * time_left;
+            if offset.length() > reach {
+                let movement = offset.normalize_or_zero() * reach;
+                transform.translation += movement.extend(0.0);
+                break;
+            }
+            transform.translation.x = target.x;
+            transform.translation.y = target.y;
+            time_left -= offset.length() / speed;
+            *position = step.position;
+            route.next += 1;
+        }
+
+        if route.next >= route.steps.len() {
+            commands.entity(entity).remove::<(NavGoal, Route)>();
+            if let Some(mut fall) = fall {
+                fall.speed = 0.0;
+            }
+        }
+    }
+}
+
+fn draw_routes(
+    mut gizmos: Gizmos,
+    settings: Res<NavigationSettings>,
+    tiles: WorldTiles,
+    agent_query: Query<(&Route, &GlobalTransform)>,
+) {
+    if !settings.debug_routes {
+        return;
+    }
+    for (route, transform) in agent_query.iter() {
+        let mut from = transform.translation().truncate();
+        for step in route.remaining() {
+            let Some(to) = tiles.world_position(LayerType::Solid, &step.position) else {
+                break;
+            };
+            let color = match step.kind {
+                MoveKind::Walk => Color::WHITE,
+                MoveKind::Climb => Color::linear_rgb(0.3, 0.9, 0.3),
+                MoveKind::Jump => Color::linear_rgb(0.95, 0.75, 0.2),
+                MoveKind::Fall => Color::linear_rgb(0.95, 0.3, 0.3),
+            };
+            gizmos.line_2d(from, to, color);
+            from = to;
+        }
+        gizmos.circle_2d(Isometry2d::from_translation(from), 4.0, Color::WHITE);
+    }
+}
@@ -0,0 +1,249 @@
+//! Build orders, placed as [`Blueprint`]s the colonists bring materials to and then build.
+//!
+//! A blueprint waits for [`Building::mass`] kg of a solid element, delivered one item at a time
+//! by [`JobKind::Deliver`] jobs, the first item picking the element. Once everything is there a
+//! [`JobKind::Build`] job is posted, which places the building made of the delivered materials.
+//! Cancelled blueprints drop what was delivered as an item.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+use serde::{Deserialize, Serialize};
+use simulation::temperature::Temperature;
+
+use crate::items::{drop_item, spawn_item, Carried, Item};
+use crate::jobs::{Job, JobBoard, JobCompleted, JobKind};
+use crate::navigation::{ladder_bundle, Ladder};
+use crate::resources::{ElementConfigs, ElementState};
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+
+/// Leftovers lighter than this, in kg, are used up rather than dropped.
+const MIN_LEFTOVER: f32 = 0.01;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Building {
+    /// A Solid tile of the delivered element.
+    Tile,
+    Ladder,
+}
+
+impl Building {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tile => "Tile",
+            Self::Ladder => "Ladder",
+        }
+    }
+
+    /// Materials needed, in kg.
+    pub fn mass(self) -> f32 {
+        match self {
+            Self::Tile => 200.0,
+            Self::Ladder => 100.0,
+        }
+    }
+}
+
+/// A building waiting for its materials and a builder.
+#[derive(Component, Debug)]
+pub struct Blueprint {
+    pub building: Building,
+    /// Of the jobs posted for it.
+    pub priority: u32,
+    /// Picked by the first delivery.
+    pub element: Option<u32>,
+    /// In kg.
+    pub delivered: f32,
+    /// Of the delivered materials, in °C.
+    pub temperature: f32,
+}
+
+impl Blueprint {
+    pub fn new(building: Building, priority: u32) -> Self {
+        Self { building, priority, element: None, delivered: 0.0, temperature: 0.0 }
+    }
+
+    /// Materials still missing, in kg.
+    pub fn missing(&self) -> f32 {
+        (self.building.mass() - self.delivered).max(0.0)
+    }
+
+    /// Adds up to the missing mass of an item, returning how much was used.
+    pub fn deliver(&mut self, element: u32, mass: f32, temperature: f32) -> f32 {
+        let used = mass.min(self.missing());
+        if used <= 0.0 {
+            return 0.0;
+        }
+        self.temperature = (self.temperature * self.delivered + temperature * used) / (self.delivered + used);
+        self.delivered += used;
+        self.element = Some(element);
+        used
+    }
+
+    /// Whether an item of `element` can go into the building.
+    fn accepts(&self, element: u32, element_configs: &ElementConfigs) -> bool {
+        let is_solid = element_configs
+            .get(element)
+            .is_some_and(|config| config.state == ElementState::Solid);
+        is_solid && self.element.is_none_or(|wanted| wanted == element)
+    }
+}
+
+/// A blueprint waiting in `position`.
+pub fn blueprint_bundle(blueprint: Blueprint, position: TilePos) -> impl Bundle {
+    (Name::new(format!("{} Blueprint", blueprint.building.label())), blueprint, position)
+}
+
+/// Whether `building` can go in `position`.
+pub fn can_build(tiles: &WorldTiles, ladder_query: &Query<&TilePos, With<Ladder>>, building: Building, position: &TilePos) -> bool {
+    match building {
+        Building::Tile => !tiles.is_solid(position),
+        Building::Ladder => !tiles.is_solid(position) && !ladder_query.iter().any(|ladder| ladder == position),
+    }
+}
+
+/// Removes a blueprint, dropping its materials in its tile.
+pub fn cancel_blueprint(
+    commands: &mut Commands,
+    tiles: &WorldTiles,
+    element_configs: &ElementConfigs,
+    entity: Entity,
+    blueprint: &Blueprint,
+    position: &TilePos,
+) {
+    commands.entity(entity).despawn_recursive();
+    let (Some(element), Some(center)) = (blueprint.element, tiles.world_position(LayerType::Solid, position)) else {
+        return;
+    };
+    let color = element_configs.get(element).map_or(Color::WHITE, |config| config.color);
+    spawn_item(commands, element, blueprint.delivered, blueprint.temperature, color, *position, center);
+}
+
+/// Posts a delivery of the closest suitable item for blueprints missing materials, and a build job
+/// for the others.
+pub(super) fn request_materials(
+    mut board: ResMut<JobBoard>,
+    element_configs: Res<ElementConfigs>,
+    blueprint_query: Query<(&Blueprint, &TilePos)>,
+    item_query: Query<(Entity, &Item, &TilePos), Without<Carried>>,
+) {
+    for (blueprint, position) in blueprint_query.iter() {
+        if blueprint.missing() <= 0.0 {
+            board.post(Job::new(JobKind::Build, *position, blueprint.priority));
+            continue;
+        }
+        if board.at(position).any(|(_, job)| job.kind == JobKind::Deliver) {
+            continue;
+        }
+
+        let distance = |other: &TilePos| {
+            let (dx, dy) = (other.x.abs_diff(position.x), other.y.abs_diff(position.y));
+            dx * dx + dy * dy
+        };
+        let item = item_query
+            .iter()
+            .filter(|(entity, item, _)| {
+                blueprint.accepts(item.element, &element_configs) && !board.iter().any(|(_, job)| job.item == Some(*entity))
+            })
+            .min_by_key(|(.., item_position)| distance(item_position));
+        if let Some((entity, ..)) = item {
+            board.post(Job { item: Some(entity), ..Job::new(JobKind::Deliver, *position, blueprint.priority) });
+        }
+    }
+}
+
+/// Cancels the blueprints whose tile was filled some other way, e.g. by the sandbox, and drops the
+/// jobs whose blueprint or item is gone.
+pub(super) fn cancel_stale_builds(
+    mut commands: Commands,
+    tiles: WorldTiles,
+    element_configs: Res<ElementConfigs>,
+    mut board: ResMut<JobBoard>,
+    blueprint_query: Query<(Entity, &Blueprint, &TilePos)>,
+    item_query: Query<(), With<Item>>,
+) {
+    let mut sites = Vec::new();
+    for (entity, blueprint, position) in blueprint_query.iter() {
+        if tiles.is_solid(position) {
+            cancel_blueprint(&mut commands, &tiles, &element_configs, entity, blueprint, position);
+        } else {
+            sites.push(*position);
+        }
+    }
+
+    let stale: Vec<_> = board
+        .iter()
+        .filter(|(_, job)| matches!(job.kind, JobKind::Build | JobKind::Deliver))
+        .filter(|(_, job)| !sites.contains(&job.position) || job.item.is_some_and(|item| !item_query.contains(item)))
+        .map(|(id, _)| id)
+        .collect();
+    for id in stale {
+        board.remove(id);
+    }
+}
+
+/// Puts delivered items into their blueprint, and builds the finished ones.
+pub(super) fn complete_builds(
+    mut commands: Commands,
+    mut completed: EventReader<JobCompleted>,
+    mut tiles: WorldTiles,
+    mut blueprint_query: Query<(Entity, &mut Blueprint, &TilePos)>,
+    mut item_query: Query<(&mut Item, &Temperature)>,
+) {
+    for JobCompleted { job, .. } in completed.read() {
+        let blueprint = blueprint_query.iter_mut().find(|(.., position)| **position == job.position);
+        match job.kind {
+            JobKind::Deliver => {
+                let Some(entity) = job.item else {
+                    continue;
+                };
+                let Ok((mut item, temperature)) = item_query.get_mut(entity) else {
+                    continue;
+                };
+                let used = blueprint.map_or(0.0, |(_, mut blueprint, _)| {
+                    blueprint.deliver(item.element, item.mass, temperature.value)
+                });
+                item.mass -= used;
+                if item.mass < MIN_LEFTOVER {
+                    commands.entity(entity).despawn_recursive();
+                } else {
+                    drop_item(&mut commands, entity);
+                }
+            }
+            JobKind::Build => {
+                let Some((entity, blueprint, position)) = blueprint else {
+                    continue;
+                };
+                match (blueprint.building, blueprint.element) {
+                    (Building::Tile, Some(element)) => {
+                        tiles.set(LayerType::Solid, position, element, blueprint.delivered, Some(blueprint.temperature));
+                    }
+                    (Building::Ladder, _) => {
+                        if let Some(center) = tiles.world_position(LayerType::Solid, position) {
+                            commands.spawn(ladder_bundle(*position, center));
+                        }
+                    }
+                    (Building::Tile, None) => {}
+                }
+                commands.entity(entity).despawn_recursive();
+            }
+            JobKind::Dig | JobKind::Mop => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deliveries_fill_the_blueprint() {
+        let mut blueprint = Blueprint::new(Building::Tile, 5);
+        assert_eq!(blueprint.deliver(3, 50.0, 10.0), 50.0);
+        assert_eq!(blueprint.deliver(3, 500.0, 40.0), 150.0);
+        assert_eq!(blueprint.missing(), 0.0);
+        // Weighted by mass
+        assert_eq!(blueprint.temperature, 32.5);
+        assert_eq!(blueprint.deliver(3, 10.0, 40.0), 0.0);
+    }
+}
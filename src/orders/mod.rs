@@ -0,0 +1,410 @@
+//! Orders the player gives the colonists: digging tiles out, building and mopping liquids up.
+//!
+//! Each order posts a [`Job`] on the [`JobBoard`] at the chosen priority, and takes effect when a
+//! colonist completes it. Dug out tiles leave their material behind as an item, which build orders
+//! use up, see [`build`]. Tiles are dug and built through [`WorldTiles`], so the navigation grid,
+//! the dirty tiles and the saves see them like any other change. Orders on tiles with nothing left
+//! to do, e.g. dug out by the sandbox meanwhile, are dropped. While an order tool is active,
+//! clicks go to it instead of selecting tiles, like the sandbox tools.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+use crate::actions::{Actions, InputAction, InputBindings};
+use crate::items::spawn_item;
+use crate::jobs::{Job, JobBoard, JobCompleted, JobId, JobKind, JobSystems, MAX_PRIORITY, MIN_PRIORITY};
+use crate::navigation::Ladder;
+use crate::picking::{HoveredTile, TilePickingSystems};
+use crate::resources::{elements, ElementConfigs};
+use crate::sandbox::{Sandbox, SandboxSystems, SandboxTool};
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::GameState;
+
+use build::{can_build, cancel_blueprint, cancel_stale_builds, complete_builds, request_materials};
+
+pub use build::{blueprint_bundle, Blueprint, Building};
+
+mod build;
+
+/// Size of a tile in world units, used to outline the orders.
+const TILE_SIZE: f32 = 16.0;
+
+pub struct OrdersPlugin;
+
+impl Plugin for OrdersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Orders>()
+            .add_systems(OnEnter(GenerationState::Done), spawn_order_panel.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (
+                    select_order_keys,
+                    click_order_buttons,
+                    apply_order,
+                    cancel_stale_orders,
+                    cancel_stale_builds,
+                    request_materials,
+                    update_order_panel,
+                )
+                    .chain()
+                    .after(TilePickingSystems)
+                    .after(SandboxSystems)
+                    .before(JobSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(
+                Update,
+                (complete_orders, complete_builds, draw_orders)
+                    .chain()
+                    .after(JobSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), reset_orders);
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderTool {
+    #[default]
+    None,
+    /// Orders the solid tiles under the cursor dug out.
+    Dig,
+    /// Places blueprints in the free tiles under the cursor.
+    Build(Building),
+    /// Orders the liquid under the cursor mopped up.
+    Mop,
+    /// Cancels the orders under the cursor.
+    Cancel,
+}
+
+impl OrderTool {
+    pub const ALL: [Self; 5] = [
+        Self::Dig,
+        Self::Build(Building::Tile),
+        Self::Build(Building::Ladder),
+        Self::Mop,
+        Self::Cancel,
+    ];
+
+    /// The action switching to this tool.
+    fn action(self) -> Option<InputAction> {
+        match self {
+            Self::None => None,
+            Self::Dig => Some(InputAction::Dig),
+            Self::Build(Building::Tile) => Some(InputAction::Build),
+            Self::Build(Building::Ladder) => Some(InputAction::BuildLadder),
+            Self::Mop => Some(InputAction::Mop),
+            Self::Cancel => Some(InputAction::CancelOrder),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Dig => "Dig",
+            Self::Build(Building::Tile) => "Build Tile",
+            Self::Build(Building::Ladder) => "Build Ladder",
+            Self::Mop => "Mop",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
+
+/// The active order tool and the priority of the jobs it posts.
+#[derive(Resource, Debug)]
+pub struct Orders {
+    pub tool: OrderTool,
+    pub priority: u32,
+}
+
+impl Default for Orders {
+    fn default() -> Self {
+        Self { tool: OrderTool::None, priority: 5 }
+    }
+}
+
+impl Orders {
+    /// Switches to `tool`, or back to none if it is already active.
+    pub fn toggle(&mut self, tool: OrderTool) {
+        self.tool = if self.tool == tool { OrderTool::None } else { tool };
+    }
+}
+
+/// Whether an order tool takes the clicks, for use as a run condition.
+pub fn order_active(orders: Option<Res<Orders>>) -> bool {
+    orders.is_some_and(|orders| orders.tool != OrderTool::None)
+}
+
+/// Whether `job` still has something to do, given the tiles.
+fn is_workable(tiles: &WorldTiles, job: &Job) -> bool {
+    match job.kind {
+        JobKind::Dig => tiles.is_solid(&job.position),
+        JobKind::Mop => tiles
+            .element(LayerType::Liquid, &job.position)
+            .is_some_and(|element| element != elements::VACUUM),
+        // Up to the blueprint, see `cancel_stale_builds`
+        JobKind::Build | JobKind::Deliver => true,
+    }
+}
+
+#[derive(Component)]
+struct OrderPanel;
+
+#[derive(Component)]
+struct OrderPanelText;
+
+#[derive(Component)]
+struct OrderButton(OrderTool);
+
+fn spawn_order_panel(mut commands: Commands, bindings: Res<InputBindings>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(8.0)),
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0.05, 0.05, 0.05, 0.8)),
+            OrderPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for tool in OrderTool::ALL {
+                        let key = tool.action().map_or_else(String::new, |action| bindings.label(action, false));
+                        parent
+                            .spawn((
+                                Button,
+                                Node {
+                                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::linear_rgb(0.15, 0.15, 0.15)),
+                                OrderButton(tool),
+                            ))
+                            .with_child((
+                                Text::new(format!("{key} {}", tool.label())),
+                                TextFont {
+                                    font_size: 14.0,
+                                    ..default()
+                                },
+                                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                            ));
+                    }
+                });
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                OrderPanelText,
+            ));
+        });
+}
+
+fn reset_orders(
+    mut commands: Commands,
+    mut orders: ResMut<Orders>,
+    panel_query: Query<Entity, Or<(With<OrderPanel>, With<Blueprint>)>>,
+) {
+    orders.tool = OrderTool::None;
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn select_order_keys(actions: Res<Actions>, mut orders: ResMut<Orders>, mut sandbox: ResMut<Sandbox>) {
+    // Picking a sandbox tool puts the order tool away, and the other way around
+    if sandbox.is_changed() && sandbox.tool != SandboxTool::None {
+        orders.tool = OrderTool::None;
+    }
+    for tool in OrderTool::ALL {
+        if tool.action().is_some_and(|action| actions.just_pressed(action)) {
+            orders.toggle(tool);
+        }
+    }
+
+    if actions.just_pressed(InputAction::LowerPriority) {
+        orders.priority = orders.priority.saturating_sub(1).max(MIN_PRIORITY);
+    }
+    if actions.just_pressed(InputAction::RaisePriority) {
+        orders.priority = (orders.priority + 1).min(MAX_PRIORITY);
+    }
+    if orders.is_changed() && orders.tool != OrderTool::None && sandbox.tool != SandboxTool::None {
+        sandbox.tool = SandboxTool::None;
+    }
+}
+
+fn click_order_buttons(interaction_query: Query<(&Interaction, &OrderButton), Changed<Interaction>>, mut orders: ResMut<Orders>) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            orders.toggle(button.0);
+        }
+    }
+}
+
+/// What [`apply_order`] reads to place an order.
+#[derive(SystemParam)]
+struct OrderInput<'w> {
+    actions: Res<'w, Actions>,
+    hovered: Res<'w, HoveredTile>,
+    orders: Res<'w, Orders>,
+    element_configs: Res<'w, ElementConfigs>,
+}
+
+fn apply_order(
+    mut commands: Commands,
+    input: OrderInput,
+    tiles: WorldTiles,
+    mut board: ResMut<JobBoard>,
+    blueprint_query: Query<(Entity, &Blueprint, &TilePos)>,
+    ladder_query: Query<&TilePos, With<Ladder>>,
+) {
+    let OrderInput { actions, hovered, orders, element_configs } = input;
+    let Some(position) = hovered.position else {
+        return;
+    };
+    if !actions.pressed(InputAction::Primary) {
+        return;
+    }
+    let blueprint = blueprint_query.iter().find(|(.., other)| **other == position);
+
+    let kind = match orders.tool {
+        OrderTool::None => return,
+        OrderTool::Dig => JobKind::Dig,
+        OrderTool::Mop => JobKind::Mop,
+        OrderTool::Build(building) => {
+            if blueprint.is_none() && can_build(&tiles, &ladder_query, building, &position) {
+                commands.spawn(blueprint_bundle(Blueprint::new(building, orders.priority), position));
+            }
+            return;
+        }
+        OrderTool::Cancel => {
+            let ids: Vec<JobId> = board.at(&position).map(|(id, _)| id).collect();
+            for id in ids {
+                board.remove(id);
+            }
+            if let Some((entity, blueprint, position)) = blueprint {
+                cancel_blueprint(&mut commands, &tiles, &element_configs, entity, blueprint, position);
+            }
+            return;
+        }
+    };
+    // Ordering again changes the priority
+    let job = Job::new(kind, position, orders.priority);
+    if is_workable(&tiles, &job) {
+        board.post(job);
+    }
+}
+
+/// Drops the orders that were done some other way.
+fn cancel_stale_orders(tiles: WorldTiles, mut board: ResMut<JobBoard>) {
+    let stale: Vec<JobId> = board
+        .iter()
+        .filter(|(_, job)| !is_workable(&tiles, job))
+        .map(|(id, _)| id)
+        .collect();
+    for id in stale {
+        board.remove(id);
+    }
+}
+
+fn complete_orders(
+    mut commands: Commands,
+    mut completed: EventReader<JobCompleted>,
+    element_configs: Res<ElementConfigs>,
+    mut tiles: WorldTiles,
+) {
+    for JobCompleted { job, worker } in completed.read() {
+        debug!("{worker} completed {} at {:?}", job.kind.label(), job.position);
+        match job.kind {
+            JobKind::Dig => {
+                let Some(tile) = tiles.get(LayerType::Solid, &job.position) else {
+                    continue;
+                };
+                tiles.set(LayerType::Solid, &job.position, elements::VACUUM, 0.0, None);
+                if let Some(center) = tiles.world_position(LayerType::Solid, &job.position) {
+                    let color = element_configs.get(tile.element).map_or(Color::WHITE, |config| config.color);
+                    spawn_item(&mut commands, tile.element, tile.mass, tile.temperature, color, job.position, center);
+                }
+            }
+            JobKind::Mop => {
+                tiles.set(LayerType::Liquid, &job.position, elements::VACUUM, 0.0, None);
+            }
+            // See `complete_builds`
+            JobKind::Build | JobKind::Deliver => {}
+        }
+    }
+}
+
+/// Outlines the tiles with orders, brighter once a colonist is on it, and the blueprints, brighter
+/// as their materials are delivered.
+fn draw_orders(
+    mut gizmos: Gizmos,
+    board: Res<JobBoard>,
+    tiles: WorldTiles,
+    blueprint_query: Query<(&Blueprint, &TilePos)>,
+) {
+    for (_, job) in board.iter() {
+        let Some(center) = tiles.world_position(LayerType::Solid, &job.position) else {
+            continue;
+        };
+        let color = match job.kind {
+            JobKind::Dig => Color::linear_rgb(0.95, 0.75, 0.2),
+            JobKind::Build | JobKind::Deliver => Color::linear_rgb(0.4, 0.9, 0.5),
+            JobKind::Mop => Color::linear_rgb(0.3, 0.6, 1.0),
+        };
+        let alpha = if job.is_open() { 0.5 } else { 1.0 };
+        gizmos.rect_2d(Isometry2d::from_translation(center), Vec2::splat(TILE_SIZE - 2.0), color.with_alpha(alpha));
+    }
+
+    for (blueprint, position) in blueprint_query.iter() {
+        let Some(center) = tiles.world_position(LayerType::Solid, position) else {
+            continue;
+        };
+        let filled = 1.0 - blueprint.missing() / blueprint.building.mass();
+        let color = Color::linear_rgba(0.4, 0.9, 0.5, 0.3 + 0.7 * filled);
+        gizmos.rect_2d(Isometry2d::from_translation(center), Vec2::splat(TILE_SIZE - 6.0), color);
+    }
+}
+
+fn update_order_panel(
+    orders: Res<Orders>,
+    board: Res<JobBoard>,
+    mut text_query: Query<&mut Text, With<OrderPanelText>>,
+    mut button_query: Query<(&OrderButton, &Interaction, &mut BackgroundColor)>,
+) {
+    for (button, interaction, mut color) in button_query.iter_mut() {
+        color.0 = match (button.0 == orders.tool, interaction) {
+            (true, _) => Color::linear_rgb(0.35, 0.45, 0.25),
+            (false, Interaction::Hovered) => Color::linear_rgb(0.25, 0.25, 0.25),
+            (false, _) => Color::linear_rgb(0.15, 0.15, 0.15),
+        };
+    }
+
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let claimed = board.iter().filter(|(_, job)| !job.is_open()).count();
+    let description = format!(
+        "Priority {}, {} orders, {claimed} being worked on",
+        orders.priority,
+        board.iter().count()
+    );
+    if text.0 != description {
+        text.0 = description;
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+
+use crate::resources::{elements, ElementConfigs};
+
+use super::{add_overlay, Overlay, OverlayMode, TileSample};
+
+/// Colours every tile by the element occupying it.
+pub struct ElementOverlayPlugin;
+
+impl Plugin for ElementOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        add_overlay::<ElementOverlay>(app);
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ElementOverlay;
+
+impl Overlay for ElementOverlay {
+    const MODE: OverlayMode = OverlayMode::Element;
+
+    fn title(&self) -> &str {
+        "Element"
+    }
+
+    fn legend(&self, element_configs: &ElementConfigs) -> Vec<(String, Color)> {
+        element_configs
+            .elements
+            .iter()
+            .filter(|element| element.id != elements::VACUUM)
+            .map(|element| (element.name.clone(), element.color))
+            .collect()
+    }
+
+    fn color(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<Color> {
+        let cell = sample.occupant()?;
+        element_configs.get(cell.element).map(|element| element.color)
+    }
+
+    fn describe(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<String> {
+        let Some(cell) = sample.occupant() else {
+            return Some("Vacuum".to_string());
+        };
+        let element = element_configs.get(cell.element)?;
+        Some(format!("{} ({})\n{:.1} kg", element.name, element.symbol, cell.mass))
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+use crate::resources::ElementConfigs;
+
+use super::{add_overlay, ColorRamp, Overlay, OverlayMode, TileSample};
+
+/// Colours the tiles of the Gas layer by their mass, the closest we have to a pressure.
+pub struct GasOverlayPlugin;
+
+impl Plugin for GasOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        add_overlay::<GasOverlay>(app);
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct GasOverlay {
+    /// Colours by gas mass, in kg.
+    pub ramp: ColorRamp,
+}
+
+impl Default for GasOverlay {
+    fn default() -> Self {
+        Self {
+            ramp: ColorRamp::new([
+                (0.0, Color::srgb(0.1, 0.1, 0.15)),
+                (0.5, Color::srgb(0.3, 0.3, 0.7)),
+                (1.0, Color::srgb(0.3, 0.7, 0.8)),
+                (2.0, Color::srgb(0.9, 0.9, 0.4)),
+                (5.0, Color::srgb(0.9, 0.3, 0.2)),
+            ]),
+        }
+    }
+}
+
+impl Overlay for GasOverlay {
+    const MODE: OverlayMode = OverlayMode::Gas;
+
+    fn title(&self) -> &str {
+        "Gas mass"
+    }
+
+    fn legend(&self, _element_configs: &ElementConfigs) -> Vec<(String, Color)> {
+        self.ramp.legend("kg")
+    }
+
+    fn color(&self, sample: &TileSample, _element_configs: &ElementConfigs) -> Option<Color> {
+        // Tiles filled with a solid or liquid have no room for gas
+        if sample.solid.is_some_and(|cell| !cell.is_empty()) || sample.liquid.is_some_and(|cell| !cell.is_empty()) {
+            return None;
+        }
+        let mass = sample.gas.filter(|cell| !cell.is_empty()).map_or(0.0, |cell| cell.mass);
+        Some(self.ramp.sample(mass))
+    }
+
+    fn describe(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<String> {
+        let cell = sample.gas.filter(|cell| !cell.is_empty())?;
+        let name = element_configs.get(cell.element).map_or("Unknown", |element| element.name.as_str());
+        Some(format!("{name}\n{:.1} g", cell.mass * 1000.0))
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use crate::resources::ElementConfigs;
+
+use super::{add_overlay, CellSample, ColorRamp, Overlay, OverlayMode, TileSample};
+
+/// Colours the tiles of the Liquid layer by how full they are.
+pub struct LiquidOverlayPlugin;
+
+impl Plugin for LiquidOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        add_overlay::<LiquidOverlay>(app);
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct LiquidOverlay {
+    /// Colours by fill level, from empty to full.
+    pub ramp: ColorRamp,
+}
+
+impl Default for LiquidOverlay {
+    fn default() -> Self {
+        Self {
+            ramp: ColorRamp::new([
+                (0.0, Color::srgb(0.75, 0.85, 0.95)),
+                (0.5, Color::srgb(0.3, 0.55, 0.9)),
+                (1.0, Color::srgb(0.05, 0.15, 0.6)),
+            ]),
+        }
+    }
+}
+
+impl LiquidOverlay {
+    /// Fraction of the tile the liquid fills, a full tile holding one cubic meter.
+    fn level(cell: &CellSample, element_configs: &ElementConfigs) -> f32 {
+        let density = element_configs.get(cell.element).map_or(0.0, |element| element.density);
+        if density > 0.0 {
+            (cell.mass / density).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Overlay for LiquidOverlay {
+    const MODE: OverlayMode = OverlayMode::Liquid;
+
+    fn title(&self) -> &str {
+        "Liquid level"
+    }
+
+    fn legend(&self, _element_configs: &ElementConfigs) -> Vec<(String, Color)> {
+        self.ramp
+            .stops()
+            .iter()
+            .rev()
+            .map(|(level, color)| (format!("{:.0} %", level * 100.0), *color))
+            .collect()
+    }
+
+    fn color(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<Color> {
+        let cell = sample.liquid.filter(|cell| !cell.is_empty())?;
+        Some(self.ramp.sample(Self::level(&cell, element_configs)))
+    }
+
+    fn describe(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<String> {
+        let cell = sample.liquid.filter(|cell| !cell.is_empty())?;
+        let name = element_configs.get(cell.element).map_or("Unknown", |element| element.name.as_str());
+        Some(format!(
+            "{name}\n{:.1} kg ({:.0} %)",
+            cell.mass,
+            Self::level(&cell, element_configs) * 100.0
+        ))
+    }
+}
@@ -0,0 +1,471 @@
+//! Overlays tint the map to show per-tile simulation data that is otherwise invisible.
+//!
+//! The active overlay is picked by the [`OverlayMode`] state and cycled with [`InputAction::CycleOverlay`]. Every
+//! overlay is a plugin implementing [`Overlay`], which maps a [`TileSample`] to a colour; this
+//! module draws it on its own tilemap on top of the layers, along with a legend and a tooltip.
+
+pub mod element;
+pub mod gas;
+pub mod liquid;
+pub mod temperature;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_ecs_tilemap::prelude::*;
+use simulation::temperature::HeatCell;
+
+use crate::actions::{Actions, InputAction};
+use crate::picking::{HoveredTile, TilePickingSystems};
+use crate::resources::{elements, ElementConfigs};
+use crate::states::generation::GenerationState;
+use crate::world::dirty::{DirtyTiles, DirtyTilesSystems};
+use crate::world::layer::{Layer, LayerType};
+use crate::world::tile::{TileElement, TileMass};
+use crate::world::Grid;
+use crate::GameState;
+
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<OverlayMode>()
+            .add_systems(
+                Update,
+                (cycle_overlay, sync_overlay.run_if(state_changed::<OverlayMode>))
+                    .chain()
+                    .in_set(OverlaySystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), reset_overlay)
+            .add_plugins((
+                temperature::TemperatureOverlayPlugin,
+                element::ElementOverlayPlugin,
+                gas::GasOverlayPlugin,
+                liquid::LiquidOverlayPlugin,
+            ));
+    }
+}
+
+/// Spawns and despawns the overlay map; the systems of every overlay run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OverlaySystems;
+
+#[derive(States, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OverlayMode {
+    #[default]
+    None,
+    Temperature,
+    Element,
+    Gas,
+    Liquid,
+}
+
+impl OverlayMode {
+    const CYCLE: [Self; 5] = [Self::None, Self::Temperature, Self::Element, Self::Gas, Self::Liquid];
+
+    pub fn next(self) -> Self {
+        let index = Self::CYCLE.iter().position(|mode| *mode == self).unwrap_or_default();
+        Self::CYCLE[(index + 1) % Self::CYCLE.len()]
+    }
+}
+
+/// Rendered above every layer of the grid.
+const OVERLAY_Z: f32 = 100.0;
+
+/// Opacity of the overlay tiles, so the world stays readable underneath.
+const OVERLAY_ALPHA: f32 = 0.8;
+
+/// The tilemap an overlay is drawn on.
+#[derive(Component)]
+pub struct OverlayMap;
+
+/// The legend explaining the colours of the active overlay.
+#[derive(Component)]
+pub struct OverlayLegend;
+
+/// Follows the cursor, showing the exact value of the hovered tile.
+#[derive(Component)]
+pub struct OverlayTooltip;
+
+/// An overlay mode, mapping the contents of a tile to a colour.
+pub trait Overlay: Resource {
+    const MODE: OverlayMode;
+
+    fn title(&self) -> &str;
+
+    /// Entries of the legend, from top to bottom.
+    fn legend(&self, element_configs: &ElementConfigs) -> Vec<(String, Color)>;
+
+    /// Colour of a tile, `None` leaves it transparent.
+    fn color(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<Color>;
+
+    /// Text of the tooltip for a hovered tile, `None` hides it.
+    fn describe(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<String>;
+}
+
+/// Registers the systems drawing an overlay while its mode is active.
+pub fn add_overlay<T: Overlay + FromWorld>(app: &mut App) {
+    app.init_resource::<T>()
+        .add_systems(
+            Update,
+            (
+                spawn_overlay_legend::<T>.run_if(state_changed::<OverlayMode>),
+                update_overlay_tooltip::<T>,
+            )
+                .chain()
+                .after(OverlaySystems)
+                .after(TilePickingSystems)
+                .run_if(in_state(T::MODE).and(in_state(GameState::Playing)).and(in_state(GenerationState::Done))),
+        )
+        // Colours follow the tiles that changed this frame
+        .add_systems(
+            PostUpdate,
+            update_overlay_colors::<T>
+                .after(DirtyTilesSystems)
+                .run_if(in_state(T::MODE).and(in_state(GameState::Playing)).and(in_state(GenerationState::Done))),
+        );
+}
+
+/// Maps values to colours by interpolating between sorted stops.
+#[derive(Clone, Debug, Reflect)]
+pub struct ColorRamp {
+    stops: Vec<(f32, Color)>,
+}
+
+impl ColorRamp {
+    pub fn new(stops: impl IntoIterator<Item = (f32, Color)>) -> Self {
+        let mut stops: Vec<_> = stops.into_iter().collect();
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { stops }
+    }
+
+    pub fn stops(&self) -> &[(f32, Color)] {
+        &self.stops
+    }
+
+    /// Values outside the ramp get the colour of the closest stop.
+    pub fn sample(&self, value: f32) -> Color {
+        let Some(upper) = self.stops.iter().position(|(stop, _)| value < *stop) else {
+            return self.stops.last().map_or(Color::NONE, |(_, color)| *color);
+        };
+        if upper == 0 {
+            return self.stops[0].1;
+        }
+
+        let (start, start_color) = self.stops[upper - 1];
+        let (end, end_color) = self.stops[upper];
+        let t = (value - start) / (end - start);
+        LinearRgba::from(start_color).mix(&LinearRgba::from(end_color), t).into()
+    }
+
+    /// Legend entries for every stop, highest first.
+    pub fn legend(&self, unit: &str) -> Vec<(String, Color)> {
+        self.stops.iter().rev().map(|(value, color)| (format!("{value} {unit}"), *color)).collect()
+    }
+}
+
+/// Contents of one cell of a layer.
+#[derive(Clone, Copy, Debug)]
+pub struct CellSample {
+    pub element: u32,
+    pub mass: f32,
+    pub temperature: f32,
+}
+
+impl CellSample {
+    pub fn is_empty(&self) -> bool {
+        self.element == elements::VACUUM || self.mass <= 0.0
+    }
+}
+
+/// Contents of every simulated layer at one tile position.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TileSample {
+    pub solid: Option<CellSample>,
+    pub liquid: Option<CellSample>,
+    pub gas: Option<CellSample>,
+}
+
+impl TileSample {
+    /// Whatever occupies the tile, checking the layers from the top.
+    pub fn occupant(&self) -> Option<&CellSample> {
+        [&self.solid, &self.liquid, &self.gas]
+            .into_iter()
+            .flatten()
+            .find(|cell| !cell.is_empty())
+    }
+}
+
+/// Reads the contents of the layers at a tile position.
+#[derive(SystemParam)]
+pub struct TileSampler<'w, 's> {
+    layer_query: Query<'w, 's, (&'static Layer, &'static TileStorage)>,
+    cell_query: Query<'w, 's, (&'static HeatCell, &'static TileElement, &'static TileMass)>,
+}
+
+impl TileSampler<'_, '_> {
+    fn cell(&self, layer_type: LayerType, tile_pos: &TilePos) -> Option<CellSample> {
+        let (_, storage) = self.layer_query.iter().find(|(layer, _)| layer.layer_type == layer_type)?;
+        let (heat_cell, element, mass) = self.cell_query.get(storage.get(tile_pos)?).ok()?;
+        Some(CellSample {
+            element: element.0,
+            mass: mass.0,
+            temperature: heat_cell.temperature.get_temperature(),
+        })
+    }
+
+    pub fn sample(&self, tile_pos: &TilePos) -> TileSample {
+        TileSample {
+            solid: self.cell(LayerType::Solid, tile_pos),
+            liquid: self.cell(LayerType::Liquid, tile_pos),
+            gas: self.cell(LayerType::Gas, tile_pos),
+        }
+    }
+}
+
+/// Switches to the next overlay, or back to none after the last one.
+fn cycle_overlay(
+    actions: Res<Actions>,
+    mode: Res<State<OverlayMode>>,
+    mut next_mode: ResMut<NextState<OverlayMode>>,
+) {
+    if actions.just_pressed(InputAction::CycleOverlay) {
+        next_mode.set(mode.get().next());
+    }
+}
+
+/// Replaces the overlay map and tooltip whenever the mode changes.
+fn sync_overlay(
+    mut commands: Commands,
+    mode: Res<State<OverlayMode>>,
+    grid_query: Query<Entity, With<Grid>>,
+    layer_query: Query<(&TilemapSize, &TilemapGridSize, &TilemapTileSize, &Transform), With<Layer>>,
+    overlay_query: Query<Entity, Or<(With<OverlayMap>, With<OverlayLegend>, With<OverlayTooltip>)>>,
+) {
+    despawn_overlay(&mut commands, &overlay_query);
+    if *mode.get() != OverlayMode::None && spawn_overlay_map(&mut commands, &grid_query, &layer_query).is_some() {
+        spawn_tooltip(&mut commands);
+    }
+}
+
+/// The overlay map goes away with the world, the UI is removed here.
+fn reset_overlay(
+    mut commands: Commands,
+    mut next_mode: ResMut<NextState<OverlayMode>>,
+    overlay_query: Query<Entity, Or<(With<OverlayMap>, With<OverlayLegend>, With<OverlayTooltip>)>>,
+) {
+    next_mode.set(OverlayMode::None);
+    despawn_overlay(&mut commands, &overlay_query);
+}
+
+fn despawn_overlay(
+    commands: &mut Commands,
+    overlay_query: &Query<Entity, Or<(With<OverlayMap>, With<OverlayLegend>, With<OverlayTooltip>)>>,
+) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Spawns an empty overlay tilemap matching the layers of the grid.
+fn spawn_overlay_map(
+    commands: &mut Commands,
+    grid_query: &Query<Entity, With<Grid>>,
+    layer_query: &Query<(&TilemapSize, &TilemapGridSize, &TilemapTileSize, &Transform), With<Layer>>,
+) -> Option<Entity> {
+    let grid_entity = grid_query.get_single().ok()?;
+    let (size, grid_size, tile_size, transform) = layer_query.iter().next()?;
+
+    let overlay_entity = commands.spawn((Name::new("Overlay"), OverlayMap)).id();
+    let mut tile_storage = TileStorage::empty(*size);
+    commands.entity(overlay_entity).with_children(|parent| {
+        for x in 0..size.x {
+            for y in 0..size.y {
+                let tile_pos = TilePos { x, y };
+                let tile_entity = parent
+                    .spawn(TileBundle {
+                        position: tile_pos,
+                        tilemap_id: TilemapId(overlay_entity),
+                        color: TileColor(Color::NONE),
+                        ..default()
+                    })
+                    .id();
+                tile_storage.set(&tile_pos, tile_entity);
+            }
+        }
+    });
+
+    commands.entity(overlay_entity).insert(TilemapBundle {
+        size: *size,
+        grid_size: *grid_size,
+        tile_size: *tile_size,
+        storage: tile_storage,
+        transform: transform.with_translation(transform.translation.with_z(OVERLAY_Z)),
+        ..default()
+    });
+    commands.entity(grid_entity).add_child(overlay_entity);
+    Some(overlay_entity)
+}
+
+fn spawn_tooltip(commands: &mut Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::linear_rgba(0.05, 0.05, 0.05, 0.8)),
+        Visibility::Hidden,
+        OverlayTooltip,
+    ))
+    .with_child((
+        Text::default(),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+    ));
+}
+
+/// Spawns the legend of an overlay in the bottom right corner.
+fn spawn_overlay_legend<T: Overlay>(mut commands: Commands, overlay: Res<T>, element_configs: Res<ElementConfigs>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(8.0)),
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0.05, 0.05, 0.05, 0.8)),
+            OverlayLegend,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(overlay.title()),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+            ));
+            for (label, color) in overlay.legend(&element_configs) {
+                parent
+                    .spawn(Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(6.0),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Node {
+                                width: Val::Px(14.0),
+                                height: Val::Px(14.0),
+                                ..default()
+                            },
+                            BackgroundColor(color),
+                        ));
+                        parent.spawn((
+                            Text::new(label),
+                            TextFont {
+                                font_size: 14.0,
+                                ..default()
+                            },
+                            TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                        ));
+                    });
+            }
+        });
+}
+
+/// Recolours the tiles whose contents changed, or every tile of a new overlay map.
+fn update_overlay_colors<T: Overlay>(
+    overlay: Res<T>,
+    element_configs: Res<ElementConfigs>,
+    dirty_tiles: Res<DirtyTiles>,
+    overlay_query: Query<(&TileStorage, Ref<OverlayMap>)>,
+    sampler: TileSampler,
+    mut color_query: Query<&mut TileColor>,
+) {
+    let Ok((overlay_storage, overlay_map)) = overlay_query.get_single() else {
+        return;
+    };
+
+    let positions: HashSet<TilePos> = if overlay_map.is_added() || overlay.is_changed() {
+        let size = overlay_storage.size;
+        (0..size.x).flat_map(|x| (0..size.y).map(move |y| TilePos { x, y })).collect()
+    } else {
+        dirty_tiles.positions()
+    };
+
+    for tile_pos in positions.iter() {
+        let Some(mut color) = overlay_storage.get(tile_pos).and_then(|entity| color_query.get_mut(entity).ok()) else {
+            continue;
+        };
+        let new_color = overlay
+            .color(&sampler.sample(tile_pos), &element_configs)
+            .map_or(Color::NONE, |color| color.with_alpha(OVERLAY_ALPHA));
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+}
+
+fn update_overlay_tooltip<T: Overlay>(
+    overlay: Res<T>,
+    element_configs: Res<ElementConfigs>,
+    hovered: Res<HoveredTile>,
+    sampler: TileSampler,
+    mut tooltip_query: Query<(&mut Node, &mut Visibility, &Children), With<OverlayTooltip>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let Ok((mut node, mut visibility, children)) = tooltip_query.get_single_mut() else {
+        return;
+    };
+
+    let description = hovered
+        .position
+        .and_then(|tile_pos| overlay.describe(&sampler.sample(&tile_pos), &element_configs));
+    let (Some(cursor), Some(description)) = (hovered.cursor, description) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    if let Some(mut text) = children.first().and_then(|child| text_query.get_mut(*child).ok()) {
+        text.0 = description;
+    }
+    node.left = Val::Px(cursor.x + 16.0);
+    node.top = Val::Px(cursor.y + 16.0);
+    *visibility = Visibility::Inherited;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_ramp_sample() {
+        let ramp = ColorRamp::new([(100.0, Color::WHITE), (0.0, Color::BLACK)]);
+
+        assert_eq!(ramp.sample(-10.0), Color::BLACK);
+        assert_eq!(ramp.sample(150.0), Color::WHITE);
+        let middle = LinearRgba::from(ramp.sample(50.0));
+        assert!((middle.red - 0.5).abs() < 1e-6);
+        assert!((middle.alpha - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_overlay_mode_cycles_back_to_none() {
+        let mut mode = OverlayMode::None;
+        for _ in 1..OverlayMode::CYCLE.len() {
+            mode = mode.next();
+            assert_ne!(mode, OverlayMode::None);
+        }
+        assert_eq!(mode.next(), OverlayMode::None);
+    }
+}
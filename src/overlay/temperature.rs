@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::resources::ElementConfigs;
+
+use super::{add_overlay, ColorRamp, Overlay, OverlayMode, TileSample};
+
+/// Tints every tile by the temperature of the element occupying it.
+pub struct TemperatureOverlayPlugin;
+
+impl Plugin for TemperatureOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        add_overlay::<TemperatureOverlay>(app);
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TemperatureOverlay {
+    /// Colours by temperature, in °C.
+    pub ramp: ColorRamp,
+}
+
+impl Default for TemperatureOverlay {
+    fn default() -> Self {
+        Self {
+            ramp: ColorRamp::new([
+                (-50.0, Color::srgb(0.2, 0.1, 0.6)),
+                (0.0, Color::srgb(0.1, 0.5, 0.9)),
+                (20.0, Color::srgb(0.2, 0.8, 0.3)),
+                (50.0, Color::srgb(0.9, 0.9, 0.2)),
+                (100.0, Color::srgb(0.95, 0.5, 0.1)),
+                (300.0, Color::srgb(0.85, 0.1, 0.1)),
+            ]),
+        }
+    }
+}
+
+impl Overlay for TemperatureOverlay {
+    const MODE: OverlayMode = OverlayMode::Temperature;
+
+    fn title(&self) -> &str {
+        "Temperature"
+    }
+
+    fn legend(&self, _element_configs: &ElementConfigs) -> Vec<(String, Color)> {
+        self.ramp.legend("°C")
+    }
+
+    fn color(&self, sample: &TileSample, _element_configs: &ElementConfigs) -> Option<Color> {
+        sample.occupant().map(|cell| self.ramp.sample(cell.temperature))
+    }
+
+    fn describe(&self, sample: &TileSample, element_configs: &ElementConfigs) -> Option<String> {
+        let cell = sample.occupant()?;
+        let name = element_configs.get(cell.element).map_or("Unknown", |element| element.name.as_str());
+        Some(format!("{name}\n{:.1} °C", cell.temperature))
+    }
+}
@@ -0,0 +1,196 @@
+//! Converts the cursor position into the tile under it, and highlights that tile.
+//!
+//! The cursor, or the gamepad's [`VirtualCursor`] while it is in use, goes through the `Camera2d`
+//! into world space, then into the tile coordinates of the layers. Mouse positions over UI nodes,
+//! as reported by `bevy_picking`, don't hover any tile. On touch screens, a [`TouchGesture::Tap`]
+//! selects the tile under it unless it lands on the UI.
+
+use bevy::picking::focus::HoverMap;
+use bevy::picking::pointer::PointerId;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_tilemap::tiles::TilePos;
+
+use crate::actions::{Actions, InputAction, TouchGesture, VirtualCursor};
+use crate::helpers::camera::CameraSystems;
+use crate::orders::order_active;
+use crate::sandbox::tool_active;
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::GameState;
+
+/// Drawn above the layers and overlays.
+const HIGHLIGHT_Z: f32 = 110.0;
+
+pub struct TilePickingPlugin;
+
+impl Plugin for TilePickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HoveredTile>()
+            .init_resource::<SelectedTile>()
+            .add_systems(
+                Update,
+                (
+                    pick_tile,
+                    (select_tile, tap_select).run_if(not(tool_active).and(not(order_active))),
+                    update_highlight,
+                )
+                    .chain()
+                    .in_set(TilePickingSystems)
+                    .after(CameraSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_picking);
+    }
+}
+
+/// Updates [`HoveredTile`] and [`SelectedTile`], systems reading them run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TilePickingSystems;
+
+/// The cursor and the tile under it.
+#[derive(Resource, Default, Debug)]
+pub struct HoveredTile {
+    /// Cursor position in the window, in logical pixels.
+    pub cursor: Option<Vec2>,
+    /// `None` outside the map or over the UI.
+    pub position: Option<TilePos>,
+}
+
+/// The tile last clicked on, cleared with [`InputAction::Secondary`].
+#[derive(Resource, Default, Debug)]
+pub struct SelectedTile(pub Option<TilePos>);
+
+#[derive(Component)]
+struct TileHighlight;
+
+/// Whether the mouse is over a UI node, according to `bevy_picking`.
+fn is_over_ui(hover_map: &Option<Res<HoverMap>>, node_query: &Query<(), With<Node>>) -> bool {
+    hover_map.as_ref().is_some_and(|hover_map| {
+        hover_map
+            .get(&PointerId::Mouse)
+            .is_some_and(|hits| hits.keys().any(|entity| node_query.contains(*entity)))
+    })
+}
+
+/// Whether a position in the window, in logical pixels, lies on a visible UI node.
+///
+/// Touches have no hover to go by once the finger is lifted, so they are tested against the nodes
+/// themselves.
+pub fn is_on_ui(
+    node_query: &Query<(&ComputedNode, &GlobalTransform, &InheritedVisibility)>,
+    position: Vec2,
+) -> bool {
+    node_query.iter().any(|(node, transform, visibility)| {
+        // Nodes are laid out in physical pixels, around their center
+        let scale = node.inverse_scale_factor();
+        let rect = Rect::from_center_size(transform.translation().truncate() * scale, node.size() * scale);
+        visibility.get() && rect.contains(position)
+    })
+}
+
+/// Converts a position in the window, in logical pixels, into world space.
+pub fn window_to_world(
+    camera_query: &Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    position: Vec2,
+) -> Option<Vec2> {
+    let (camera, camera_transform) = camera_query.get_single().ok()?;
+    camera.viewport_to_world_2d(camera_transform, position).ok()
+}
+
+fn pick_tile(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    hover_map: Option<Res<HoverMap>>,
+    node_query: Query<(), With<Node>>,
+    virtual_cursor: Res<VirtualCursor>,
+    tiles: WorldTiles,
+    mut hovered: ResMut<HoveredTile>,
+) {
+    let cursor = virtual_cursor
+        .get()
+        .or_else(|| window_query.get_single().ok().and_then(|window| window.cursor_position()));
+    let world_position = cursor.and_then(|cursor| window_to_world(&camera_query, cursor));
+    // Every layer shares the same grid, the solid one stands for all of them
+    let position = world_position
+        // The gamepad's virtual cursor isn't a pointer, only the mouse is blocked by the UI
+        .filter(|_| virtual_cursor.active || !is_over_ui(&hover_map, &node_query))
+        .and_then(|world_position| tiles.tile_at(LayerType::Solid, world_position));
+
+    *hovered = HoveredTile { cursor, position };
+}
+
+fn select_tile(actions: Res<Actions>, hovered: Res<HoveredTile>, mut selected: ResMut<SelectedTile>) {
+    if actions.just_pressed(InputAction::Primary) {
+        if let Some(position) = hovered.position {
+            selected.0 = Some(position);
+        }
+    }
+    if actions.just_pressed(InputAction::Secondary) {
+        selected.0 = None;
+    }
+}
+
+fn tap_select(
+    mut gestures: EventReader<TouchGesture>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    node_query: Query<(&ComputedNode, &GlobalTransform, &InheritedVisibility)>,
+    tiles: WorldTiles,
+    mut selected: ResMut<SelectedTile>,
+) {
+    for gesture in gestures.read() {
+        let TouchGesture::Tap { position } = *gesture else {
+            continue;
+        };
+        if is_on_ui(&node_query, position) {
+            continue;
+        }
+        // Tapping outside the map clears the selection, like the secondary action
+        selected.0 = window_to_world(&camera_query, position)
+            .and_then(|world_position| tiles.tile_at(LayerType::Solid, world_position));
+    }
+}
+
+fn update_highlight(
+    mut commands: Commands,
+    hovered: Res<HoveredTile>,
+    tiles: WorldTiles,
+    mut highlight_query: Query<(&mut Transform, &mut Visibility), With<TileHighlight>>,
+) {
+    let center = hovered
+        .position
+        .and_then(|position| tiles.world_position(LayerType::Solid, &position));
+
+    let Ok((mut transform, mut visibility)) = highlight_query.get_single_mut() else {
+        commands.spawn((
+            Name::new("Tile Highlight"),
+            Sprite::from_color(Color::linear_rgba(1.0, 1.0, 1.0, 0.25), Vec2::splat(16.0)),
+            Transform::from_translation(center.unwrap_or_default().extend(HIGHLIGHT_Z)),
+            if center.is_some() { Visibility::Inherited } else { Visibility::Hidden },
+            TileHighlight,
+        ));
+        return;
+    };
+
+    match center {
+        Some(center) => {
+            transform.translation = center.extend(HIGHLIGHT_Z);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+fn clear_picking(
+    mut commands: Commands,
+    mut hovered: ResMut<HoveredTile>,
+    mut selected: ResMut<SelectedTile>,
+    highlight_query: Query<Entity, With<TileHighlight>>,
+) {
+    *hovered = HoveredTile::default();
+    selected.0 = None;
+    for entity in highlight_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,4 +1,8 @@
+use std::ops::RangeInclusive;
+
+use bevy::color::Color;
 use bevy::ecs::system::Resource;
+use bevy::reflect::Reflect;
 
 
 #[derive(Resource)]
@@ -11,24 +15,196 @@ pub struct Tile {
     pub id: u32,
 }
 
+/// Ids of the built-in elements, as used by [`ElementConfigs::default`] and the biome rules.
+pub mod elements {
+    pub const VACUUM: u32 = 0;
+    pub const OXYGEN: u32 = 1;
+    pub const CARBON_DIOXIDE: u32 = 2;
+    pub const NATURAL_GAS: u32 = 3;
+    pub const STEAM: u32 = 4;
+    pub const WATER: u32 = 5;
+    pub const POLLUTED_WATER: u32 = 6;
+    pub const CRUDE_OIL: u32 = 7;
+    pub const SANDSTONE: u32 = 8;
+    pub const GRANITE: u32 = 9;
+    pub const COPPER_ORE: u32 = 10;
+    pub const IRON_ORE: u32 = 11;
+    pub const GOLD_AMALGAM: u32 = 12;
+}
+
+#[derive(Default, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ElementState {
+    #[default]
+    Vacuum,
+    Solid,
+    Liquid,
+    Gas,
+}
+
 #[derive(Resource)]
 pub struct ElementConfigs {
     pub elements: Vec<ElementConfig>,
 }
 
+impl ElementConfigs {
+    pub fn get(&self, id: u32) -> Option<&ElementConfig> {
+        self.elements.iter().find(|element| element.id == id)
+    }
+
+    pub fn state_of(&self, id: u32) -> ElementState {
+        self.get(id).map(|element| element.state).unwrap_or_default()
+    }
+}
+
 #[derive(Resource)]
-pub struct ElementConfig { 
+pub struct ElementConfig {
     pub id: u32,
     pub name: String,
     pub symbol: String,
+    pub state: ElementState,
     pub density: f32,
     pub specific_heat: f32,
+    /// Colour used by the element overlay.
+    pub color: Color,
+    /// Row of the element's sprites in `textures/tiles.png`, see [`crate::world::autotile`].
+    pub texture_index: u32,
 }
 
-impl Default for Tileset {
+impl ElementConfig {
+    #[allow(clippy::too_many_arguments)]
+    fn new(id: u32, name: &str, symbol: &str, state: ElementState, density: f32, specific_heat: f32, color: Color, texture_index: u32) -> Self {
+        Self { id, name: name.to_string(), symbol: symbol.to_string(), state, density, specific_heat, color, texture_index }
+    }
+}
+
+impl Default for ElementConfigs {
     fn default() -> Self {
-        Self { tiles: vec![] }
+        use elements::*;
+        use ElementState::*;
+
+        Self {
+            elements: vec![
+                ElementConfig::new(VACUUM, "Vacuum", "", Vacuum, 0.0, 0.0, Color::srgb(0.0, 0.0, 0.0), 0),
+                ElementConfig::new(OXYGEN, "Oxygen", "O2", Gas, 1.43, 1.005, Color::srgb(0.55, 0.75, 0.95), 5),
+                ElementConfig::new(CARBON_DIOXIDE, "Carbon Dioxide", "CO2", Gas, 1.98, 0.846, Color::srgb(0.45, 0.45, 0.45), 4),
+                ElementConfig::new(NATURAL_GAS, "Natural Gas", "CH4", Gas, 0.72, 2.191, Color::srgb(0.75, 0.65, 0.35), 12),
+                ElementConfig::new(STEAM, "Steam", "H2O", Gas, 0.6, 4.179, Color::srgb(0.9, 0.9, 0.95), 13),
+                ElementConfig::new(WATER, "Water", "H2O", Liquid, 1000.0, 4.179, Color::srgb(0.2, 0.45, 0.9), 1),
+                ElementConfig::new(POLLUTED_WATER, "Polluted Water", "H2O", Liquid, 1010.0, 4.179, Color::srgb(0.45, 0.5, 0.25), 2),
+                ElementConfig::new(CRUDE_OIL, "Crude Oil", "Oil", Liquid, 870.0, 1.69, Color::srgb(0.15, 0.12, 0.1), 3),
+                ElementConfig::new(SANDSTONE, "Sandstone", "Sst", Solid, 2320.0, 0.8, Color::srgb(0.85, 0.7, 0.45), 7),
+                ElementConfig::new(GRANITE, "Granite", "Gr", Solid, 2700.0, 0.79, Color::srgb(0.6, 0.55, 0.55), 8),
+                ElementConfig::new(COPPER_ORE, "Copper Ore", "Cu", Solid, 5000.0, 0.386, Color::srgb(0.75, 0.45, 0.25), 9),
+                ElementConfig::new(IRON_ORE, "Iron Ore", "Fe", Solid, 5150.0, 0.449, Color::srgb(0.6, 0.3, 0.25), 10),
+                ElementConfig::new(GOLD_AMALGAM, "Gold Amalgam", "Au", Solid, 6000.0, 0.15, Color::srgb(0.9, 0.75, 0.25), 11),
+            ],
+        }
     }
 }
 
+/// A random-walk blob of a solid element placed in the Solid layer.
+#[derive(Clone, Debug)]
+pub struct OreVeinRule {
+    pub element: u32,
+    /// How many veins are placed per world.
+    pub count: RangeInclusive<u32>,
+    /// Number of random-walk steps, roughly the size of a vein in tiles.
+    pub length: RangeInclusive<u32>,
+    /// Mass of every ore tile, in kg.
+    pub mass: f32,
+}
+
+/// A circular pocket of liquid or gas carved out of the Solid layer.
+#[derive(Clone, Debug)]
+pub struct ResourcePocketRule {
+    pub element: u32,
+    pub count: RangeInclusive<u32>,
+    pub radius: RangeInclusive<u32>,
+    /// Mass of every tile in the pocket, in kg.
+    pub mass: f32,
+    pub temperature: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct GeyserRule {
+    pub element: u32,
+    pub count: RangeInclusive<u32>,
+    /// Mass emitted per second while erupting, in kg.
+    pub emission_rate: f32,
+    pub temperature: f32,
+    /// Length of a full eruption cycle, in seconds.
+    pub period: f32,
+    /// How long the geyser erupts at the start of every cycle, in seconds.
+    pub active: f32,
+}
 
+#[derive(Clone, Debug)]
+pub struct BiomeConfig {
+    pub id: u32,
+    pub name: String,
+    /// Solid element filling the Solid layer before the features are placed in it.
+    pub rock: u32,
+    pub ore_veins: Vec<OreVeinRule>,
+    pub resource_pockets: Vec<ResourcePocketRule>,
+    pub geysers: Vec<GeyserRule>,
+}
+
+#[derive(Resource)]
+pub struct BiomeConfigs {
+    pub biomes: Vec<BiomeConfig>,
+}
+
+impl BiomeConfigs {
+    pub fn get(&self, id: u32) -> Option<&BiomeConfig> {
+        self.biomes.iter().find(|biome| biome.id == id)
+    }
+}
+
+impl Default for BiomeConfigs {
+    fn default() -> Self {
+        use elements::*;
+
+        Self {
+            biomes: vec![
+                BiomeConfig {
+                    id: 0,
+                    name: "Temperate".to_string(),
+                    rock: SANDSTONE,
+                    ore_veins: vec![
+                        OreVeinRule { element: COPPER_ORE, count: 1..=3, length: 4..=12, mass: 1200.0 },
+                        OreVeinRule { element: GRANITE, count: 1..=2, length: 6..=16, mass: 1600.0 },
+                    ],
+                    resource_pockets: vec![
+                        ResourcePocketRule { element: WATER, count: 1..=2, radius: 1..=3, mass: 900.0, temperature: 22.0 },
+                        ResourcePocketRule { element: OXYGEN, count: 1..=3, radius: 1..=2, mass: 1.8, temperature: 24.0 },
+                    ],
+                    geysers: vec![
+                        GeyserRule { element: STEAM, count: 0..=1, emission_rate: 2.0, temperature: 110.0, period: 60.0, active: 15.0 },
+                    ],
+                },
+                BiomeConfig {
+                    id: 1,
+                    name: "Oil Field".to_string(),
+                    rock: GRANITE,
+                    ore_veins: vec![
+                        OreVeinRule { element: IRON_ORE, count: 1..=3, length: 4..=10, mass: 1400.0 },
+                        OreVeinRule { element: GOLD_AMALGAM, count: 0..=1, length: 3..=6, mass: 1000.0 },
+                    ],
+                    resource_pockets: vec![
+                        ResourcePocketRule { element: CRUDE_OIL, count: 1..=3, radius: 1..=3, mass: 800.0, temperature: 90.0 },
+                        ResourcePocketRule { element: NATURAL_GAS, count: 0..=2, radius: 1..=2, mass: 2.5, temperature: 80.0 },
+                    ],
+                    geysers: vec![
+                        GeyserRule { element: NATURAL_GAS, count: 0..=1, emission_rate: 0.5, temperature: 150.0, period: 120.0, active: 40.0 },
+                    ],
+                },
+            ],
+        }
+    }
+}
+
+impl Default for Tileset {
+    fn default() -> Self {
+        Self { tiles: vec![] }
+    }
+}
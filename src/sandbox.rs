@@ -0,0 +1,541 @@
+//! Tools for painting test scenarios into the world: a brush, an eraser, a flood fill, a heat gun
+//! and ladders.
+//!
+//! Every edit goes through [`WorldTiles`], the same path the simulation uses, so it ends up in
+//! [`crate::world::dirty::DirtyTiles`] and the overlays, auto-tiling and saves pick it up. While a
+//! tool is active, clicks go to the tool instead of selecting tiles.
+
+use std::collections::VecDeque;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::helpers::square_grid::neighbors::Neighbors;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::actions::{Actions, InputAction, InputBindings};
+use crate::navigation::{ladder_bundle, Ladder};
+use crate::picking::{HoveredTile, TilePickingSystems};
+use crate::resources::{elements, ElementConfigs, ElementState};
+use crate::states::generation::GenerationState;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+use crate::world::Grid;
+use crate::GameState;
+
+/// In °C.
+const ABSOLUTE_ZERO: f32 = -273.15;
+
+/// Flood fills stop after this many tiles, so filling the open sky doesn't stall the game.
+const MAX_FLOOD_FILL: usize = 4096;
+
+const MAX_RADIUS: u32 = 16;
+
+/// Size of a tile in world units, used to draw the brush outline.
+const TILE_SIZE: f32 = 16.0;
+
+pub struct SandboxPlugin;
+
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SandboxSettings>()
+            .init_resource::<Sandbox>()
+            .add_systems(OnEnter(GenerationState::Done), spawn_tool_panel.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (select_tool_keys, click_tool_buttons, apply_tool, draw_brush, update_tool_panel)
+                    .chain()
+                    .in_set(SandboxSystems)
+                    .after(TilePickingSystems)
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), reset_sandbox);
+    }
+}
+
+/// Applies the active tool to the world.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SandboxSystems;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SandboxTool {
+    /// Clicks select tiles, see [`crate::picking::SelectedTile`].
+    #[default]
+    None,
+    /// Paints the element while [`InputAction::Primary`] is held.
+    Brush,
+    /// Empties tiles while [`InputAction::Primary`] is held.
+    Eraser,
+    /// Replaces the connected area of the clicked element.
+    FloodFill,
+    /// Heats with [`InputAction::Primary`] and cools with [`InputAction::Secondary`].
+    HeatGun,
+    /// Places or removes a [`Ladder`] in the clicked tile.
+    Ladder,
+}
+
+impl SandboxTool {
+    pub const ALL: [Self; 5] = [Self::Brush, Self::Eraser, Self::FloodFill, Self::HeatGun, Self::Ladder];
+
+    /// The action switching to this tool.
+    fn action(self) -> Option<InputAction> {
+        match self {
+            Self::None => None,
+            Self::Brush => Some(InputAction::Brush),
+            Self::Eraser => Some(InputAction::Eraser),
+            Self::FloodFill => Some(InputAction::FloodFill),
+            Self::HeatGun => Some(InputAction::HeatGun),
+            Self::Ladder => Some(InputAction::Ladder),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Brush => "Brush",
+            Self::Eraser => "Eraser",
+            Self::FloodFill => "Fill",
+            Self::HeatGun => "Heat Gun",
+            Self::Ladder => "Ladder",
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrushShape {
+    #[default]
+    Circle,
+    Square,
+}
+
+#[derive(Resource, Debug)]
+pub struct SandboxSettings {
+    /// Energy the heat gun adds to or removes from every tile under it, in kW.
+    pub heat_gun_power: f32,
+}
+
+impl Default for SandboxSettings {
+    fn default() -> Self {
+        Self { heat_gun_power: 10_000.0 }
+    }
+}
+
+/// The active tool and what it paints.
+#[derive(Resource, Debug)]
+pub struct Sandbox {
+    pub tool: SandboxTool,
+    pub shape: BrushShape,
+    /// In tiles, 0 paints a single tile.
+    pub radius: u32,
+    pub layer: LayerType,
+    pub element: u32,
+    /// In kg.
+    pub mass: f32,
+    /// In °C.
+    pub temperature: f32,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            tool: SandboxTool::None,
+            shape: BrushShape::Circle,
+            radius: 1,
+            layer: LayerType::Solid,
+            element: elements::GRANITE,
+            mass: 1600.0,
+            temperature: 20.0,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Switches to `tool`, or back to none if it is already active.
+    pub fn toggle(&mut self, tool: SandboxTool) {
+        self.tool = if self.tool == tool { SandboxTool::None } else { tool };
+    }
+
+    /// Paints `element` from now on, on the layer matching its state with a full tile of it.
+    pub fn set_element(&mut self, element: u32, element_configs: &ElementConfigs) {
+        let Some(config) = element_configs.get(element) else {
+            return;
+        };
+        self.element = element;
+        self.mass = config.density;
+        self.layer = match config.state {
+            ElementState::Solid => LayerType::Solid,
+            ElementState::Liquid => LayerType::Liquid,
+            ElementState::Gas => LayerType::Gas,
+            ElementState::Vacuum => self.layer,
+        };
+    }
+}
+
+/// Whether a sandbox tool takes the clicks, for use as a run condition.
+pub fn tool_active(sandbox: Option<Res<Sandbox>>) -> bool {
+    sandbox.is_some_and(|sandbox| sandbox.tool != SandboxTool::None)
+}
+
+/// Tiles covered by a brush centred on `center`, clipped to the map.
+pub fn brush_tiles(center: &TilePos, shape: BrushShape, radius: u32, map_size: UVec2) -> Vec<TilePos> {
+    let radius = radius as i32;
+    let mut positions = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if shape == BrushShape::Circle && dx * dx + dy * dy > radius * radius + radius {
+                continue;
+            }
+            let (x, y) = (center.x as i32 + dx, center.y as i32 + dy);
+            if x >= 0 && y >= 0 && (x as u32) < map_size.x && (y as u32) < map_size.y {
+                positions.push(TilePos { x: x as u32, y: y as u32 });
+            }
+        }
+    }
+    positions
+}
+
+/// The area connected to `start` through 4-neighbours for which `is_fillable` holds, up to `limit` tiles.
+pub fn flood_fill(start: &TilePos, map_size: UVec2, limit: usize, mut is_fillable: impl FnMut(&TilePos) -> bool) -> Vec<TilePos> {
+    let size = TilemapSize { x: map_size.x, y: map_size.y };
+    if start.x >= size.x || start.y >= size.y || !is_fillable(start) {
+        return Vec::new();
+    }
+
+    let mut visited = vec![false; size.count()];
+    visited[start.to_index(&size)] = true;
+    let mut queue = VecDeque::from([*start]);
+    let mut area = Vec::new();
+    while let Some(position) = queue.pop_front() {
+        area.push(position);
+        if area.len() >= limit {
+            break;
+        }
+        for neighbour in Neighbors::get_square_neighboring_positions(&position, &size, false).iter() {
+            let index = neighbour.to_index(&size);
+            if !visited[index] {
+                visited[index] = true;
+                if is_fillable(neighbour) {
+                    queue.push_back(*neighbour);
+                }
+            }
+        }
+    }
+    area
+}
+
+#[derive(Component)]
+struct ToolPanel;
+
+#[derive(Component)]
+struct ToolPanelText;
+
+#[derive(Component)]
+struct ToolButton(SandboxTool);
+
+fn spawn_tool_panel(mut commands: Commands, bindings: Res<InputBindings>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(8.0)),
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            BackgroundColor(Color::linear_rgba(0.05, 0.05, 0.05, 0.8)),
+            ToolPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(4.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for tool in SandboxTool::ALL {
+                        let key = tool.action().map_or_else(String::new, |action| bindings.label(action, false));
+                        parent
+                            .spawn((
+                                Button,
+                                Node {
+                                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::linear_rgb(0.15, 0.15, 0.15)),
+                                ToolButton(tool),
+                            ))
+                            .with_child((
+                                Text::new(format!("{key} {}", tool.label())),
+                                TextFont {
+                                    font_size: 14.0,
+                                    ..default()
+                                },
+                                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                            ));
+                    }
+                });
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                ToolPanelText,
+            ));
+        });
+}
+
+fn reset_sandbox(mut commands: Commands, mut sandbox: ResMut<Sandbox>, panel_query: Query<Entity, With<ToolPanel>>) {
+    sandbox.tool = SandboxTool::None;
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn select_tool_keys(
+    actions: Res<Actions>,
+    element_configs: Res<ElementConfigs>,
+    grid_query: Query<&Grid>,
+    mut sandbox: ResMut<Sandbox>,
+) {
+    for tool in SandboxTool::ALL {
+        if tool.action().is_some_and(|action| actions.just_pressed(action)) {
+            sandbox.toggle(tool);
+        }
+    }
+    if sandbox.tool == SandboxTool::None {
+        return;
+    }
+
+    if actions.just_pressed(InputAction::BrushShape) {
+        sandbox.shape = match sandbox.shape {
+            BrushShape::Circle => BrushShape::Square,
+            BrushShape::Square => BrushShape::Circle,
+        };
+    }
+    if actions.just_pressed(InputAction::BrushShrink) {
+        sandbox.radius = sandbox.radius.saturating_sub(1);
+    }
+    if actions.just_pressed(InputAction::BrushGrow) {
+        sandbox.radius = (sandbox.radius + 1).min(MAX_RADIUS);
+    }
+
+    let step = actions.just_pressed(InputAction::NextElement) as i32 - actions.just_pressed(InputAction::PreviousElement) as i32;
+    if step != 0 {
+        // Vacuum is what the eraser is for
+        let paintable: Vec<u32> = element_configs
+            .elements
+            .iter()
+            .map(|element| element.id)
+            .filter(|id| *id != elements::VACUUM)
+            .collect();
+        if !paintable.is_empty() {
+            let index = paintable.iter().position(|id| *id == sandbox.element).unwrap_or_default() as i32;
+            let element = paintable[(index + step).rem_euclid(paintable.len() as i32) as usize];
+            sandbox.set_element(element, &element_configs);
+        }
+    }
+
+    if actions.just_pressed(InputAction::CycleLayer) {
+        if let Ok(grid) = grid_query.get_single() {
+            let layers = grid.get_layers();
+            if !layers.is_empty() {
+                let index = layers.iter().position(|layer| layer.layer_type == sandbox.layer);
+                sandbox.layer = layers[index.map_or(0, |index| (index + 1) % layers.len())].layer_type;
+            }
+        }
+    }
+
+    if actions.just_pressed(InputAction::Colder) {
+        sandbox.temperature = (sandbox.temperature - 10.0).max(ABSOLUTE_ZERO);
+    }
+    if actions.just_pressed(InputAction::Warmer) {
+        sandbox.temperature += 10.0;
+    }
+    if actions.just_pressed(InputAction::Lighter) {
+        sandbox.mass /= 2.0;
+    }
+    if actions.just_pressed(InputAction::Heavier) {
+        sandbox.mass *= 2.0;
+    }
+}
+
+fn click_tool_buttons(interaction_query: Query<(&Interaction, &ToolButton), Changed<Interaction>>, mut sandbox: ResMut<Sandbox>) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            sandbox.toggle(button.0);
+        }
+    }
+}
+
+/// What [`apply_tool`] reads to use the active tool.
+#[derive(SystemParam)]
+struct ToolInput<'w> {
+    time: Res<'w, Time>,
+    actions: Res<'w, Actions>,
+    hovered: Res<'w, HoveredTile>,
+    settings: Res<'w, SandboxSettings>,
+    element_configs: Res<'w, ElementConfigs>,
+}
+
+fn apply_tool(
+    mut commands: Commands,
+    input: ToolInput,
+    sandbox: Res<Sandbox>,
+    mut tiles: WorldTiles,
+    ladder_query: Query<(Entity, &TilePos), With<Ladder>>,
+) {
+    let ToolInput { time, actions, hovered, settings, element_configs } = input;
+    let (Some(position), Some(map_size)) = (hovered.position, tiles.map_size()) else {
+        return;
+    };
+    let layer = sandbox.layer;
+
+    match sandbox.tool {
+        SandboxTool::Brush if actions.pressed(InputAction::Primary) => {
+            for tile_pos in brush_tiles(&position, sandbox.shape, sandbox.radius, map_size) {
+                tiles.set(layer, &tile_pos, sandbox.element, sandbox.mass, Some(sandbox.temperature));
+            }
+        }
+        SandboxTool::Eraser if actions.pressed(InputAction::Primary) => {
+            for tile_pos in brush_tiles(&position, sandbox.shape, sandbox.radius, map_size) {
+                tiles.set(layer, &tile_pos, elements::VACUUM, 0.0, None);
+            }
+        }
+        SandboxTool::FloodFill if actions.just_pressed(InputAction::Primary) => {
+            let Some(target) = tiles.element(layer, &position) else {
+                return;
+            };
+            let area = flood_fill(&position, map_size, MAX_FLOOD_FILL, |tile_pos| tiles.element(layer, tile_pos) == Some(target));
+            for tile_pos in area {
+                tiles.set(layer, &tile_pos, sandbox.element, sandbox.mass, Some(sandbox.temperature));
+            }
+        }
+        SandboxTool::HeatGun => {
+            let direction = match (actions.pressed(InputAction::Primary), actions.pressed(InputAction::Secondary)) {
+                (true, false) => 1.0,
+                (false, true) => -1.0,
+                _ => return,
+            };
+            // In kJ
+            let energy = direction * settings.heat_gun_power * time.delta_secs();
+            for tile_pos in brush_tiles(&position, sandbox.shape, sandbox.radius, map_size) {
+                let Some(tile) = tiles.get(layer, &tile_pos) else {
+                    continue;
+                };
+                // In kJ/K, vacuum has nothing to heat
+                let heat_capacity = element_configs
+                    .get(tile.element)
+                    .map_or(0.0, |config| tile.mass * config.specific_heat);
+                if heat_capacity > 0.0 {
+                    let temperature = (tile.temperature + energy / heat_capacity).max(ABSOLUTE_ZERO);
+                    tiles.set_temperature(layer, &tile_pos, temperature);
+                }
+            }
+        }
+        SandboxTool::Ladder if actions.just_pressed(InputAction::Primary) => {
+            match ladder_query.iter().find(|(_, ladder)| **ladder == position) {
+                Some((entity, _)) => commands.entity(entity).despawn_recursive(),
+                None => {
+                    if let Some(center) = tiles.world_position(LayerType::Solid, &position) {
+                        commands.spawn(ladder_bundle(position, center));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Outlines the area the brush, eraser and heat gun work on.
+fn draw_brush(mut gizmos: Gizmos, hovered: Res<HoveredTile>, sandbox: Res<Sandbox>, tiles: WorldTiles) {
+    if matches!(sandbox.tool, SandboxTool::None | SandboxTool::FloodFill | SandboxTool::Ladder) {
+        return;
+    }
+    let Some(center) = hovered
+        .position
+        .and_then(|position| tiles.world_position(sandbox.layer, &position))
+    else {
+        return;
+    };
+
+    let color = match sandbox.tool {
+        SandboxTool::Eraser => Color::linear_rgb(1.0, 0.3, 0.3),
+        SandboxTool::HeatGun => Color::linear_rgb(1.0, 0.6, 0.1),
+        _ => Color::WHITE,
+    };
+    let extent = (sandbox.radius as f32 + 0.5) * TILE_SIZE;
+    match sandbox.shape {
+        BrushShape::Circle => {
+            gizmos.circle_2d(Isometry2d::from_translation(center), extent, color);
+        }
+        BrushShape::Square => {
+            gizmos.rect_2d(Isometry2d::from_translation(center), Vec2::splat(extent * 2.0), color);
+        }
+    }
+}
+
+fn update_tool_panel(
+    sandbox: Res<Sandbox>,
+    settings: Res<SandboxSettings>,
+    element_configs: Res<ElementConfigs>,
+    mut text_query: Query<&mut Text, With<ToolPanelText>>,
+    mut button_query: Query<(&ToolButton, &Interaction, &mut BackgroundColor)>,
+) {
+    for (button, interaction, mut color) in button_query.iter_mut() {
+        color.0 = match (button.0 == sandbox.tool, interaction) {
+            (true, _) => Color::linear_rgb(0.35, 0.45, 0.25),
+            (false, Interaction::Hovered) => Color::linear_rgb(0.25, 0.25, 0.25),
+            (false, _) => Color::linear_rgb(0.15, 0.15, 0.15),
+        };
+    }
+
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let element = element_configs.get(sandbox.element).map_or("Unknown", |element| element.name.as_str());
+    let description = match sandbox.tool {
+        SandboxTool::None => "Pick a tool to edit the world".to_string(),
+        SandboxTool::HeatGun => format!(
+            "{:?} layer, {:?} radius {}, {:.0} kW",
+            sandbox.layer, sandbox.shape, sandbox.radius, settings.heat_gun_power
+        ),
+        SandboxTool::Eraser => format!("{:?} layer, {:?} radius {}", sandbox.layer, sandbox.shape, sandbox.radius),
+        _ => format!(
+            "{element} on the {:?} layer, {:.1} kg at {:.0} °C, {:?} radius {}",
+            sandbox.layer, sandbox.mass, sandbox.temperature, sandbox.shape, sandbox.radius
+        ),
+    };
+    if text.0 != description {
+        text.0 = description;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brush_tiles_are_clipped_to_the_map() {
+        let map_size = UVec2::new(10, 10);
+
+        assert_eq!(brush_tiles(&TilePos { x: 5, y: 5 }, BrushShape::Square, 0, map_size), [TilePos { x: 5, y: 5 }]);
+        assert_eq!(brush_tiles(&TilePos { x: 5, y: 5 }, BrushShape::Square, 1, map_size).len(), 9);
+        assert_eq!(brush_tiles(&TilePos { x: 5, y: 5 }, BrushShape::Circle, 2, map_size).len(), 21);
+        assert_eq!(brush_tiles(&TilePos { x: 0, y: 9 }, BrushShape::Square, 1, map_size).len(), 4);
+    }
+
+    #[test]
+    fn test_flood_fill_stays_within_the_region() {
+        // A wall at x == 2 splits the map in two
+        let map_size = UVec2::new(5, 3);
+        let area = flood_fill(&TilePos { x: 0, y: 1 }, map_size, usize::MAX, |position| position.x != 2);
+
+        assert_eq!(area.len(), 6);
+        assert!(area.iter().all(|position| position.x < 2));
+        assert!(flood_fill(&TilePos { x: 2, y: 0 }, map_size, usize::MAX, |position| position.x != 2).is_empty());
+        assert_eq!(flood_fill(&TilePos { x: 3, y: 0 }, map_size, 4, |_| true).len(), 4);
+    }
+}
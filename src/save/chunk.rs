@@ -0,0 +1,359 @@
+//! Binary layout of a single chunk of a layer.
+//!
+//! Every chunk stores its tiles as packed arrays in row-major order: element ids as `u16`,
+//! mass, temperature and conductivity as `f32`. Arrays holding a single
+//! value are stored once, and element ids are palette encoded when a chunk only contains a few
+//! elements.
+
+use std::io::{Read, Write};
+
+use bevy::math::UVec2;
+use bevy_ecs_tilemap::tiles::TilePos;
+use serde::{Deserialize, Serialize};
+use simulation::temperature::ThermalConductivity;
+
+use super::SaveError;
+
+const UNIFORM: u8 = 0;
+const PALETTE: u8 = 1;
+const RAW: u8 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    #[default]
+    Deflate,
+    /// Only available with the `zstd` feature.
+    Zstd,
+}
+
+impl Compression {
+    pub fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, SaveError> {
+        match self {
+            Compression::None => Ok(bytes.to_vec()),
+            Compression::Deflate => {
+                let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(zstd::encode_all(bytes, 0)?),
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd => Err(SaveError::UnsupportedCompression(*self)),
+        }
+    }
+
+    /// Decompresses `bytes`, failing as soon as they grow past `max_len` so a crafted save can't
+    /// expand into more than a chunk can hold.
+    pub fn decompress(&self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, SaveError> {
+        match self {
+            Compression::None => read_at_most(bytes, max_len),
+            Compression::Deflate => read_at_most(flate2::read::DeflateDecoder::new(bytes), max_len),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => read_at_most(zstd::stream::read::Decoder::new(bytes)?, max_len),
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd => Err(SaveError::UnsupportedCompression(*self)),
+        }
+    }
+}
+
+fn read_at_most(reader: impl Read, max_len: usize) -> Result<Vec<u8>, SaveError> {
+    let mut bytes = Vec::new();
+    reader.take(max_len as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() > max_len {
+        return Err(SaveError::Deserialize(format!("chunk decompresses to more than {max_len} bytes")));
+    }
+    Ok(bytes)
+}
+
+/// Tiles of one chunk of a layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkSave {
+    /// Position of the chunk, in chunks.
+    pub position: UVec2,
+    /// Size of the chunk in tiles, smaller than the chunk size on the edges of the map.
+    pub size: UVec2,
+    pub elements: Vec<u16>,
+    pub mass: Vec<f32>,
+    pub temperature: Vec<f32>,
+    pub conductivity: Vec<f32>,
+}
+
+impl ChunkSave {
+    pub fn new(position: UVec2, size: UVec2) -> Self {
+        let len = (size.x * size.y) as usize;
+        Self {
+            position,
+            size,
+            elements: vec![0; len],
+            mass: vec![0.0; len],
+            temperature: vec![0.0; len],
+            conductivity: vec![ThermalConductivity::default().value; len],
+        }
+    }
+
+    /// Empty chunks covering a map, in row-major order.
+    pub fn layout(map_size: UVec2, chunk_size: UVec2) -> Vec<Self> {
+        let chunks = (map_size + chunk_size - UVec2::ONE) / chunk_size;
+        let mut layout = Vec::with_capacity((chunks.x * chunks.y) as usize);
+        for y in 0..chunks.y {
+            for x in 0..chunks.x {
+                let position = UVec2::new(x, y);
+                layout.push(Self::new(position, Self::size_at(position, map_size, chunk_size).unwrap_or_default()));
+            }
+        }
+        layout
+    }
+
+    /// Size of the chunk in `position`, or `None` if it lies outside the map.
+    pub fn size_at(position: UVec2, map_size: UVec2, chunk_size: UVec2) -> Option<UVec2> {
+        let origin = UVec2::new(position.x.checked_mul(chunk_size.x)?, position.y.checked_mul(chunk_size.y)?);
+        if origin.x >= map_size.x || origin.y >= map_size.y {
+            return None;
+        }
+        Some((map_size - origin).min(chunk_size))
+    }
+
+    /// Map position of every tile of the chunk, along with its index in the packed arrays.
+    pub fn tile_positions(&self, chunk_size: UVec2) -> impl Iterator<Item = (usize, TilePos)> + '_ {
+        let origin = self.position * chunk_size;
+        (0..self.size.y).flat_map(move |y| {
+            (0..self.size.x).map(move |x| {
+                ((y * self.size.x + x) as usize, TilePos { x: origin.x + x, y: origin.y + y })
+            })
+        })
+    }
+
+    /// The most bytes [`Self::encode`] writes for a chunk of `size`: its size, elements stored raw
+    /// or with the largest palette, and every `f32` array stored raw.
+    pub fn max_encoded_len(size: UVec2) -> usize {
+        let len = (size.x * size.y) as usize;
+        let elements = 1 + (2 * len).max(2 + 2 * (u8::MAX as usize + 1) + len);
+        4 + elements + 3 * (1 + 4 * len)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.size.x as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.size.y as u16).to_le_bytes());
+        encode_elements(&mut bytes, &self.elements);
+        encode_f32s(&mut bytes, &self.mass);
+        encode_f32s(&mut bytes, &self.temperature);
+        encode_f32s(&mut bytes, &self.conductivity);
+        bytes
+    }
+
+    /// Decodes a chunk no larger than `max_size`, checked before any tile is allocated.
+    pub fn decode(position: UVec2, max_size: UVec2, bytes: &[u8]) -> Result<Self, SaveError> {
+        let mut reader = ByteReader { bytes };
+        let size = UVec2::new(reader.u16()? as u32, reader.u16()? as u32);
+        if size.x > max_size.x || size.y > max_size.y {
+            return Err(SaveError::Deserialize(format!("chunk {position} of size {size} is larger than {max_size}")));
+        }
+        let len = (size.x * size.y) as usize;
+        Ok(Self {
+            position,
+            size,
+            elements: decode_elements(&mut reader, len)?,
+            mass: decode_f32s(&mut reader, len)?,
+            temperature: decode_f32s(&mut reader, len)?,
+            conductivity: decode_f32s(&mut reader, len)?,
+        })
+    }
+}
+
+fn encode_elements(bytes: &mut Vec<u8>, elements: &[u16]) {
+    let mut palette: Vec<u16> = vec![];
+    for element in elements {
+        if !palette.contains(element) {
+            palette.push(*element);
+            if palette.len() > u8::MAX as usize + 1 {
+                break;
+            }
+        }
+    }
+
+    match palette.len() {
+        0 | 1 => {
+            bytes.push(UNIFORM);
+            bytes.extend_from_slice(&palette.first().copied().unwrap_or_default().to_le_bytes());
+        }
+        len if len <= u8::MAX as usize + 1 => {
+            bytes.push(PALETTE);
+            bytes.extend_from_slice(&(len as u16).to_le_bytes());
+            for element in palette.iter() {
+                bytes.extend_from_slice(&element.to_le_bytes());
+            }
+            for element in elements {
+                bytes.push(palette.iter().position(|entry| entry == element).unwrap() as u8);
+            }
+        }
+        _ => {
+            bytes.push(RAW);
+            for element in elements {
+                bytes.extend_from_slice(&element.to_le_bytes());
+            }
+        }
+    }
+}
+
+fn decode_elements(reader: &mut ByteReader, len: usize) -> Result<Vec<u16>, SaveError> {
+    match reader.u8()? {
+        UNIFORM => Ok(vec![reader.u16()?; len]),
+        PALETTE => {
+            let palette = (0..reader.u16()?).map(|_| reader.u16()).collect::<Result<Vec<_>, _>>()?;
+            (0..len)
+                .map(|_| {
+                    let index = reader.u8()? as usize;
+                    palette.get(index).copied().ok_or_else(|| {
+                        SaveError::Deserialize(format!("palette index {index} out of range"))
+                    })
+                })
+                .collect()
+        }
+        RAW => (0..len).map(|_| reader.u16()).collect(),
+        tag => Err(SaveError::Deserialize(format!("unknown element encoding {tag}"))),
+    }
+}
+
+fn encode_f32s(bytes: &mut Vec<u8>, values: &[f32]) {
+    match values.first() {
+        Some(first) if values.iter().all(|value| value.to_bits() == first.to_bits()) => {
+            bytes.push(UNIFORM);
+            bytes.extend_from_slice(&first.to_le_bytes());
+        }
+        _ => {
+            bytes.push(RAW);
+            for value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+fn decode_f32s(reader: &mut ByteReader, len: usize) -> Result<Vec<f32>, SaveError> {
+    match reader.u8()? {
+        UNIFORM => Ok(vec![reader.f32()?; len]),
+        RAW => (0..len).map(|_| reader.f32()).collect(),
+        tag => Err(SaveError::Deserialize(format!("unknown value encoding {tag}"))),
+    }
+}
+
+/// Reads little endian values from a byte slice.
+pub(super) struct ByteReader<'a> {
+    pub bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], SaveError> {
+        if self.bytes.len() < len {
+            return Err(SaveError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, SaveError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, SaveError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, SaveError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, SaveError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, SaveError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(chunk: &ChunkSave) -> (usize, ChunkSave) {
+        let bytes = chunk.encode();
+        (bytes.len(), ChunkSave::decode(chunk.position, chunk.size, &bytes).unwrap())
+    }
+
+    #[test]
+    fn test_uniform_chunk_is_tiny() {
+        let mut chunk = ChunkSave::new(UVec2::new(1, 2), UVec2::new(32, 32));
+        chunk.elements.fill(9);
+        chunk.mass.fill(1600.0);
+        chunk.temperature.fill(21.5);
+
+        let (len, decoded) = round_trip(&chunk);
+        assert_eq!(decoded, chunk);
+        // size + four uniform arrays
+        assert_eq!(len, 4 + 3 + 5 + 5 + 5);
+    }
+
+    #[test]
+    fn test_palette_and_raw_chunks() {
+        let mut chunk = ChunkSave::new(UVec2::ZERO, UVec2::new(4, 3));
+        for (index, element) in chunk.elements.iter_mut().enumerate() {
+            *element = (index % 3) as u16 * 100;
+        }
+        for (index, mass) in chunk.mass.iter_mut().enumerate() {
+            *mass = index as f32 * 0.5;
+        }
+
+        let (_, decoded) = round_trip(&chunk);
+        assert_eq!(decoded, chunk);
+
+        let mut chunk = ChunkSave::new(UVec2::ZERO, UVec2::new(32, 32));
+        for (index, element) in chunk.elements.iter_mut().enumerate() {
+            *element = index as u16;
+        }
+        let (_, decoded) = round_trip(&chunk);
+        assert_eq!(decoded, chunk);
+    }
+
+    #[test]
+    fn test_rejects_oversized_chunk() {
+        let mut bytes = ChunkSave::new(UVec2::ZERO, UVec2::new(8, 8)).encode();
+        bytes[..4].fill(0xff);
+        let result = ChunkSave::decode(UVec2::ZERO, UVec2::new(8, 8), &bytes);
+        assert!(matches!(result, Err(SaveError::Deserialize(_))));
+
+        assert_eq!(ChunkSave::size_at(UVec2::new(1, 0), UVec2::new(40, 3), UVec2::splat(32)), Some(UVec2::new(8, 3)));
+        assert_eq!(ChunkSave::size_at(UVec2::new(0, 1), UVec2::new(40, 3), UVec2::splat(32)), None);
+    }
+
+    #[test]
+    fn test_compression_round_trip() {
+        let bytes = ChunkSave::new(UVec2::ZERO, UVec2::new(8, 8)).encode();
+        for compression in [Compression::None, Compression::Deflate] {
+            let compressed = compression.compress(&bytes).unwrap();
+            assert_eq!(compression.decompress(&compressed, bytes.len()).unwrap(), bytes);
+            assert!(matches!(compression.decompress(&compressed, bytes.len() - 1), Err(SaveError::Deserialize(_))));
+        }
+    }
+
+    #[test]
+    fn test_max_encoded_len() {
+        let size = UVec2::new(32, 32);
+        let mut chunk = ChunkSave::new(UVec2::ZERO, size);
+        for (index, element) in chunk.elements.iter_mut().enumerate() {
+            *element = (index % 200) as u16;
+            chunk.mass[index] = index as f32;
+            chunk.temperature[index] = index as f32;
+            chunk.conductivity[index] = index as f32;
+        }
+        assert!(chunk.encode().len() <= ChunkSave::max_encoded_len(size));
+
+        for (index, element) in chunk.elements.iter_mut().enumerate() {
+            *element = index as u16;
+        }
+        assert!(chunk.encode().len() <= ChunkSave::max_encoded_len(size));
+    }
+}
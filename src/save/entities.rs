@@ -0,0 +1,256 @@
+//! The things standing on the tiles of a save: colonists, items, ladders, blueprints and the orders
+//! on the job board.
+//!
+//! They are stored in the save metadata, and spawned again once the layers of a loaded world are
+//! built. Jobs only the colonists post, such as deliveries, are posted again from the blueprints,
+//! and colonists pick their work again.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::TilePos;
+use serde::{Deserialize, Serialize};
+use simulation::temperature::Temperature;
+
+use super::{PendingLoad, SaveError};
+use crate::colonist::{spawn_colonist, Asleep, Colonist, Needs, Stats, Stressed, Trait};
+use crate::items::{spawn_item, Item};
+use crate::jobs::{Job, JobBoard, JobKind};
+use crate::navigation::{ladder_bundle, Ladder};
+use crate::orders::{blueprint_bundle, Blueprint, Building};
+use crate::resources::ElementConfigs;
+use crate::world::layer::LayerType;
+use crate::world::tiles::WorldTiles;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct EntitiesSave {
+    pub colonists: Vec<ColonistSave>,
+    pub items: Vec<ItemSave>,
+    pub ladders: Vec<(u32, u32)>,
+    pub blueprints: Vec<BlueprintSave>,
+    pub orders: Vec<OrderSave>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ColonistSave {
+    pub position: (u32, u32),
+    pub name: String,
+    pub traits: Vec<Trait>,
+    pub hue: f32,
+    pub stats: Stats,
+    pub needs: Needs,
+    pub asleep: bool,
+    pub stressed: bool,
+}
+
+impl ColonistSave {
+    fn capture(
+        colonist: &Colonist,
+        stats: &Stats,
+        needs: &Needs,
+        position: &TilePos,
+        asleep: bool,
+        stressed: bool,
+    ) -> Self {
+        Self {
+            position: (position.x, position.y),
+            name: colonist.name.clone(),
+            traits: colonist.traits.clone(),
+            hue: colonist.hue,
+            stats: *stats,
+            needs: *needs,
+            asleep,
+            stressed,
+        }
+    }
+
+    fn restore(&self) -> (Colonist, TilePos) {
+        let colonist = Colonist { name: self.name.clone(), traits: self.traits.clone(), hue: self.hue };
+        (colonist, TilePos { x: self.position.0, y: self.position.1 })
+    }
+}
+
+/// An item, carried or not.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemSave {
+    pub position: (u32, u32),
+    pub element: u32,
+    /// In kg.
+    pub mass: f32,
+    /// In °C.
+    pub temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BlueprintSave {
+    pub position: (u32, u32),
+    pub building: Building,
+    pub priority: u32,
+    pub element: Option<u32>,
+    pub delivered: f32,
+    pub temperature: f32,
+}
+
+impl BlueprintSave {
+    fn capture(blueprint: &Blueprint, position: &TilePos) -> Self {
+        Self {
+            position: (position.x, position.y),
+            building: blueprint.building,
+            priority: blueprint.priority,
+            element: blueprint.element,
+            delivered: blueprint.delivered,
+            temperature: blueprint.temperature,
+        }
+    }
+
+    /// Fails on delivered mass that isn't a number, or that has no element to build with.
+    pub(super) fn validate(&self) -> Result<(), SaveError> {
+        if !(self.delivered.is_finite() && self.delivered >= 0.0) || (self.delivered > 0.0 && self.element.is_none()) {
+            return Err(SaveError::Deserialize(format!(
+                "blueprint at {:?} has {} kg delivered of element {:?}",
+                self.position, self.delivered, self.element
+            )));
+        }
+        Ok(())
+    }
+
+    /// Never more delivered than the building takes.
+    fn restore(&self) -> (Blueprint, TilePos) {
+        let blueprint = Blueprint {
+            building: self.building,
+            priority: self.priority,
+            element: self.element,
+            delivered: self.delivered.min(self.building.mass()),
+            temperature: self.temperature,
+        };
+        (blueprint, TilePos { x: self.position.0, y: self.position.1 })
+    }
+}
+
+/// A job the player posted, without its worker.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OrderSave {
+    pub position: (u32, u32),
+    pub kind: JobKind,
+    pub priority: u32,
+}
+
+impl OrderSave {
+    /// Whether the player posted `job`, rather than a blueprint.
+    fn is_order(job: &Job) -> bool {
+        matches!(job.kind, JobKind::Dig | JobKind::Mop)
+    }
+
+    fn capture(job: &Job) -> Self {
+        Self { position: (job.position.x, job.position.y), kind: job.kind, priority: job.priority }
+    }
+
+    fn restore(&self) -> Job {
+        Job::new(self.kind, TilePos { x: self.position.0, y: self.position.1 }, self.priority)
+    }
+}
+
+/// Everything needed to capture the entities into an [`EntitiesSave`].
+#[derive(SystemParam)]
+pub struct EntitySnapshot<'w, 's> {
+    /// Missing in headless runs, which have no jobs.
+    board: Option<Res<'w, JobBoard>>,
+    colonist_query: Query<
+        'w,
+        's,
+        (&'static Colonist, &'static Stats, &'static Needs, &'static TilePos, Has<Asleep>, Has<Stressed>),
+    >,
+    item_query: Query<'w, 's, (&'static Item, &'static Temperature, &'static TilePos)>,
+    ladder_query: Query<'w, 's, &'static TilePos, With<Ladder>>,
+    blueprint_query: Query<'w, 's, (&'static Blueprint, &'static TilePos)>,
+}
+
+impl EntitySnapshot<'_, '_> {
+    pub fn capture(&self) -> EntitiesSave {
+        EntitiesSave {
+            colonists: self
+                .colonist_query
+                .iter()
+                .map(|(colonist, stats, needs, position, asleep, stressed)| {
+                    ColonistSave::capture(colonist, stats, needs, position, asleep, stressed)
+                })
+                .collect(),
+            items: self
+                .item_query
+                .iter()
+                .map(|(item, temperature, position)| ItemSave {
+                    position: (position.x, position.y),
+                    element: item.element,
+                    mass: item.mass,
+                    temperature: temperature.value,
+                })
+                .collect(),
+            ladders: self.ladder_query.iter().map(|position| (position.x, position.y)).collect(),
+            blueprints: self
+                .blueprint_query
+                .iter()
+                .map(|(blueprint, position)| BlueprintSave::capture(blueprint, position))
+                .collect(),
+            orders: self
+                .board
+                .iter()
+                .flat_map(|board| board.iter())
+                .filter(|(_, job)| OrderSave::is_order(job))
+                .map(|(_, job)| OrderSave::capture(job))
+                .collect(),
+        }
+    }
+}
+
+/// Spawns the saved entities on the layers of the loaded world, and finishes the load.
+pub(super) fn restore_entities(
+    mut commands: Commands,
+    pending: Res<PendingLoad>,
+    element_configs: Res<ElementConfigs>,
+    tiles: WorldTiles,
+    mut board: ResMut<JobBoard>,
+) {
+    let entities = &pending.0.entities;
+
+    for colonist in entities.colonists.iter() {
+        let (restored, position) = colonist.restore();
+        let Some(center) = tiles.world_position(LayerType::Solid, &position) else {
+            continue;
+        };
+        let mut entity = spawn_colonist(&mut commands, restored, colonist.stats, colonist.needs, position, center);
+        if colonist.asleep {
+            entity.insert(Asleep);
+        }
+        if colonist.stressed {
+            entity.insert(Stressed);
+        }
+    }
+    for item in entities.items.iter() {
+        let position = TilePos { x: item.position.0, y: item.position.1 };
+        let Some(center) = tiles.world_position(LayerType::Solid, &position) else {
+            continue;
+        };
+        let color = element_configs.get(item.element).map_or(Color::WHITE, |config| config.color);
+        spawn_item(&mut commands, item.element, item.mass, item.temperature, color, position, center);
+    }
+    for position in entities.ladders.iter() {
+        let position = TilePos { x: position.0, y: position.1 };
+        if let Some(center) = tiles.world_position(LayerType::Solid, &position) {
+            commands.spawn(ladder_bundle(position, center));
+        }
+    }
+    for blueprint in entities.blueprints.iter() {
+        let (blueprint, position) = blueprint.restore();
+        if tiles.world_position(LayerType::Solid, &position).is_some() {
+            commands.spawn(blueprint_bundle(blueprint, position));
+        }
+    }
+    for order in entities.orders.iter() {
+        let job = order.restore();
+        if tiles.world_position(LayerType::Solid, &job.position).is_some() {
+            board.post(job);
+        }
+    }
+
+    commands.remove_resource::<PendingLoad>();
+}
@@ -0,0 +1,631 @@
+//! On-disk layout of a world save.
+//!
+//! ```text
+//! offset  size  field
+//! 0       4     magic bytes `FALL`
+//! 4       4     format version, little endian
+//! 8       4     CRC-32 of the payload, little endian
+//! 12      8     payload length in bytes, little endian
+//! 20      ..    payload
+//! ```
+//!
+//! Since version 2 the payload is:
+//!
+//! ```text
+//! u32         length of the metadata
+//! ..          metadata (RON): map size, seed, biome, tick, geysers, layers, chunk size, compression
+//!             and entities
+//! u32         number of chunks
+//! 25 * count  chunk index: layer u8, chunk x u32, chunk y u32, offset u64, length u32, CRC-32 u32
+//! ..          chunk data, see [`super::chunk`], each chunk compressed on its own
+//! ```
+//!
+//! Every chunk has its own checksum, checked by [`ChunkReader`] as it decompresses the chunk.
+//!
+//! The payload is decoded with the layout of the version found in the header and then
+//! migrated one version at a time up to [`FORMAT_VERSION`].
+
+use std::fmt;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use bevy::math::UVec2;
+use serde::{Deserialize, Serialize};
+use simulation::SimulationTick;
+
+use super::chunk::{ByteReader, ChunkSave, Compression};
+use super::entities::BlueprintSave;
+use super::{EntitiesSave, GeyserSave, LayerSave, WorldSave};
+use crate::world::layer::LayerType;
+use crate::world::CHUNK_SIZE;
+
+pub const MAGIC: [u8; 4] = *b"FALL";
+
+/// Version written by [`encode`]. Bump it and add a step to [`VersionedSave::migrate`]
+/// whenever the layout of [`WorldSave`] changes in a way older payloads can't be read with,
+/// e.g. a renamed `LayerType` variant or a new component field without a default.
+pub const FORMAT_VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 20;
+const INDEX_ENTRY_SIZE: usize = 25;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    /// The file doesn't start with [`MAGIC`].
+    BadMagic,
+    /// The file is shorter than its header claims.
+    Truncated,
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The file was written by a newer version of the game.
+    UnsupportedVersion { found: u32, latest: u32 },
+    /// Only the latest format can be streamed, older ones have to be migrated with [`decode`].
+    NotStreamable { version: u32 },
+    /// The game was built without support for the compression used by the file.
+    UnsupportedCompression(Compression),
+    Serialize(String),
+    Deserialize(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "io error: {error}"),
+            SaveError::BadMagic => write!(f, "not a save file"),
+            SaveError::Truncated => write!(f, "save file is truncated"),
+            SaveError::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch (expected {expected:#010x}, got {actual:#010x})")
+            }
+            SaveError::UnsupportedVersion { found, latest } => {
+                write!(f, "save format version {found} is newer than the supported version {latest}")
+            }
+            SaveError::NotStreamable { version } => {
+                write!(f, "save format version {version} can't be streamed, it needs to be migrated first")
+            }
+            SaveError::UnsupportedCompression(compression) => {
+                write!(f, "{compression:?} compression is not supported by this build")
+            }
+            SaveError::Serialize(error) => write!(f, "failed to serialize: {error}"),
+            SaveError::Deserialize(error) => write!(f, "failed to deserialize: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => SaveError::Truncated,
+            _ => SaveError::Io(error),
+        }
+    }
+}
+
+/// Layouts of older format versions, kept around to migrate their saves.
+mod v1 {
+    use serde::{Deserialize, Serialize};
+    use simulation::temperature::HeatCell;
+    use simulation::SimulationTick;
+
+    use crate::save::GeyserSave;
+    use crate::world::layer::LayerType;
+
+    /// Version 1 stored the whole world as RON, one entry per tile.
+    #[derive(Serialize, Deserialize)]
+    pub struct WorldSave {
+        pub map_size: (u32, u32),
+        pub seed: u32,
+        pub biome: Option<u32>,
+        pub tick: SimulationTick,
+        pub layers: Vec<LayerSave>,
+        pub geysers: Vec<GeyserSave>,
+    }
+
+    /// Tiles of a layer, in `TilePos::to_index` order.
+    #[derive(Serialize, Deserialize)]
+    pub struct LayerSave {
+        pub layer_type: LayerType,
+        pub tiles: Vec<TileSave>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct TileSave {
+        pub element: u32,
+        pub mass: f32,
+        pub heat: HeatCell,
+    }
+}
+
+/// Everything but the tiles, stored in front of the chunk index.
+#[derive(Serialize, Deserialize)]
+struct SaveMeta {
+    map_size: (u32, u32),
+    seed: u32,
+    biome: Option<u32>,
+    tick: SimulationTick,
+    geysers: Vec<GeyserSave>,
+    layers: Vec<LayerType>,
+    chunk_size: (u32, u32),
+    compression: Compression,
+    entities: EntitiesSave,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ChunkEntry {
+    layer: u8,
+    position: UVec2,
+    /// Offset from the start of the chunk data.
+    offset: u64,
+    length: u32,
+    checksum: u32,
+}
+
+/// A decoded payload, in the layout of the version it was written with.
+enum VersionedSave {
+    V1(v1::WorldSave),
+    V2(WorldSave),
+}
+
+impl VersionedSave {
+    fn decode(version: u32, bytes: &[u8], payload: &[u8]) -> Result<Self, SaveError> {
+        match version {
+            1 => {
+                let payload = std::str::from_utf8(payload).map_err(|error| SaveError::Deserialize(error.to_string()))?;
+                let save = ron::from_str(payload).map_err(|error| SaveError::Deserialize(error.to_string()))?;
+                Ok(VersionedSave::V1(save))
+            }
+            2 => Ok(VersionedSave::V2(ChunkReader::open(Cursor::new(bytes))?.read_all()?)),
+            found => Err(SaveError::UnsupportedVersion { found, latest: FORMAT_VERSION }),
+        }
+    }
+
+    fn version(&self) -> u32 {
+        match self {
+            VersionedSave::V1(_) => 1,
+            VersionedSave::V2(_) => 2,
+        }
+    }
+
+    /// Upgrades the save to the next version.
+    fn migrate(self) -> Result<Self, SaveError> {
+        match self {
+            // Per-tile entries are split into packed chunks
+            VersionedSave::V1(save) => {
+                let map_size = UVec2::new(save.map_size.0, save.map_size.1);
+                let layers = save
+                    .layers
+                    .into_iter()
+                    .map(|layer| {
+                        let mut chunks = ChunkSave::layout(map_size, CHUNK_SIZE);
+                        for chunk in chunks.iter_mut() {
+                            let tiles: Vec<_> = chunk.tile_positions(CHUNK_SIZE).collect();
+                            for (index, position) in tiles {
+                                let Some(tile) = layer.tiles.get((position.y * map_size.x + position.x) as usize) else {
+                                    continue;
+                                };
+                                chunk.elements[index] = u16::try_from(tile.element)
+                                    .map_err(|error| SaveError::Deserialize(error.to_string()))?;
+                                chunk.mass[index] = tile.mass;
+                                chunk.temperature[index] = tile.heat.temperature.value;
+                                chunk.conductivity[index] = tile.heat.conductivity.value;
+                            }
+                        }
+                        Ok(LayerSave { layer_type: layer.layer_type, chunks })
+                    })
+                    .collect::<Result<_, SaveError>>()?;
+
+                Ok(VersionedSave::V2(WorldSave {
+                    map_size: save.map_size,
+                    seed: save.seed,
+                    biome: save.biome,
+                    tick: save.tick,
+                    chunk_size: (CHUNK_SIZE.x, CHUNK_SIZE.y),
+                    layers,
+                    geysers: save.geysers,
+                    entities: EntitiesSave::default(),
+                }))
+            }
+            VersionedSave::V2(_) => Ok(self),
+        }
+    }
+
+    fn into_latest(mut self) -> Result<WorldSave, SaveError> {
+        while self.version() < FORMAT_VERSION {
+            self = self.migrate()?;
+        }
+        match self {
+            VersionedSave::V2(save) => Ok(save),
+            save => Err(SaveError::UnsupportedVersion { found: save.version(), latest: FORMAT_VERSION }),
+        }
+    }
+}
+
+pub fn encode(save: &WorldSave, compression: Compression) -> Result<Vec<u8>, SaveError> {
+    let meta = SaveMeta {
+        map_size: save.map_size,
+        seed: save.seed,
+        biome: save.biome,
+        tick: save.tick,
+        geysers: save.geysers.clone(),
+        layers: save.layers.iter().map(|layer| layer.layer_type).collect(),
+        chunk_size: save.chunk_size,
+        compression,
+        entities: save.entities.clone(),
+    };
+    let meta = ron::to_string(&meta).map_err(|error| SaveError::Serialize(error.to_string()))?;
+
+    let mut index = Vec::new();
+    let mut data = Vec::new();
+    for (layer, layer_save) in save.layers.iter().enumerate() {
+        for chunk in layer_save.chunks.iter() {
+            let compressed = compression.compress(&chunk.encode())?;
+            index.push(ChunkEntry {
+                layer: layer as u8,
+                position: chunk.position,
+                offset: data.len() as u64,
+                length: compressed.len() as u32,
+                checksum: crc32fast::hash(&compressed),
+            });
+            data.extend_from_slice(&compressed);
+        }
+    }
+
+    let mut payload = Vec::with_capacity(8 + meta.len() + index.len() * INDEX_ENTRY_SIZE + data.len());
+    payload.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+    payload.extend_from_slice(meta.as_bytes());
+    payload.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    for entry in index.iter() {
+        payload.push(entry.layer);
+        payload.extend_from_slice(&entry.position.x.to_le_bytes());
+        payload.extend_from_slice(&entry.position.y.to_le_bytes());
+        payload.extend_from_slice(&entry.offset.to_le_bytes());
+        payload.extend_from_slice(&entry.length.to_le_bytes());
+        payload.extend_from_slice(&entry.checksum.to_le_bytes());
+    }
+    payload.extend_from_slice(&data);
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Parses the header, returning the format version, payload checksum and payload length.
+fn read_header(header: &[u8]) -> Result<(u32, u32, u64), SaveError> {
+    if header.len() < MAGIC.len() || header[..MAGIC.len()] != MAGIC {
+        return Err(SaveError::BadMagic);
+    }
+    let mut reader = ByteReader { bytes: header.get(MAGIC.len()..HEADER_SIZE).ok_or(SaveError::Truncated)? };
+    let version = reader.u32()?;
+    if version > FORMAT_VERSION {
+        return Err(SaveError::UnsupportedVersion { found: version, latest: FORMAT_VERSION });
+    }
+    Ok((version, reader.u32()?, reader.u64()?))
+}
+
+pub fn decode(bytes: &[u8]) -> Result<WorldSave, SaveError> {
+    let (version, expected, length) = read_header(bytes)?;
+    let payload = usize::try_from(length)
+        .ok()
+        .and_then(|length| bytes.get(HEADER_SIZE..HEADER_SIZE.checked_add(length)?))
+        .ok_or(SaveError::Truncated)?;
+
+    let actual = crc32fast::hash(payload);
+    if actual != expected {
+        return Err(SaveError::ChecksumMismatch { expected, actual });
+    }
+
+    let save = VersionedSave::decode(version, bytes, payload)?.into_latest()?;
+    save.geysers.iter().try_for_each(GeyserSave::validate)?;
+    save.entities.blueprints.iter().try_for_each(BlueprintSave::validate)?;
+    Ok(save)
+}
+
+/// Reads `len` bytes, failing before allocating them when they would go past `end`, so corrupt
+/// lengths can't ask for more memory than the file holds.
+fn read_bytes<R: Read + Seek>(reader: &mut R, len: u64, end: u64) -> Result<Vec<u8>, SaveError> {
+    let position = reader.stream_position()?;
+    if position.checked_add(len).is_none_or(|stop| stop > end) {
+        return Err(SaveError::Truncated);
+    }
+    let mut bytes = vec![0; usize::try_from(len).map_err(|_| SaveError::Truncated)?];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads the chunks of a save one at a time, without loading the whole file.
+pub struct ChunkReader<R> {
+    reader: R,
+    meta: SaveMeta,
+    index: Vec<ChunkEntry>,
+    data_start: u64,
+    /// End of the payload in the stream, or of the stream if it is shorter.
+    end: u64,
+}
+
+impl<R: Read + Seek> ChunkReader<R> {
+    /// Reads the header, metadata and chunk index. The payload checksum is not verified,
+    /// every chunk is checked against its own checksum when it's read.
+    pub fn open(mut reader: R) -> Result<Self, SaveError> {
+        let start = reader.stream_position()?;
+        let stream_end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let (version, _, length) = read_header(&header)?;
+        if version != FORMAT_VERSION {
+            return Err(SaveError::NotStreamable { version });
+        }
+        let end = (start + HEADER_SIZE as u64).saturating_add(length).min(stream_end);
+
+        let mut meta_len = [0; 4];
+        reader.read_exact(&mut meta_len)?;
+        let meta = read_bytes(&mut reader, u32::from_le_bytes(meta_len) as u64, end)?;
+        let meta: SaveMeta = std::str::from_utf8(&meta)
+            .map_err(|error| error.to_string())
+            .and_then(|meta| ron::from_str(meta).map_err(|error| error.to_string()))
+            .map_err(SaveError::Deserialize)?;
+
+        let mut count = [0; 4];
+        reader.read_exact(&mut count)?;
+        let index = read_bytes(&mut reader, u32::from_le_bytes(count) as u64 * INDEX_ENTRY_SIZE as u64, end)?;
+        let mut index_reader = ByteReader { bytes: &index };
+        let index = (0..u32::from_le_bytes(count))
+            .map(|_| {
+                Ok(ChunkEntry {
+                    layer: index_reader.u8()?,
+                    position: UVec2::new(index_reader.u32()?, index_reader.u32()?),
+                    offset: index_reader.u64()?,
+                    length: index_reader.u32()?,
+                    checksum: index_reader.u32()?,
+                })
+            })
+            .collect::<Result<Vec<_>, SaveError>>()?;
+
+        let data_start = reader.stream_position()?;
+        Ok(Self { reader, meta, index, data_start, end })
+    }
+
+    pub fn map_size(&self) -> UVec2 {
+        UVec2::new(self.meta.map_size.0, self.meta.map_size.1)
+    }
+
+    pub fn chunk_size(&self) -> UVec2 {
+        UVec2::new(self.meta.chunk_size.0, self.meta.chunk_size.1)
+    }
+
+    fn read_entry(&mut self, entry: &ChunkEntry) -> Result<ChunkSave, SaveError> {
+        let max_size = ChunkSave::size_at(entry.position, self.map_size(), self.chunk_size())
+            .ok_or_else(|| SaveError::Deserialize(format!("chunk {} lies outside the map", entry.position)))?;
+        let offset = self.data_start.checked_add(entry.offset).ok_or(SaveError::Truncated)?;
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let compressed = read_bytes(&mut self.reader, entry.length as u64, self.end)?;
+
+        let actual = crc32fast::hash(&compressed);
+        if actual != entry.checksum {
+            return Err(SaveError::ChecksumMismatch { expected: entry.checksum, actual });
+        }
+        let bytes = self.meta.compression.decompress(&compressed, ChunkSave::max_encoded_len(max_size))?;
+        ChunkSave::decode(entry.position, max_size, &bytes)
+    }
+
+    /// Reads every chunk into a full [`WorldSave`].
+    pub fn read_all(mut self) -> Result<WorldSave, SaveError> {
+        let mut layers: Vec<LayerSave> = self
+            .meta
+            .layers
+            .iter()
+            .map(|layer_type| LayerSave { layer_type: *layer_type, chunks: vec![] })
+            .collect();
+        for entry in self.index.clone() {
+            let chunk = self.read_entry(&entry)?;
+            let layer = layers
+                .get_mut(entry.layer as usize)
+                .ok_or_else(|| SaveError::Deserialize(format!("unknown layer {}", entry.layer)))?;
+            layer.chunks.push(chunk);
+        }
+
+        let meta = self.meta;
+        Ok(WorldSave {
+            map_size: meta.map_size,
+            seed: meta.seed,
+            biome: meta.biome,
+            tick: meta.tick,
+            chunk_size: meta.chunk_size,
+            layers,
+            geysers: meta.geysers,
+            entities: meta.entities,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simulation::temperature::{HeatCell, ThermalConductivity};
+
+    use crate::colonist::{Needs, Stats, Trait};
+    use crate::jobs::JobKind;
+    use crate::orders::Building;
+    use crate::save::entities::{BlueprintSave, ColonistSave, ItemSave, OrderSave};
+
+    fn test_save() -> WorldSave {
+        let map_size = UVec2::new(40, 3);
+        let mut chunks = ChunkSave::layout(map_size, CHUNK_SIZE);
+        chunks[0].elements[0] = 8;
+        chunks[0].mass[0] = 1200.0;
+        chunks[1].temperature.fill(30.0);
+        chunks[1].conductivity[0] = 0.25;
+
+        WorldSave {
+            map_size: (map_size.x, map_size.y),
+            seed: 42,
+            biome: Some(0),
+            tick: SimulationTick(7),
+            chunk_size: (CHUNK_SIZE.x, CHUNK_SIZE.y),
+            layers: vec![LayerSave { layer_type: LayerType::Solid, chunks }],
+            geysers: vec![],
+            entities: EntitiesSave {
+                colonists: vec![ColonistSave {
+                    position: (2, 0),
+                    name: "Ada".to_string(),
+                    traits: vec![Trait::Mole],
+                    hue: 120.0,
+                    stats: Stats { digging: 4, ..Default::default() },
+                    needs: Needs { stamina: 10.0, ..Default::default() },
+                    asleep: true,
+                    stressed: false,
+                }],
+                items: vec![ItemSave { position: (6, 0), element: 8, mass: 150.0, temperature: 40.0 }],
+                ladders: vec![(3, 1)],
+                blueprints: vec![BlueprintSave {
+                    position: (4, 2),
+                    building: Building::Ladder,
+                    priority: 5,
+                    element: Some(8),
+                    delivered: 20.0,
+                    temperature: 25.0,
+                }],
+                orders: vec![OrderSave { position: (5, 0), kind: JobKind::Dig, priority: 5 }],
+            },
+        }
+    }
+
+    fn with_header(version: u32, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for compression in [Compression::None, Compression::Deflate] {
+            let bytes = encode(&test_save(), compression).unwrap();
+            let save = decode(&bytes).unwrap();
+
+            assert_eq!(save.map_size, (40, 3));
+            assert_eq!(save.seed, 42);
+            assert_eq!(save.tick, SimulationTick(7));
+            assert_eq!(save.layers[0].layer_type, LayerType::Solid);
+            assert_eq!(save.layers[0].chunks, test_save().layers[0].chunks);
+            assert_eq!(save.entities, test_save().entities);
+        }
+    }
+
+    #[test]
+    fn test_migrates_v1() {
+        let save = v1::WorldSave {
+            map_size: (2, 1),
+            seed: 42,
+            biome: None,
+            tick: SimulationTick(3),
+            layers: vec![v1::LayerSave {
+                layer_type: LayerType::Liquid,
+                tiles: vec![
+                    v1::TileSave {
+                        element: 5,
+                        mass: 900.0,
+                        heat: HeatCell { conductivity: ThermalConductivity { value: 0.5 }, ..Default::default() },
+                    },
+                    v1::TileSave { element: 0, mass: 0.0, heat: HeatCell::default() },
+                ],
+            }],
+            geysers: vec![],
+        };
+        let bytes = with_header(1, ron::to_string(&save).unwrap().as_bytes());
+
+        let reader = ChunkReader::open(Cursor::new(bytes.clone()));
+        assert!(matches!(reader, Err(SaveError::NotStreamable { version: 1 })));
+        let save = decode(&bytes).unwrap();
+        let chunk = &save.layers[0].chunks[0];
+        assert_eq!(save.tick, SimulationTick(3));
+        assert_eq!(chunk.size, UVec2::new(2, 1));
+        assert_eq!(chunk.elements, vec![5, 0]);
+        assert_eq!(chunk.mass, vec![900.0, 0.0]);
+        assert_eq!(chunk.conductivity, vec![0.5, ThermalConductivity::default().value]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_geyser_cycle() {
+        for (period, elapsed) in [(-1.0, 0.0), (f32::NAN, 0.0), (10.0, f32::INFINITY)] {
+            let mut save = test_save();
+            save.geysers.push(GeyserSave {
+                position: (1, 1),
+                element: 5,
+                emission_rate: 1.0,
+                temperature: 90.0,
+                active: 0.5,
+                period,
+                elapsed,
+            });
+            let bytes = encode(&save, Compression::None).unwrap();
+            assert!(matches!(decode(&bytes), Err(SaveError::Deserialize(_))), "{period} s, {elapsed} s");
+        }
+    }
+
+    #[test]
+    fn test_rejects_blueprint_mass_without_element() {
+        for (element, delivered) in [(None, 20.0), (Some(8), f32::NAN), (Some(8), -1.0)] {
+            let mut save = test_save();
+            save.entities.blueprints[0].element = element;
+            save.entities.blueprints[0].delivered = delivered;
+            let bytes = encode(&save, Compression::None).unwrap();
+            assert!(matches!(decode(&bytes), Err(SaveError::Deserialize(_))), "{delivered} kg of {element:?}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_magic() {
+        let mut bytes = encode(&test_save(), Compression::None).unwrap();
+        bytes[0] = b'X';
+        assert!(matches!(decode(&bytes), Err(SaveError::BadMagic)));
+    }
+
+    #[test]
+    fn test_rejects_corrupt_payload() {
+        let mut bytes = encode(&test_save(), Compression::None).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(matches!(decode(&bytes), Err(SaveError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_rejects_truncated_file() {
+        let bytes = encode(&test_save(), Compression::None).unwrap();
+        assert!(matches!(decode(&bytes[..bytes.len() - 4]), Err(SaveError::Truncated)));
+        assert!(matches!(decode(&bytes[..HEADER_SIZE - 1]), Err(SaveError::Truncated)));
+    }
+
+    #[test]
+    fn test_rejects_inflated_lengths() {
+        let bytes = encode(&test_save(), Compression::None).unwrap();
+        let meta_len = u32::from_le_bytes(bytes[HEADER_SIZE..HEADER_SIZE + 4].try_into().unwrap()) as usize;
+        let count_at = HEADER_SIZE + 4 + meta_len;
+        // Layer, x, y and offset come before the length of the first chunk
+        let length_at = count_at + 4 + 17;
+        for (at, len) in [(HEADER_SIZE, 4), (count_at, 4), (length_at, 4)] {
+            let mut bytes = bytes.clone();
+            bytes[at..at + len].fill(0xff);
+            let result = ChunkReader::open(Cursor::new(bytes)).and_then(ChunkReader::read_all);
+            assert!(matches!(result, Err(SaveError::Truncated)), "length at {at}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let mut bytes = encode(&test_save(), Compression::None).unwrap();
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode(&bytes),
+            Err(SaveError::UnsupportedVersion { found, .. }) if found == FORMAT_VERSION + 1
+        ));
+    }
+}
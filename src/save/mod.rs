@@ -0,0 +1,344 @@
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
+use common::resources::MapSize;
+use serde::{Deserialize, Serialize};
+use simulation::temperature::HeatCell;
+use simulation::SimulationTick;
+
+use crate::actions::{Actions, InputAction};
+use crate::jobs::clear_jobs;
+use crate::states::generation::GenerationState;
+use crate::resources::ElementConfigs;
+use crate::world::features::{geyser_name, Geyser, WorldBiome};
+use crate::world::layer::{Layer, LayerType};
+use crate::world::tile::{TileElement, TileMass};
+use crate::world::{drop_world, GenerationSeed, Grid, CHUNK_SIZE};
+use crate::GameState;
+
+pub use chunk::{ChunkSave, Compression};
+pub use entities::{EntitiesSave, EntitySnapshot};
+pub use format::SaveError;
+
+pub mod chunk;
+pub mod entities;
+pub mod format;
+
+/// This plugin persists the world to disk.
+/// The world is saved on request, every time the autosave timer finishes and when leaving `GameState::Playing`.
+/// A saved world is loaded by regenerating the layers with its size and seed and overwriting them with the saved tiles.
+/// Colonists, items, ladders, blueprints and orders are spawned again on top of them.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSettings>()
+            .add_event::<SaveWorld>()
+            .add_event::<LoadWorld>()
+            .add_systems(
+                Update,
+                (quicksave, autosave, save_world)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, load_world.run_if(in_state(GameState::Menu)))
+            .add_systems(OnExit(GameState::Playing), save_world_on_exit.before(drop_world).before(clear_jobs))
+            .add_systems(
+                OnEnter(GenerationState::PlacingFeatures),
+                (apply_pending_load, entities::restore_entities)
+                    .chain()
+                    .run_if(resource_exists::<PendingLoad>),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct SaveSettings {
+    pub path: PathBuf,
+    pub compression: Compression,
+    pub autosave: Timer,
+}
+
+impl Default for SaveSettings {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("saves/world.sav"),
+            compression: Compression::default(),
+            autosave: Timer::from_seconds(300.0, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Request to write the current world to [`SaveSettings::path`].
+#[derive(Event, Default)]
+pub struct SaveWorld;
+
+/// Request to load the world stored at [`SaveSettings::path`].
+#[derive(Event, Default)]
+pub struct LoadWorld;
+
+/// A loaded save waiting for the world generation to build its layers.
+#[derive(Resource)]
+pub struct PendingLoad(pub WorldSave);
+
+#[derive(Clone, Debug)]
+pub struct WorldSave {
+    pub map_size: (u32, u32),
+    pub seed: u32,
+    pub biome: Option<u32>,
+    pub tick: SimulationTick,
+    pub chunk_size: (u32, u32),
+    pub layers: Vec<LayerSave>,
+    pub geysers: Vec<GeyserSave>,
+    pub entities: EntitiesSave,
+}
+
+#[derive(Clone, Debug)]
+pub struct LayerSave {
+    pub layer_type: LayerType,
+    pub chunks: Vec<ChunkSave>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GeyserSave {
+    pub position: (u32, u32),
+    pub element: u32,
+    pub emission_rate: f32,
+    pub temperature: f32,
+    pub active: f32,
+    pub period: f32,
+    pub elapsed: f32,
+}
+
+impl GeyserSave {
+    fn capture(geyser: &Geyser, position: &TilePos) -> Self {
+        Self {
+            position: (position.x, position.y),
+            element: geyser.element,
+            emission_rate: geyser.emission_rate,
+            temperature: geyser.temperature,
+            active: geyser.active,
+            period: geyser.cycle.duration().as_secs_f32(),
+            elapsed: geyser.cycle.elapsed_secs(),
+        }
+    }
+
+    /// Fails on cycle times the timer can't hold, which would panic in [`Self::restore`].
+    fn validate(&self) -> Result<(), SaveError> {
+        if !(self.period.is_finite() && self.period > 0.0 && self.elapsed.is_finite() && self.elapsed >= 0.0) {
+            return Err(SaveError::Deserialize(format!(
+                "geyser at {:?} has an invalid cycle of {} s, {} s elapsed",
+                self.position, self.period, self.elapsed
+            )));
+        }
+        Ok(())
+    }
+
+    fn restore(&self) -> (Geyser, TilePos) {
+        let mut cycle = Timer::from_seconds(self.period, TimerMode::Repeating);
+        cycle.set_elapsed(std::time::Duration::from_secs_f32(self.elapsed));
+        let geyser = Geyser {
+            element: self.element,
+            emission_rate: self.emission_rate,
+            temperature: self.temperature,
+            active: self.active,
+            cycle,
+        };
+        (geyser, TilePos { x: self.position.0, y: self.position.1 })
+    }
+}
+
+/// Everything needed to capture the current world into a [`WorldSave`].
+#[derive(SystemParam)]
+pub struct WorldSnapshot<'w, 's> {
+    size: Res<'w, MapSize>,
+    seed: Res<'w, GenerationSeed>,
+    biome: Option<Res<'w, WorldBiome>>,
+    tick: Res<'w, SimulationTick>,
+    layer_query: Query<'w, 's, (&'static Layer, &'static TileStorage)>,
+    tile_query: Query<'w, 's, (&'static TileElement, &'static TileMass, &'static HeatCell)>,
+    geyser_query: Query<'w, 's, (&'static Geyser, &'static TilePos)>,
+    entities: EntitySnapshot<'w, 's>,
+}
+
+impl WorldSnapshot<'_, '_> {
+    /// Fails on element ids too large for the chunks to store.
+    pub fn capture(&self) -> Result<WorldSave, SaveError> {
+        let size = self.size.0;
+        let layers = self
+            .layer_query
+            .iter()
+            .map(|(layer, storage)| {
+                let mut chunks = ChunkSave::layout(size, CHUNK_SIZE);
+                for chunk in chunks.iter_mut() {
+                    let tiles: Vec<_> = chunk.tile_positions(CHUNK_SIZE).collect();
+                    for (index, position) in tiles {
+                        let Some((element, mass, heat)) = storage
+                            .get(&position)
+                            .and_then(|entity| self.tile_query.get(entity).ok())
+                        else {
+                            continue;
+                        };
+                        chunk.elements[index] = u16::try_from(element.0).map_err(|_| {
+                            SaveError::Serialize(format!("element {} at {position:?} is too large to save", element.0))
+                        })?;
+                        chunk.mass[index] = mass.0;
+                        chunk.temperature[index] = heat.temperature.get_temperature();
+                        chunk.conductivity[index] = heat.conductivity.value;
+                    }
+                }
+                Ok(LayerSave { layer_type: layer.layer_type, chunks })
+            })
+            .collect::<Result<_, SaveError>>()?;
+
+        Ok(WorldSave {
+            map_size: (size.x, size.y),
+            seed: self.seed.get(),
+            biome: self.biome.as_ref().map(|biome| biome.0),
+            tick: *self.tick,
+            chunk_size: (CHUNK_SIZE.x, CHUNK_SIZE.y),
+            layers,
+            geysers: self
+                .geyser_query
+                .iter()
+                .map(|(geyser, position)| GeyserSave::capture(geyser, position))
+                .collect(),
+            entities: self.entities.capture(),
+        })
+    }
+}
+
+pub fn write_save(path: &Path, save: &WorldSave, compression: Compression) -> Result<(), SaveError> {
+    let bytes = format::encode(save, compression)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+pub fn read_save(path: &Path) -> Result<WorldSave, SaveError> {
+    format::decode(&std::fs::read(path)?)
+}
+
+fn quicksave(actions: Res<Actions>, mut save_events: EventWriter<SaveWorld>) {
+    if actions.just_pressed(InputAction::Quicksave) {
+        save_events.send_default();
+    }
+}
+
+fn autosave(time: Res<Time>, mut settings: ResMut<SaveSettings>, mut save_events: EventWriter<SaveWorld>) {
+    settings.autosave.tick(time.delta());
+    if settings.autosave.just_finished() {
+        save_events.send_default();
+    }
+}
+
+fn save_world(
+    mut save_events: EventReader<SaveWorld>,
+    settings: Res<SaveSettings>,
+    generation_state: Res<State<GenerationState>>,
+    snapshot: WorldSnapshot,
+) {
+    if save_events.is_empty() {
+        return;
+    }
+    save_events.clear();
+
+    if !generation_state.is_done() {
+        warn!("Cannot save while the world is generating");
+        return;
+    }
+    match snapshot.capture().and_then(|save| write_save(&settings.path, &save, settings.compression)) {
+        Ok(()) => info!("Saved world to {:?}", settings.path),
+        Err(error) => error!("Failed to save world: {error}"),
+    }
+}
+
+fn save_world_on_exit(
+    settings: Res<SaveSettings>,
+    generation_state: Res<State<GenerationState>>,
+    snapshot: WorldSnapshot,
+) {
+    if !generation_state.is_done() {
+        return;
+    }
+    if let Err(error) = snapshot.capture().and_then(|save| write_save(&settings.path, &save, settings.compression)) {
+        error!("Failed to save world: {error}");
+    }
+}
+
+fn load_world(
+    mut commands: Commands,
+    mut load_events: EventReader<LoadWorld>,
+    settings: Res<SaveSettings>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_generation_state: ResMut<NextState<GenerationState>>,
+) {
+    if load_events.is_empty() {
+        return;
+    }
+    load_events.clear();
+
+    let save = match read_save(&settings.path) {
+        Ok(save) => save,
+        Err(error) => {
+            warn!("Failed to load world from {:?}: {error}", settings.path);
+            return;
+        }
+    };
+
+    // The layers are regenerated with the saved size and seed, then overwritten in `apply_pending_load`
+    commands.insert_resource(MapSize(UVec2::new(save.map_size.0, save.map_size.1)));
+    commands.insert_resource(GenerationSeed::new(save.seed));
+    commands.insert_resource(PendingLoad(save));
+    next_state.set(GameState::Playing);
+    next_generation_state.set(GenerationState::Initializing);
+}
+
+fn apply_pending_load(
+    mut commands: Commands,
+    pending: Res<PendingLoad>,
+    element_configs: Res<ElementConfigs>,
+    mut tick: ResMut<SimulationTick>,
+    grid_query: Query<Entity, With<Grid>>,
+    layer_query: Query<(&Layer, &TileStorage)>,
+    mut tile_query: Query<(&mut TileElement, &mut TileMass, &mut HeatCell)>,
+) {
+    let save = &pending.0;
+    let chunk_size = UVec2::new(save.chunk_size.0, save.chunk_size.1);
+
+    for layer_save in save.layers.iter() {
+        let Some((_, storage)) = layer_query.iter().find(|(layer, _)| layer.layer_type == layer_save.layer_type) else {
+            warn!("Saved layer {:?} does not exist in the world", layer_save.layer_type);
+            continue;
+        };
+        for chunk in layer_save.chunks.iter() {
+            for (index, position) in chunk.tile_positions(chunk_size) {
+                let Some(tile_entity) = storage.checked_get(&position) else {
+                    continue;
+                };
+                if let Ok((mut element, mut mass, mut heat)) = tile_query.get_mut(tile_entity) {
+                    element.0 = chunk.elements[index] as u32;
+                    mass.0 = chunk.mass[index];
+                    heat.temperature.set_temperature(chunk.temperature[index]);
+                    heat.conductivity.value = chunk.conductivity[index];
+                }
+            }
+        }
+    }
+
+    let grid_entity = grid_query.single();
+    commands.entity(grid_entity).with_children(|parent| {
+        for geyser in save.geysers.iter() {
+            parent.spawn((geyser_name(&element_configs, geyser.element), geyser.restore()));
+        }
+    });
+
+    if let Some(biome) = save.biome {
+        commands.insert_resource(WorldBiome(biome));
+    }
+    *tick = save.tick;
+}
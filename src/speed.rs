@@ -0,0 +1,84 @@
+//! Pauses, speeds up and slows down the simulation by changing the interval of [`SimulationRate`].
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use simulation::SimulationRate;
+
+use crate::actions::{Actions, InputAction};
+use crate::GameState;
+
+/// Speeds the simulation can run at, relative to its default rate.
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+const NORMAL_SPEED: usize = 2;
+
+pub struct SimulationSpeedPlugin;
+
+impl Plugin for SimulationSpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationSpeed>()
+            .add_systems(Update, change_speed.run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), reset_speed);
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct SimulationSpeed {
+    /// Index into [`SPEEDS`].
+    speed: usize,
+    pub paused: bool,
+    /// Interval of the simulation at normal speed.
+    step: Duration,
+}
+
+impl FromWorld for SimulationSpeed {
+    fn from_world(world: &mut World) -> Self {
+        let step = world.get_resource_or_init::<SimulationRate>().rate.duration();
+        Self { speed: NORMAL_SPEED, paused: false, step }
+    }
+}
+
+impl SimulationSpeed {
+    pub fn multiplier(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    /// Simulated time of one tick, whatever the speed.
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    fn apply(&self, rate: &mut SimulationRate) {
+        rate.rate.set_duration(self.step.div_f32(self.multiplier()));
+        if self.paused {
+            rate.rate.pause();
+        } else {
+            rate.rate.unpause();
+        }
+    }
+}
+
+fn change_speed(actions: Res<Actions>, mut speed: ResMut<SimulationSpeed>, mut rate: ResMut<SimulationRate>) {
+    let previous = (speed.speed, speed.paused);
+    if actions.just_pressed(InputAction::Pause) {
+        speed.paused = !speed.paused;
+    }
+    if actions.just_pressed(InputAction::SpeedUp) {
+        speed.speed = (speed.speed + 1).min(SPEEDS.len() - 1);
+    }
+    if actions.just_pressed(InputAction::SlowDown) {
+        speed.speed = speed.speed.saturating_sub(1);
+    }
+
+    if (speed.speed, speed.paused) != previous {
+        speed.apply(&mut rate);
+        info!("Simulation {} at {}x", if speed.paused { "paused" } else { "running" }, speed.multiplier());
+    }
+}
+
+fn reset_speed(mut speed: ResMut<SimulationSpeed>, mut rate: ResMut<SimulationRate>) {
+    speed.speed = NORMAL_SPEED;
+    speed.paused = false;
+    speed.apply(&mut rate);
+}
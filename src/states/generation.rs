@@ -7,12 +7,14 @@ pub enum GenerationState {
     Idle,
     Initializing,
     Generating,
+    /// Ore veins, resource pockets and geysers are placed on top of the generated layers.
+    PlacingFeatures,
     Done,
 }
 
 impl GenerationState {
     pub fn is_generating(&self) -> bool {
-        matches!(self, Self::Generating | Self::Initializing)
+        matches!(self, Self::Generating | Self::Initializing | Self::PlacingFeatures)
     }
 
     pub fn is_done(&self) -> bool {
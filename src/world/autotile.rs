@@ -0,0 +1,90 @@
+//! Picks edge sprites for the Solid and Liquid layers from the neighbours of every tile.
+//!
+//! Every element has a row of [`AUTOTILE_VARIANTS`] sprites in `textures/tiles.png`, one for
+//! each 4-neighbour mask, where a bit is set when the neighbour on that side is occupied.
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_ecs_tilemap::helpers::square_grid::neighbors::Neighbors;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::resources::{elements, ElementConfigs};
+
+use super::dirty::DirtyTiles;
+use super::layer::{Layer, LayerType};
+use super::tile::TileElement;
+
+/// Sprites per element in the atlas.
+pub const AUTOTILE_VARIANTS: u32 = 16;
+
+pub const NORTH: u32 = 1;
+pub const EAST: u32 = 2;
+pub const SOUTH: u32 = 4;
+pub const WEST: u32 = 8;
+
+/// A tile surrounded on every side, drawn as a plain square. Used by the layers that aren't auto-tiled.
+pub const INTERIOR: u32 = NORTH | EAST | SOUTH | WEST;
+
+pub const AUTOTILED_LAYERS: [LayerType; 2] = [LayerType::Solid, LayerType::Liquid];
+
+/// Index in the atlas of an element's sprite for a neighbour mask.
+pub fn atlas_index(texture_index: u32, mask: u32) -> u32 {
+    texture_index * AUTOTILE_VARIANTS + mask
+}
+
+fn neighbour_mask(
+    tile_pos: &TilePos,
+    storage: &TileStorage,
+    size: &TilemapSize,
+    tile_query: &Query<(&TileElement, &mut TileTextureIndex)>,
+) -> u32 {
+    let neighbours = Neighbors::get_square_neighboring_positions(tile_pos, size, false);
+    let is_occupied = |position: Option<TilePos>| {
+        position
+            .and_then(|position| storage.get(&position))
+            .and_then(|entity| tile_query.get(entity).ok())
+            .is_some_and(|(element, _)| element.0 != elements::VACUUM)
+    };
+
+    [
+        (neighbours.north, NORTH),
+        (neighbours.east, EAST),
+        (neighbours.south, SOUTH),
+        (neighbours.west, WEST),
+    ]
+    .into_iter()
+    .filter(|(position, _)| is_occupied(*position))
+    .fold(0, |mask, (_, bit)| mask | bit)
+}
+
+/// Updates the sprites of the tiles that changed this frame, along with their neighbours.
+pub fn update_autotiles(
+    element_configs: Res<ElementConfigs>,
+    dirty_tiles: Res<DirtyTiles>,
+    layer_query: Query<(&Layer, &TileStorage, &TilemapSize)>,
+    mut tile_query: Query<(&TileElement, &mut TileTextureIndex)>,
+) {
+    for (layer, storage, size) in layer_query.iter() {
+        if !AUTOTILED_LAYERS.contains(&layer.layer_type) {
+            continue;
+        }
+        let Some(dirty_layer) = dirty_tiles.layer(layer.layer_type) else {
+            continue;
+        };
+
+        let mut positions = HashSet::new();
+        for tile_pos in dirty_layer.iter() {
+            positions.insert(tile_pos);
+            positions.extend(Neighbors::get_square_neighboring_positions(&tile_pos, size, false).iter().copied());
+        }
+        for tile_pos in positions {
+            let mask = neighbour_mask(&tile_pos, storage, size, &tile_query);
+            let Some(Ok((element, mut texture_index))) = storage.get(&tile_pos).map(|entity| tile_query.get_mut(entity)) else {
+                continue;
+            };
+            if let Some(config) = element_configs.get(element.0) {
+                texture_index.0 = atlas_index(config.texture_index, mask);
+            }
+        }
+    }
+}
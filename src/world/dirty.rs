@@ -0,0 +1,161 @@
+//! Tracks which tiles changed during the current frame, per layer.
+//!
+//! Every change to a tile's element, mass or heat cell is collected in [`PostUpdate`], whether
+//! it came from the simulation, an edit going through [`super::tiles::WorldTiles`] or a save
+//! being loaded. Systems reacting to changes read [`DirtyTiles`] after [`DirtyTilesSystems`]
+//! instead of scanning the grid. The tracker is cleared in [`Last`].
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_ecs_tilemap::prelude::*;
+use simulation::temperature::HeatCell;
+
+use super::layer::{Layer, LayerType};
+use super::tile::{TileElement, TileMass};
+
+pub struct DirtyTilesPlugin;
+
+impl Plugin for DirtyTilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DirtyTiles>()
+            .add_systems(PostUpdate, collect_dirty_tiles.in_set(DirtyTilesSystems))
+            .add_systems(Last, clear_dirty_tiles);
+    }
+}
+
+/// Fills [`DirtyTiles`]; systems reading it run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DirtyTilesSystems;
+
+/// Dirty tiles of one layer, as a bitset over the map.
+#[derive(Debug, Default)]
+pub struct DirtyLayer {
+    size: UVec2,
+    bits: Vec<u64>,
+    count: usize,
+}
+
+impl DirtyLayer {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            bits: vec![0; ((size.x * size.y) as usize).div_ceil(64)],
+            ..default()
+        }
+    }
+
+    fn index(&self, position: &TilePos) -> Option<usize> {
+        (position.x < self.size.x && position.y < self.size.y).then(|| (position.y * self.size.x + position.x) as usize)
+    }
+
+    pub fn mark(&mut self, position: &TilePos) {
+        let Some(index) = self.index(position) else {
+            return;
+        };
+        let bit = 1 << (index % 64);
+        if self.bits[index / 64] & bit == 0 {
+            self.bits[index / 64] |= bit;
+            self.count += 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The dirty tiles, in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = TilePos> + '_ {
+        let width = self.size.x;
+        self.bits.iter().enumerate().flat_map(move |(word_index, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let index = word_index as u32 * 64 + word.trailing_zeros();
+                word &= word - 1;
+                Some(TilePos { x: index % width, y: index / width })
+            })
+        })
+    }
+
+    pub fn clear(&mut self) {
+        if self.count > 0 {
+            self.bits.fill(0);
+            self.count = 0;
+        }
+    }
+}
+
+/// Tiles that changed this frame, per layer.
+#[derive(Resource, Debug, Default)]
+pub struct DirtyTiles {
+    layers: HashMap<LayerType, DirtyLayer>,
+}
+
+impl DirtyTiles {
+    pub fn mark(&mut self, layer: LayerType, size: UVec2, position: &TilePos) {
+        let dirty_layer = self.layers.entry(layer).or_insert_with(|| DirtyLayer::new(size));
+        if dirty_layer.size != size {
+            *dirty_layer = DirtyLayer::new(size);
+        }
+        dirty_layer.mark(position);
+    }
+
+    pub fn layer(&self, layer: LayerType) -> Option<&DirtyLayer> {
+        self.layers.get(&layer).filter(|dirty_layer| !dirty_layer.is_empty())
+    }
+
+    /// Every dirty tile along with its layer.
+    pub fn iter(&self) -> impl Iterator<Item = (LayerType, TilePos)> + '_ {
+        self.layers
+            .iter()
+            .flat_map(|(layer, dirty_layer)| dirty_layer.iter().map(move |position| (*layer, position)))
+    }
+
+    /// Positions dirty on any layer, each reported once.
+    pub fn positions(&self) -> HashSet<TilePos> {
+        self.iter().map(|(_, position)| position).collect()
+    }
+
+    pub fn clear(&mut self) {
+        for dirty_layer in self.layers.values_mut() {
+            dirty_layer.clear();
+        }
+    }
+}
+
+fn collect_dirty_tiles(
+    mut dirty_tiles: ResMut<DirtyTiles>,
+    layer_query: Query<(&Layer, &TilemapSize)>,
+    changed_query: Query<(&TilePos, &TilemapId), Or<(Changed<TileElement>, Changed<TileMass>, Changed<HeatCell>)>>,
+) {
+    for (position, tilemap_id) in changed_query.iter() {
+        if let Ok((layer, size)) = layer_query.get(tilemap_id.0) {
+            dirty_tiles.mark(layer.layer_type, UVec2::new(size.x, size.y), position);
+        }
+    }
+}
+
+fn clear_dirty_tiles(mut dirty_tiles: ResMut<DirtyTiles>) {
+    dirty_tiles.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_layer() {
+        let mut dirty_layer = DirtyLayer::new(UVec2::new(40, 3));
+        for position in [TilePos { x: 39, y: 2 }, TilePos { x: 1, y: 0 }, TilePos { x: 1, y: 0 }, TilePos { x: 40, y: 0 }] {
+            dirty_layer.mark(&position);
+        }
+
+        assert_eq!(dirty_layer.iter().collect::<Vec<_>>(), [TilePos { x: 1, y: 0 }, TilePos { x: 39, y: 2 }]);
+
+        dirty_layer.clear();
+        assert!(dirty_layer.is_empty());
+        assert_eq!(dirty_layer.iter().count(), 0);
+    }
+}
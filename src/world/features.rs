@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+use bevy::utils::tracing;
+use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
+use common::resources::MapSize;
+use rand::{rngs::StdRng, Rng};
+use simulation::temperature::HeatCell;
+use simulation::{tick_simulation, SimulationRate};
+
+use crate::save::PendingLoad;
+use crate::resources::{elements, BiomeConfigs, ElementConfigs, GeyserRule, OreVeinRule, ResourcePocketRule};
+use crate::speed::SimulationSpeed;
+use crate::states::generation::GenerationState;
+use crate::GameState;
+
+use super::layer::{Layer, LayerType};
+use super::tile::{TileElement, TileMass};
+use super::tiles::WorldTiles;
+use super::{Grid, SeededRng};
+
+/// Fills the world with the rock of its biome and places its points of interest in it: ore veins,
+/// resource pockets and geysers, then the start area the colonists are printed in.
+///
+/// Runs once the layers are built, using the rules of a biome picked by the seeded RNG.
+pub struct FeaturesPlugin;
+
+impl Plugin for FeaturesPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_type::<Geyser>()
+            .register_type::<WorldBiome>()
+            .add_systems(
+                OnEnter(GenerationState::PlacingFeatures),
+                // Loaded worlds get their features from the save instead
+                ((place_features, place_start_area).chain().run_if(not(resource_exists::<PendingLoad>)), next_generation_step)
+                    .chain(),
+            )
+            .add_systems(Update, emit_geysers.after(tick_simulation).run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// Size of the start area, in tiles.
+const START_AREA_SIZE: UVec2 = UVec2::new(12, 4);
+
+/// Temperature of the air in the start area, in °C.
+const START_AREA_TEMPERATURE: f32 = 22.0;
+
+/// The biome the current world was generated with.
+#[derive(Resource, Reflect, Clone, Copy, Debug)]
+pub struct WorldBiome(pub u32);
+
+/// Periodically emits an element into the tile it sits on.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Geyser {
+    pub element: u32,
+    /// Mass emitted per second while erupting, in kg.
+    pub emission_rate: f32,
+    pub temperature: f32,
+    /// How long the geyser erupts at the start of every cycle, in seconds.
+    pub active: f32,
+    pub cycle: Timer,
+}
+
+impl Geyser {
+    pub fn from_rule(rule: &GeyserRule) -> Self {
+        Self {
+            element: rule.element,
+            emission_rate: rule.emission_rate,
+            temperature: rule.temperature,
+            active: rule.active,
+            cycle: Timer::from_seconds(rule.period, TimerMode::Repeating),
+        }
+    }
+
+    pub fn is_erupting(&self) -> bool {
+        self.cycle.elapsed_secs() < self.active
+    }
+}
+
+fn find_layer<'a>(layer_query: &'a Query<(&Layer, &TileStorage)>, layer_type: LayerType) -> Option<&'a TileStorage> {
+    layer_query
+        .iter()
+        .find(|(layer, _)| layer.layer_type == layer_type)
+        .map(|(_, storage)| storage)
+}
+
+fn random_position(rng: &mut StdRng, size: UVec2) -> UVec2 {
+    UVec2::new(rng.random_range(0..size.x), rng.random_range(0..size.y))
+}
+
+/// Walks randomly from a random start, returning every tile visited.
+fn ore_vein_positions(rng: &mut StdRng, rule: &OreVeinRule, size: UVec2) -> Vec<TilePos> {
+    let mut position = random_position(rng, size).as_ivec2();
+    let steps = rng.random_range(rule.length.clone());
+
+    let mut positions = Vec::with_capacity(steps as usize);
+    for _ in 0..steps {
+        positions.push(TilePos { x: position.x as u32, y: position.y as u32 });
+        let direction = match rng.random_range(0..4) {
+            0 => IVec2::X,
+            1 => IVec2::NEG_X,
+            2 => IVec2::Y,
+            _ => IVec2::NEG_Y,
+        };
+        position = (position + direction).clamp(IVec2::ZERO, size.as_ivec2() - IVec2::ONE);
+    }
+    positions
+}
+
+/// Every tile within a random radius around a random center.
+fn pocket_positions(rng: &mut StdRng, rule: &ResourcePocketRule, size: UVec2) -> Vec<TilePos> {
+    let center = random_position(rng, size).as_ivec2();
+    let radius = rng.random_range(rule.radius.clone()) as i32;
+
+    let mut positions = vec![];
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            let position = center + IVec2::new(dx, dy);
+            if dx * dx + dy * dy > radius * radius
+                || position.cmplt(IVec2::ZERO).any()
+                || position.cmpge(size.as_ivec2()).any()
+            {
+                continue;
+            }
+            positions.push(TilePos { x: position.x as u32, y: position.y as u32 });
+        }
+    }
+    positions
+}
+
+fn set_tile(
+    storage: &TileStorage,
+    tile_query: &mut Query<(&mut TileElement, &mut TileMass, &mut HeatCell)>,
+    position: &TilePos,
+    element: u32,
+    mass: f32,
+    temperature: Option<f32>,
+) {
+    let Some(tile_entity) = storage.get(position) else {
+        return;
+    };
+    if let Ok((mut tile_element, mut tile_mass, mut heat_cell)) = tile_query.get_mut(tile_entity) {
+        tile_element.0 = element;
+        tile_mass.0 = mass;
+        if let Some(temperature) = temperature {
+            heat_cell.temperature.set_temperature(temperature);
+        }
+    }
+}
+
+#[tracing::instrument(name = "Placing world features", skip_all)]
+#[allow(clippy::too_many_arguments)]
+fn place_features(
+    mut commands: Commands,
+    mut rng: ResMut<SeededRng<StdRng>>,
+    biomes: Res<BiomeConfigs>,
+    element_configs: Res<ElementConfigs>,
+    size: Res<MapSize>,
+    grid_query: Query<Entity, With<Grid>>,
+    layer_query: Query<(&Layer, &TileStorage)>,
+    mut tile_query: Query<(&mut TileElement, &mut TileMass, &mut HeatCell)>,
+) {
+    let rng = &mut rng.0;
+    let size = size.0;
+
+    if biomes.biomes.is_empty() {
+        warn!("No biomes configured, skipping feature placement");
+        return;
+    }
+    let biome = &biomes.biomes[rng.random_range(0..biomes.biomes.len())];
+    info!("Placing features for biome {}", biome.name);
+    commands.insert_resource(WorldBiome(biome.id));
+
+    let Some(solid_storage) = find_layer(&layer_query, LayerType::Solid) else {
+        warn!("No solid layer found, skipping feature placement");
+        return;
+    };
+
+    // Everything is buried in the biome's rock, the features and the start area are carved out of it
+    let rock_mass = element_configs.get(biome.rock).map_or(0.0, |config| config.density);
+    for y in 0..size.y {
+        for x in 0..size.x {
+            set_tile(solid_storage, &mut tile_query, &TilePos { x, y }, biome.rock, rock_mass, None);
+        }
+    }
+
+    for rule in biome.ore_veins.iter() {
+        for _ in 0..rng.random_range(rule.count.clone()) {
+            for position in ore_vein_positions(rng, rule, size) {
+                set_tile(solid_storage, &mut tile_query, &position, rule.element, rule.mass, None);
+            }
+        }
+    }
+
+    for rule in biome.resource_pockets.iter() {
+        let state = element_configs.state_of(rule.element);
+        let Some(storage) = LayerType::for_element_state(state).and_then(|layer_type| find_layer(&layer_query, layer_type)) else {
+            warn!("Resource pocket element {} has no matching layer ({:?})", rule.element, state);
+            continue;
+        };
+        for _ in 0..rng.random_range(rule.count.clone()) {
+            for position in pocket_positions(rng, rule, size) {
+                // Carve the pocket out of the rock before filling it
+                set_tile(solid_storage, &mut tile_query, &position, elements::VACUUM, 0.0, None);
+                set_tile(storage, &mut tile_query, &position, rule.element, rule.mass, Some(rule.temperature));
+            }
+        }
+    }
+
+    let grid_entity = grid_query.single();
+    for rule in biome.geysers.iter() {
+        for _ in 0..rng.random_range(rule.count.clone()) {
+            let position = random_position(rng, size);
+            let position = TilePos { x: position.x, y: position.y };
+
+            set_tile(solid_storage, &mut tile_query, &position, elements::VACUUM, 0.0, None);
+            commands.entity(grid_entity).with_children(|parent| {
+                parent.spawn((
+                    geyser_name(&element_configs, rule.element),
+                    Geyser::from_rule(rule),
+                    position,
+                ));
+            });
+        }
+    }
+}
+
+/// Name of a geyser of `element`, the same for generated and loaded worlds.
+pub fn geyser_name(element_configs: &ElementConfigs, element: u32) -> Name {
+    let name = element_configs.get(element).map_or("Unknown", |element| element.name.as_str());
+    Name::new(format!("{name} Geyser"))
+}
+
+/// The open room in the middle of the map where colonists start, without its floor.
+///
+/// Only depends on the size of the map, so loaded worlds find it in the same place.
+pub fn start_area(size: UVec2) -> URect {
+    let area_size = START_AREA_SIZE.min(size);
+    let min = (size - area_size) / 2;
+    URect::from_corners(min, min + area_size)
+}
+
+/// Clears the start area, fills it with breathable air and lays a floor below it.
+#[tracing::instrument(name = "Placing start area", skip_all)]
+fn place_start_area(
+    size: Res<MapSize>,
+    layer_query: Query<(&Layer, &TileStorage)>,
+    mut tile_query: Query<(&mut TileElement, &mut TileMass, &mut HeatCell)>,
+    element_configs: Res<ElementConfigs>,
+) {
+    let area = start_area(size.0);
+    let density = |element| element_configs.get(element).map_or(0.0, |config| config.density);
+
+    for y in area.min.y..area.max.y {
+        for x in area.min.x..area.max.x {
+            let position = TilePos { x, y };
+            for (layer_type, element, mass, temperature) in [
+                (LayerType::Solid, elements::VACUUM, 0.0, None),
+                (LayerType::Liquid, elements::VACUUM, 0.0, None),
+                (LayerType::Gas, elements::OXYGEN, density(elements::OXYGEN), Some(START_AREA_TEMPERATURE)),
+            ] {
+                if let Some(storage) = find_layer(&layer_query, layer_type) {
+                    set_tile(storage, &mut tile_query, &position, element, mass, temperature);
+                }
+            }
+        }
+    }
+
+    // The bottom row of the map holds things up already
+    let (Some(floor), Some(solid_storage)) = (area.min.y.checked_sub(1), find_layer(&layer_query, LayerType::Solid)) else {
+        return;
+    };
+    for x in area.min.x..area.max.x {
+        let position = TilePos { x, y: floor };
+        set_tile(solid_storage, &mut tile_query, &position, elements::SANDSTONE, density(elements::SANDSTONE), None);
+    }
+}
+
+/// Runs the geysers for the simulation ticks of the frame, so they stop while it is paused.
+fn emit_geysers(
+    rate: Res<SimulationRate>,
+    speed: Option<Res<SimulationSpeed>>,
+    element_configs: Res<ElementConfigs>,
+    mut tiles: WorldTiles,
+    mut geyser_query: Query<(&mut Geyser, &TilePos)>,
+) {
+    let ticks = rate.rate.times_finished_this_tick();
+    if ticks == 0 {
+        return;
+    }
+    // Headless runs have no speed, their ticks last the interval of the simulation
+    let step = speed.map_or(rate.rate.duration(), |speed| speed.step()) * ticks;
+
+    for (mut geyser, position) in geyser_query.iter_mut() {
+        geyser.cycle.tick(step);
+        if !geyser.is_erupting() {
+            continue;
+        }
+
+        let Some(layer_type) = LayerType::for_element_state(element_configs.state_of(geyser.element)) else {
+            continue;
+        };
+        let Some(tile) = tiles.get(layer_type, position) else {
+            continue;
+        };
+        // Blocked by another element
+        if tile.element != geyser.element && tile.mass > 0.0 {
+            continue;
+        }
+
+        let emitted = geyser.emission_rate * step.as_secs_f32();
+        if emitted <= 0.0 {
+            continue;
+        }
+        let total = tile.mass + emitted;
+        let temperature = (tile.temperature * tile.mass + geyser.temperature * emitted) / total;
+        tiles.set(layer_type, position, geyser.element, total, Some(temperature));
+    }
+}
+
+fn next_generation_step(mut next_state: ResMut<NextState<GenerationState>>) {
+    next_state.set(GenerationState::Done);
+}
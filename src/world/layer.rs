@@ -1,7 +1,10 @@
 use bevy::{prelude::*, utils::tracing::{self, Instrument}};
 use bevy_ecs_tilemap::{map::{TilemapId, TilemapSize}, tiles::{TileBundle, TilePos, TileStorage}, TilemapBundle};
 use common::resources::MapSize;
+use crate::loading::TextureAssets;
 use crate::states::generation::GenerationState;
+use crate::resources::ElementState;
+use super::tile::{TileElement, TileMass};
 
 pub struct LayerPlugin;
 
@@ -9,20 +12,22 @@ impl Plugin for LayerPlugin {
     fn build(&self, app: &mut App) {
         app
             .insert_resource(MapSize(UVec2::new(3, 3)))
-            .add_systems(OnEnter(GenerationState::Generating), ((build_background_layer, build_solid_layer), next_generation_step).chain());
+            .add_systems(OnEnter(GenerationState::Generating), ((build_background_layer, build_gas_layer, build_liquid_layer, build_solid_layer), next_generation_step).chain());
     }
 }
 
-#[derive(Component, Reflect)]
+/// Distance between the layers along the z axis.
+pub const LAYER_Z_STEP: f32 = 1.0;
+
+#[derive(Component, Reflect, Clone, Debug)]
 pub struct Layer {
     pub id: u32,
-    pub tile_storage: TileStorage,
     pub layer_type: LayerType,
 }
 
 impl Default for Layer {
     fn default() -> Self {
-        Self { id: 0, tile_storage: TileStorage::default(), layer_type: LayerType::Empty }
+        Self { id: LayerType::Empty.id(), layer_type: LayerType::Empty }
     }
 }
 
@@ -49,6 +54,8 @@ pub struct LayerBuilder {
     layer_type: Option<LayerType>,
     size: Option<TilemapSize>,
     transform: Option<Transform>,
+    texture: Option<Handle<Image>>,
+    grid: Option<Entity>,
 }
 
 impl LayerBuilder {
@@ -76,24 +83,42 @@ impl LayerBuilder {
         self
     }
 
+    /// The tile atlas, `None` when running without assets.
+    pub fn with_texture(mut self, texture: Option<Handle<Image>>) -> Self {
+        self.texture = texture;
+        self
+    }
+
+    /// Adds the layer to a [`super::Grid`], as a child and in its list of layers.
+    pub fn with_grid(mut self, grid: Entity) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+
     pub fn build(self, commands: &mut Commands) -> Entity {
 
         info!("Building layer");
 
-        let layer_entity = commands.spawn(LayerBundle::default()).id();
+        let layer_type = self.layer_type.unwrap_or_default();
+        let layer = Layer { id: layer_type.id(), layer_type };
+        let layer_entity = commands.spawn(LayerBundle {
+            layer: layer.clone(),
+            ..default()
+        }).id();
         
         if let Some(name) = self.name {
             commands.entity(layer_entity).insert(Name::new(name));
         }
 
-        if let Some(transform) = self.transform {
-            commands.entity(layer_entity).insert(transform);
-        }
+        // Layers are stacked by id, whatever the transform they were given
+        let mut transform = self.transform.unwrap_or_default();
+        transform.translation.z += layer.id as f32 * LAYER_Z_STEP;
+        commands.entity(layer_entity).insert(transform);
 
         if let Some(size) = self.size {
             use bevy_ecs_tilemap::prelude::*;
             let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
-            let grid_size = TilemapGridSize { x: size.x as f32 * tile_size.x, y: size.y as f32 * tile_size.y };
+            let grid_size = TilemapGridSize { x: tile_size.x, y: tile_size.y };
 
             let mut tile_storage = TileStorage::empty(size.into());
             fill_layer(
@@ -103,11 +128,26 @@ impl LayerBuilder {
                 &mut tile_storage,
             );
 
-            commands.entity(layer_entity).insert(TilemapBundle {
-                grid_size,
-                tile_size,
-                storage: tile_storage,
-                ..Default::default()
+            commands.entity(layer_entity).insert((
+                TilemapBundle {
+                    grid_size,
+                    size,
+                    tile_size,
+                    storage: tile_storage,
+                    texture: self.texture.map(TilemapTexture::Single).unwrap_or_default(),
+                    transform,
+                    ..Default::default()
+                },
+                layer.clone(),
+            ));
+        }
+
+        if let Some(grid_entity) = self.grid {
+            commands.entity(grid_entity).add_child(layer_entity);
+            commands.queue(move |world: &mut World| {
+                if let Some(mut grid) = world.get_mut::<super::Grid>(grid_entity) {
+                    grid.add_layer(layer);
+                }
             });
         }
 
@@ -115,8 +155,13 @@ impl LayerBuilder {
     }
 }
 
-#[tracing::instrument(name = "Building solid layer", skip(commands, size, grid_query))]
-fn build_background_layer(mut commands: Commands, size: Res<MapSize>, mut grid_query: Query<Entity, With<super::Grid>>) {
+#[tracing::instrument(name = "Building solid layer", skip(commands, size, textures, grid_query))]
+fn build_background_layer(
+    mut commands: Commands,
+    size: Res<MapSize>,
+    textures: Option<Res<TextureAssets>>,
+    mut grid_query: Query<Entity, With<super::Grid>>,
+) {
 
     use tracing::info;
 
@@ -125,19 +170,68 @@ fn build_background_layer(mut commands: Commands, size: Res<MapSize>, mut grid_q
     let map_size = TilemapSize { x: size.0.x, y: size.0.y };
     let grid_entity = grid_query.single_mut();
 
-    let layer_entity = 
-        LayerBuilder::new()
-            .with_name("Background Layer")
-            .with_type(LayerType::Background)
-            .with_size(map_size)
-            .build(&mut commands);
+    LayerBuilder::new()
+        .with_name("Background Layer")
+        .with_type(LayerType::Background)
+        .with_size(map_size)
+        .with_texture(textures.map(|textures| textures.tile_atlas.clone()))
+        .with_grid(grid_entity)
+        .build(&mut commands);
+}
+
+#[tracing::instrument(name = "Building gas layer", skip(commands, size, textures, grid_query))]
+fn build_gas_layer(
+    mut commands: Commands,
+    size: Res<MapSize>,
+    textures: Option<Res<TextureAssets>>,
+    mut grid_query: Query<Entity, With<super::Grid>>,
+) {
+
+    use tracing::info;
+
+    info!("Building gas layer");
+
+    let grid_entity = grid_query.single_mut();
+
+    LayerBuilder::new()
+        .with_name("Gas Layer")
+        .with_type(LayerType::Gas)
+        .with_size(TilemapSize::from(size.into_inner().0))
+        .with_texture(textures.map(|textures| textures.tile_atlas.clone()))
+        .with_grid(grid_entity)
+        .build(&mut commands);
+}
+
+#[tracing::instrument(name = "Building liquid layer", skip(commands, size, textures, grid_query))]
+fn build_liquid_layer(
+    mut commands: Commands,
+    size: Res<MapSize>,
+    textures: Option<Res<TextureAssets>>,
+    mut grid_query: Query<Entity, With<super::Grid>>,
+) {
+
+    use tracing::info;
+
+    info!("Building liquid layer");
 
-    commands.entity(grid_entity)
-        .add_child(layer_entity);
+    let grid_entity = grid_query.single_mut();
+
+    LayerBuilder::new()
+        .with_name("Liquid Layer")
+        .with_type(LayerType::Liquid)
+        .with_size(TilemapSize::from(size.into_inner().0))
+        .with_texture(textures.map(|textures| textures.tile_atlas.clone()))
+        .with_grid(grid_entity)
+        .build(&mut commands);
 }
 
-#[tracing::instrument(name = "Building solid layer", skip(commands, size, grid_query))]
-fn build_solid_layer(mut commands: Commands, size: Res<MapSize>, mut grid_query: Query<Entity, With<super::Grid>>) {
+#[tracing::instrument(name = "Building solid layer", skip(commands, size, textures, grid_query))]
+fn build_solid_layer(
+    mut commands: Commands,
+    size: Res<MapSize>,
+    textures: Option<Res<TextureAssets>>,
+    mut grid_query: Query<Entity, With<super::Grid>>,
+) {
 
     use tracing::info;
 
@@ -145,15 +239,13 @@ fn build_solid_layer(mut commands: Commands, size: Res<MapSize>, mut grid_query:
 
     let grid_entity = grid_query.single_mut();
 
-    let layer_entity = 
-        LayerBuilder::new()
-            .with_name("Solid Layer")
-            .with_type(LayerType::Solid)
-            .with_size(TilemapSize::from(size.into_inner().0))
-            .build(&mut commands);
-
-    commands.entity(grid_entity)
-        .add_child(layer_entity);
+    LayerBuilder::new()
+        .with_name("Solid Layer")
+        .with_type(LayerType::Solid)
+        .with_size(TilemapSize::from(size.into_inner().0))
+        .with_texture(textures.map(|textures| textures.tile_atlas.clone()))
+        .with_grid(grid_entity)
+        .build(&mut commands);
 }
 
 #[tracing::instrument(name = "Filling layer", skip(commands, tile_storage))]
@@ -175,7 +267,7 @@ fn fill_layer(
                     tilemap_id,
                     ..Default::default()
                 })
-                .insert(HeatCell::default())
+                .insert((HeatCell::default(), TileElement::default(), TileMass::default()))
                 .id();
                 tile_storage.set(&tile_pos, tile_entity);
             }
@@ -185,10 +277,10 @@ fn fill_layer(
 }
 
 fn next_generation_step(mut commands: Commands, mut next_state: ResMut<NextState<GenerationState>>) {
-    next_state.set(GenerationState::Done);
+    next_state.set(GenerationState::PlacingFeatures);
 }
 
-#[derive(Component, Default, Reflect, Debug)]
+#[derive(Component, Default, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum LayerType {
     Background = -1,
     #[default]
@@ -202,3 +294,52 @@ pub enum LayerType {
 
 }
 
+impl LayerType {
+    /// Id of the layers of this type, which is also their order from the back. Every type has its own.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Background => 0,
+            Self::Empty => 1,
+            Self::Gas => 2,
+            Self::GasPipe => 3,
+            Self::Liquid => 4,
+            Self::LiquidPipe => 5,
+            Self::Solid => 6,
+            Self::NPC => 7,
+        }
+    }
+
+    /// The layer holding tiles of an element in the given state, if any.
+    pub fn for_element_state(state: ElementState) -> Option<Self> {
+        match state {
+            ElementState::Solid => Some(Self::Solid),
+            ElementState::Liquid => Some(Self::Liquid),
+            ElementState::Gas => Some(Self::Gas),
+            ElementState::Vacuum => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_ids_are_distinct() {
+        let types = [
+            LayerType::Background,
+            LayerType::Empty,
+            LayerType::Gas,
+            LayerType::GasPipe,
+            LayerType::Liquid,
+            LayerType::LiquidPipe,
+            LayerType::Solid,
+            LayerType::NPC,
+        ];
+        let mut ids: Vec<_> = types.iter().map(LayerType::id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), types.len());
+        assert!(LayerType::Background.id() < LayerType::Gas.id());
+    }
+}
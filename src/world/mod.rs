@@ -1,16 +1,22 @@
 pub mod tile;
 pub mod layer;
+pub mod features;
+pub mod autotile;
+pub mod visibility;
+pub mod tiles;
+pub mod dirty;
 
 use std::{rc::Rc, sync::Arc};
 
 use bevy::{prelude::*, utils::HashSet};
-use bevy_ecs_tilemap::prelude::*;
 use layer::Layer;
-use tile::FallTileBundle;
-use rand::{rngs::ThreadRng, Rng};
+use tile::{TileElement, TileMass};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::{loading::TextureAssets, GameState};
+use crate::resources::{BiomeConfigs, ElementConfigs};
 use crate::states::generation::GenerationState;
+use simulation::SimulationTick;
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
@@ -18,10 +24,23 @@ impl Plugin for WorldPlugin {
         app
             .init_resource::<SolidTiles>()
             .init_resource::<GenerationSeed>()
-            .init_non_send_resource::<SeededRng<ThreadRng>>()
-            .add_plugins(TilemapPlugin)
+            .init_resource::<SeededRng<StdRng>>()
+            .init_resource::<ElementConfigs>()
+            .init_resource::<BiomeConfigs>()
+            .register_type::<TileElement>()
+            .register_type::<TileMass>()
+            .add_event::<tiles::TileChanged>()
             .add_plugins(layer::LayerPlugin)
+            .add_plugins(features::FeaturesPlugin)
+            .add_plugins(dirty::DirtyTilesPlugin)
             .add_systems(OnEnter(GenerationState::Initializing), build_world)
+            .add_systems(Update, tile::update_tile_textures.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                PostUpdate,
+                autotile::update_autotiles
+                    .after(dirty::DirtyTilesSystems)
+                    .run_if(in_state(GameState::Playing)),
+            )
             .add_systems(OnExit(GameState::Playing), drop_world);
     }
 }
@@ -48,9 +67,33 @@ impl Default for GenerationSeed {
     }
 }
 
-impl Default for SeededRng<ThreadRng> {
-    fn default() -> Self {
-        Self(rand::thread_rng())
+impl GenerationSeed {
+    pub fn new(seed: u32) -> Self {
+        Self(seed)
+    }
+
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+impl SeededRng<StdRng> {
+    pub fn from_seed(seed: u32) -> Self {
+        Self(StdRng::seed_from_u64(seed as u64))
+    }
+}
+
+impl<R: Rng> SeededRng<R> {
+    /// The generator, for randomness that should follow the seed of the world.
+    pub fn rng(&mut self) -> &mut R {
+        &mut self.0
+    }
+}
+
+impl FromWorld for SeededRng<StdRng> {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.get_resource_or_init::<GenerationSeed>().get();
+        Self::from_seed(seed)
     }
 }
 
@@ -64,6 +107,7 @@ pub struct Grid {
     /// The first layer is the base layer, and the layers are rendered in order of their id.
     /// 
     /// The layer id corresponds to the layer index in the tilemap and the z-index in the 3D scene. 
+    /// Their tiles are read and written through [`tiles::WorldTiles`].
     layers: Vec<Layer>,
 }
 
@@ -83,6 +127,12 @@ impl Grid {
     pub fn get_layers_mut(&mut self) -> &mut [Layer] {
         &mut self.layers
     }
+
+    /// Registers a layer, keeping the layers sorted by id.
+    pub fn add_layer(&mut self, layer: Layer) {
+        let index = self.layers.partition_point(|other| other.id <= layer.id);
+        self.layers.insert(index, layer);
+    }
 }
 
 impl Default for Grid {
@@ -91,15 +141,25 @@ impl Default for Grid {
     }
 }
 
-const CHUNK_SIZE: UVec2 = UVec2 { x: 32, y: 32 };
+pub const CHUNK_SIZE: UVec2 = UVec2 { x: 32, y: 32 };
 
-fn drop_world(mut commands: Commands, tilemap_query: Query<Entity, With<TileStorage>>) {
-    for tilemap_entity in tilemap_query.iter() {
-        commands.entity(tilemap_entity).despawn_recursive();
+pub(crate) fn drop_world(mut commands: Commands, grid_query: Query<Entity, With<Grid>>) {
+    // Layers and world features such as geysers are children of the grid
+    for grid_entity in grid_query.iter() {
+        commands.entity(grid_entity).despawn_recursive();
     }
 }
 
-fn build_world(mut commands: Commands, mut next_state: ResMut<NextState<GenerationState>>) {
+fn build_world(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GenerationState>>,
+    seed: Res<GenerationSeed>,
+    mut rng: ResMut<SeededRng<StdRng>>,
+) {
+    // Every generated world starts from the same RNG state for a given seed
+    *rng = SeededRng::from_seed(seed.get());
+    commands.insert_resource(SimulationTick::default());
+
     commands.spawn_empty()
         .insert(Name::new("World"))
         .insert(Grid::default())
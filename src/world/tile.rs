@@ -1,17 +1,72 @@
 use bevy::reflect::Reflect;
 
 use bevy::prelude::*;
+use bevy_ecs_tilemap::map::TilemapId;
+use bevy_ecs_tilemap::tiles::{TileTextureIndex, TileVisible};
 
+use crate::resources::{elements, ElementConfigs};
+
+use super::autotile::{atlas_index, INTERIOR};
+use super::layer::{Layer, LayerType};
+
+/// Texture of the background tiles in `textures/tiles.png`.
+pub const BACKGROUND_TEXTURE_INDEX: u32 = 6;
 
 #[derive(Default, Component, Reflect, Clone, Copy, Debug)]
 pub struct TileTemperature(pub f32);
 
+/// Id of the element occupying the tile, see [`crate::resources::ElementConfigs`].
+#[derive(Default, Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileElement(pub u32);
+
 //#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Default, Component, Reflect, Clone, Copy, Debug)]
 pub struct TileMass(pub f32);
 
 #[derive(Bundle, Default, Reflect, Clone, Copy, Debug)]
 pub struct FallTileBundle {
+    pub tile_element: TileElement,
     pub tile_mass: TileMass,
     pub tile_temperature: TileTemperature,
-}
\ No newline at end of file
+}
+
+/// Keeps the atlas index and visibility of every tile in sync with its element.
+///
+/// Auto-tiled layers get their edge sprites from [`super::autotile::update_autotiles`] later in the frame.
+pub fn update_tile_textures(
+    element_configs: Res<ElementConfigs>,
+    layer_query: Query<&Layer>,
+    mut tile_query: Query<(&TileElement, &TilemapId, &mut TileTextureIndex, &mut TileVisible), Changed<TileElement>>,
+) {
+    for (element, tilemap_id, mut texture_index, mut visible) in tile_query.iter_mut() {
+        let is_background = layer_query
+            .get(tilemap_id.0)
+            .is_ok_and(|layer| layer.layer_type == LayerType::Background);
+        if is_background {
+            texture_index.0 = atlas_index(BACKGROUND_TEXTURE_INDEX, INTERIOR);
+            continue;
+        }
+
+        // Vacuum shows the layers below
+        visible.0 = element.0 != elements::VACUUM;
+        if let Some(config) = element_configs.get(element.0) {
+            texture_index.0 = atlas_index(config.texture_index, INTERIOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_element_has_its_own_texture() {
+        let element_configs = ElementConfigs::default();
+        let mut textures = vec![BACKGROUND_TEXTURE_INDEX];
+        textures.extend(element_configs.elements.iter().filter(|config| config.id != elements::VACUUM).map(|config| config.texture_index));
+        let count = textures.len();
+        textures.sort();
+        textures.dedup();
+        assert_eq!(textures.len(), count);
+    }
+}
@@ -0,0 +1,274 @@
+//! Reading and writing the tiles of the world by layer and position.
+//!
+//! Game code should go through [`WorldTiles`] rather than querying the tile components, so that
+//! every change is reported with a [`TileChanged`] event.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use simulation::temperature::{exchange_heat, HeatCell};
+
+use crate::resources::{elements, ElementConfigs};
+
+use super::layer::{Layer, LayerType};
+use super::tile::{TileElement, TileMass};
+use super::CHUNK_SIZE;
+
+/// What changed about a tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TileChangeKind {
+    Element,
+    Mass,
+    Temperature,
+}
+
+/// Sent whenever a tile is modified through [`WorldTiles`].
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct TileChanged {
+    pub layer: LayerType,
+    pub position: TilePos,
+    pub kind: TileChangeKind,
+}
+
+/// Contents of a tile on one layer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileData {
+    pub element: u32,
+    /// In kg.
+    pub mass: f32,
+    /// In °C.
+    pub temperature: f32,
+    pub conductivity: f32,
+}
+
+/// The chunk a tile belongs to, as laid out in save files.
+pub fn chunk_of(position: &TilePos) -> UVec2 {
+    UVec2::new(position.x, position.y) / CHUNK_SIZE
+}
+
+/// Positions of the tiles of a chunk that lie within the map.
+pub fn chunk_tiles(chunk: UVec2, map_size: UVec2) -> impl Iterator<Item = TilePos> {
+    let origin = chunk * CHUNK_SIZE;
+    let end = (origin + CHUNK_SIZE).min(map_size);
+    (origin.y..end.y).flat_map(move |y| (origin.x..end.x).map(move |x| TilePos { x, y }))
+}
+
+#[derive(SystemParam)]
+pub struct WorldTiles<'w, 's> {
+    layer_query: Query<
+        'w,
+        's,
+        (
+            &'static Layer,
+            &'static TileStorage,
+            &'static TilemapSize,
+            &'static TilemapGridSize,
+            &'static TilemapType,
+            &'static GlobalTransform,
+        ),
+    >,
+    tile_query: Query<'w, 's, (&'static mut TileElement, &'static mut TileMass, &'static mut HeatCell)>,
+    events: EventWriter<'w, TileChanged>,
+}
+
+impl WorldTiles<'_, '_> {
+    fn storage(&self, layer: LayerType) -> Option<&TileStorage> {
+        self.layer_query
+            .iter()
+            .find(|(candidate, ..)| candidate.layer_type == layer)
+            .map(|(_, storage, ..)| storage)
+    }
+
+    fn entity(&self, layer: LayerType, position: &TilePos) -> Option<Entity> {
+        self.storage(layer)?.checked_get(position)
+    }
+
+    /// Size of the map in tiles, taken from the layers.
+    pub fn map_size(&self) -> Option<UVec2> {
+        self.layer_query.iter().next().map(|(_, _, size, ..)| UVec2::new(size.x, size.y))
+    }
+
+    /// The tile of a layer under a point in world space.
+    pub fn tile_at(&self, layer: LayerType, world_position: Vec2) -> Option<TilePos> {
+        let (_, _, size, grid_size, map_type, transform) =
+            self.layer_query.iter().find(|(candidate, ..)| candidate.layer_type == layer)?;
+        let local_position = transform
+            .affine()
+            .inverse()
+            .transform_point3(world_position.extend(0.0))
+            .truncate();
+        TilePos::from_world_pos(&local_position, size, grid_size, map_type)
+    }
+
+    /// Center of a tile of a layer in world space.
+    pub fn world_position(&self, layer: LayerType, position: &TilePos) -> Option<Vec2> {
+        let (_, _, _, grid_size, map_type, transform) =
+            self.layer_query.iter().find(|(candidate, ..)| candidate.layer_type == layer)?;
+        let local_position = position.center_in_world(grid_size, map_type);
+        Some(transform.transform_point(local_position.extend(0.0)).truncate())
+    }
+
+    pub fn get(&self, layer: LayerType, position: &TilePos) -> Option<TileData> {
+        let (element, mass, heat_cell) = self.tile_query.get(self.entity(layer, position)?).ok()?;
+        Some(TileData {
+            element: element.0,
+            mass: mass.0,
+            temperature: heat_cell.temperature.get_temperature(),
+            conductivity: heat_cell.conductivity.value,
+        })
+    }
+
+    pub fn get_at(&self, layer: LayerType, world_position: Vec2) -> Option<TileData> {
+        self.get(layer, &self.tile_at(layer, world_position)?)
+    }
+
+    pub fn element(&self, layer: LayerType, position: &TilePos) -> Option<u32> {
+        self.get(layer, position).map(|tile| tile.element)
+    }
+
+    pub fn mass(&self, layer: LayerType, position: &TilePos) -> Option<f32> {
+        self.get(layer, position).map(|tile| tile.mass)
+    }
+
+    pub fn temperature(&self, layer: LayerType, position: &TilePos) -> Option<f32> {
+        self.get(layer, position).map(|tile| tile.temperature)
+    }
+
+    /// Whether something stands in the Solid layer at a tile, blocking agents and holding them up.
+    pub fn is_solid(&self, position: &TilePos) -> bool {
+        self.element(LayerType::Solid, position)
+            .is_some_and(|element| element != elements::VACUUM)
+    }
+
+    /// Returns `false` if there is no such tile. Unchanged values don't send events.
+    pub fn set_element(&mut self, layer: LayerType, position: &TilePos, element: u32) -> bool {
+        let Some(entity) = self.entity(layer, position) else {
+            return false;
+        };
+        let Ok((mut tile_element, ..)) = self.tile_query.get_mut(entity) else {
+            return false;
+        };
+        if tile_element.0 != element {
+            tile_element.0 = element;
+            self.events.send(TileChanged { layer, position: *position, kind: TileChangeKind::Element });
+        }
+        true
+    }
+
+    /// Returns `false` if there is no such tile. Unchanged values don't send events.
+    pub fn set_mass(&mut self, layer: LayerType, position: &TilePos, mass: f32) -> bool {
+        let Some(entity) = self.entity(layer, position) else {
+            return false;
+        };
+        let Ok((_, mut tile_mass, _)) = self.tile_query.get_mut(entity) else {
+            return false;
+        };
+        if tile_mass.0 != mass {
+            tile_mass.0 = mass;
+            self.events.send(TileChanged { layer, position: *position, kind: TileChangeKind::Mass });
+        }
+        true
+    }
+
+    /// Returns `false` if there is no such tile. Unchanged values don't send events.
+    pub fn set_temperature(&mut self, layer: LayerType, position: &TilePos, temperature: f32) -> bool {
+        let Some(entity) = self.entity(layer, position) else {
+            return false;
+        };
+        let Ok((.., mut heat_cell)) = self.tile_query.get_mut(entity) else {
+            return false;
+        };
+        if heat_cell.temperature.get_temperature() != temperature {
+            heat_cell.temperature.set_temperature(temperature);
+            self.events.send(TileChanged { layer, position: *position, kind: TileChangeKind::Temperature });
+        }
+        true
+    }
+
+    /// Replaces the contents of a tile, keeping its temperature when `None`.
+    pub fn set(&mut self, layer: LayerType, position: &TilePos, element: u32, mass: f32, temperature: Option<f32>) -> bool {
+        if !self.set_element(layer, position, element) {
+            return false;
+        }
+        self.set_mass(layer, position, mass);
+        if let Some(temperature) = temperature {
+            self.set_temperature(layer, position, temperature);
+        }
+        true
+    }
+
+    pub fn set_at(&mut self, layer: LayerType, world_position: Vec2, element: u32, mass: f32, temperature: Option<f32>) -> bool {
+        match self.tile_at(layer, world_position) {
+            Some(position) => self.set(layer, &position, element, mass, temperature),
+            None => false,
+        }
+    }
+
+    /// Trades heat between something in `position` at `temperature` °C, holding `heat_capacity` kJ/K,
+    /// and the liquid in the tile or else its gas, over `conductance` kJ/K of contact. Returns the new
+    /// temperature of the thing, which keeps it in a vacuum.
+    pub fn exchange_heat(
+        &mut self,
+        element_configs: &ElementConfigs,
+        position: &TilePos,
+        temperature: f32,
+        heat_capacity: f32,
+        conductance: f32,
+    ) -> f32 {
+        let surroundings = [LayerType::Liquid, LayerType::Gas]
+            .into_iter()
+            .find_map(|layer| self.get(layer, position).filter(|tile| tile.mass > 0.0).map(|tile| (layer, tile)));
+        let Some((layer, tile)) = surroundings else {
+            return temperature;
+        };
+        let tile_capacity = element_configs.get(tile.element).map_or(0.0, |config| tile.mass * config.specific_heat);
+        let (temperature, tile_temperature) = exchange_heat(temperature, heat_capacity, tile.temperature, tile_capacity, conductance);
+        self.set_temperature(layer, position, tile_temperature);
+        temperature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::super::layer::LayerBuilder;
+    use super::*;
+
+    #[test]
+    fn test_set_and_get_tiles() {
+        let mut world = World::new();
+        world.init_resource::<Events<TileChanged>>();
+        LayerBuilder::new()
+            .with_type(LayerType::Solid)
+            .with_size(TilemapSize { x: 4, y: 3 })
+            .build(&mut world.commands());
+        world.flush();
+
+        let mut state = SystemState::<WorldTiles>::new(&mut world);
+        let mut tiles = state.get_mut(&mut world);
+        let position = TilePos { x: 3, y: 2 };
+
+        assert_eq!(tiles.map_size(), Some(UVec2::new(4, 3)));
+        assert!(tiles.set(LayerType::Solid, &position, elements::GRANITE, 1000.0, Some(30.0)));
+        assert!(!tiles.set(LayerType::Solid, &TilePos { x: 4, y: 0 }, elements::GRANITE, 1000.0, None));
+        assert!(!tiles.set(LayerType::Gas, &position, elements::OXYGEN, 1.0, None));
+        // Setting the same value again is not a change
+        assert!(tiles.set_element(LayerType::Solid, &position, elements::GRANITE));
+
+        let tile = tiles.get(LayerType::Solid, &position).unwrap();
+        assert_eq!((tile.element, tile.mass, tile.temperature), (elements::GRANITE, 1000.0, 30.0));
+        state.apply(&mut world);
+
+        let events = world.resource::<Events<TileChanged>>();
+        let kinds: Vec<_> = events.iter_current_update_events().map(|event| event.kind).collect();
+        assert_eq!(kinds, [TileChangeKind::Element, TileChangeKind::Mass, TileChangeKind::Temperature]);
+    }
+
+    #[test]
+    fn test_chunk_tiles_are_clipped_to_the_map() {
+        let position = TilePos { x: 40, y: 5 };
+        assert_eq!(chunk_of(&position), UVec2::new(1, 0));
+        assert_eq!(chunk_tiles(UVec2::new(1, 0), UVec2::new(42, 10)).count(), 10 * 10);
+    }
+}
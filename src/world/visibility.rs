@@ -0,0 +1,135 @@
+//! Shows or hides each layer on its own, from the [`InputAction::ToggleLayer`] actions or the
+//! layer panel.
+
+use bevy::prelude::*;
+
+use crate::actions::{Actions, InputAction, InputBindings, TOGGLED_LAYERS};
+use crate::states::generation::GenerationState;
+use crate::GameState;
+
+use super::layer::{Layer, LayerType};
+use super::Grid;
+
+pub struct LayerVisibilityPlugin;
+
+impl Plugin for LayerVisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GenerationState::Done), spawn_layer_panel.run_if(in_state(GameState::Playing)))
+            .add_systems(
+                Update,
+                (toggle_layer_keys, click_layer_buttons, update_layer_buttons)
+                    .chain()
+                    .run_if(in_state(GameState::Playing).and(in_state(GenerationState::Done))),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_layer_panel);
+    }
+}
+
+#[derive(Component)]
+struct LayerPanel;
+
+/// Toggles the layer with the given id.
+#[derive(Component)]
+struct LayerToggle(u32);
+
+fn toggle_layer(layer_query: &mut Query<(&Layer, &mut Visibility)>, id: u32) {
+    for (layer, mut visibility) in layer_query.iter_mut() {
+        if layer.id == id {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+fn layer_label(layer_type: LayerType) -> String {
+    format!("{layer_type:?}")
+}
+
+fn spawn_layer_panel(mut commands: Commands, bindings: Res<InputBindings>, grid_query: Query<&Grid>) {
+    let Ok(grid) = grid_query.get_single() else {
+        return;
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                top: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            LayerPanel,
+        ))
+        .with_children(|parent| {
+            for (index, layer) in grid.get_layers().iter().enumerate().take(TOGGLED_LAYERS) {
+                let key = bindings.label(InputAction::ToggleLayer(index as u8), false);
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        BackgroundColor(Color::linear_rgb(0.15, 0.15, 0.15)),
+                        LayerToggle(layer.id),
+                    ))
+                    .with_child((
+                        Text::new(format!("{key} {}", layer_label(layer.layer_type))),
+                        TextFont {
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(Color::linear_rgb(0.9, 0.9, 0.9)),
+                    ));
+            }
+        });
+}
+
+fn despawn_layer_panel(mut commands: Commands, panel_query: Query<Entity, With<LayerPanel>>) {
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn toggle_layer_keys(actions: Res<Actions>, grid_query: Query<&Grid>, mut layer_query: Query<(&Layer, &mut Visibility)>) {
+    let Ok(grid) = grid_query.get_single() else {
+        return;
+    };
+    for (index, layer) in grid.get_layers().iter().enumerate().take(TOGGLED_LAYERS) {
+        if actions.just_pressed(InputAction::ToggleLayer(index as u8)) {
+            toggle_layer(&mut layer_query, layer.id);
+        }
+    }
+}
+
+fn click_layer_buttons(
+    interaction_query: Query<(&Interaction, &LayerToggle), Changed<Interaction>>,
+    mut layer_query: Query<(&Layer, &mut Visibility)>,
+) {
+    for (interaction, toggle) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            toggle_layer(&mut layer_query, toggle.0);
+        }
+    }
+}
+
+/// Dims the buttons of hidden layers.
+fn update_layer_buttons(
+    layer_query: Query<(&Layer, &Visibility)>,
+    mut button_query: Query<(&LayerToggle, &Interaction, &mut BackgroundColor)>,
+) {
+    for (toggle, interaction, mut color) in button_query.iter_mut() {
+        let hidden = layer_query
+            .iter()
+            .any(|(layer, visibility)| layer.id == toggle.0 && *visibility == Visibility::Hidden);
+        color.0 = match (hidden, interaction) {
+            (true, _) => Color::linear_rgba(0.05, 0.05, 0.05, 0.6),
+            (false, Interaction::Hovered) => Color::linear_rgb(0.25, 0.25, 0.25),
+            (false, _) => Color::linear_rgb(0.15, 0.15, 0.15),
+        };
+    }
+}